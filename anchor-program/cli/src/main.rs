@@ -0,0 +1,728 @@
+//! `kaizen`: command-line client for the deepwork program (SOL sessions).
+
+mod rpc;
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use clap::{Parser, Subcommand};
+use deepwork_client::anchor_lang::Discriminator;
+use deepwork_client::{
+    instruction, pda, preview, state, FocusReport, FocusSession, GlobalState, SessionKey, Task,
+    UserState, UserStats,
+};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+
+use crate::rpc::RpcClient;
+
+// Sessions expired per transaction by `expire`; each takes four accounts, and
+// more would push the transaction past the packet size limit
+const EXPIRE_BATCH_SIZE: usize = 6;
+
+#[derive(Parser)]
+#[command(name = "kaizen", about = "Stake on your focus sessions", version)]
+struct Cli {
+    /// RPC endpoint
+    #[arg(
+        long,
+        short = 'u',
+        global = true,
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: String,
+
+    /// Keypair file that signs and pays for transactions
+    #[arg(
+        long,
+        short = 'k',
+        global = true,
+        default_value = "~/.config/solana/id.json"
+    )]
+    keypair: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start a focus session
+    Start {
+        /// Stake in SOL
+        #[arg(long)]
+        stake: String,
+        #[arg(long)]
+        minutes: u64,
+        /// Task description (repeat for several tasks)
+        #[arg(long = "task", required = true)]
+        tasks: Vec<String>,
+        /// Accountability partner who signs off task completions
+        #[arg(long)]
+        partner: Option<Pubkey>,
+        /// Key allowed to update tasks and complete the session for you; it pays
+        /// the fees and the epoch share's rent when it completes, so fund it
+        #[arg(long)]
+        session_key: Option<Pubkey>,
+        /// How long the session key stays valid (at most a week)
+        #[arg(long, default_value_t = 24, requires = "session_key")]
+        session_key_hours: i64,
+    },
+    /// Complete a session once its duration has passed
+    Complete {
+        session_id: u64,
+        /// Session owner, when signing with their session key
+        #[arg(long)]
+        owner: Option<Pubkey>,
+        /// Attestor's signature over the focus report, needed once an attestor is registered
+        #[arg(long, requires_all = ["focused_minutes", "distractions"])]
+        attestation: Option<Signature>,
+        /// Focused minutes in the signed report
+        #[arg(long, requires = "attestation")]
+        focused_minutes: Option<u32>,
+        /// Distraction count in the signed report
+        #[arg(long, requires = "attestation")]
+        distractions: Option<u32>,
+    },
+    /// Prove you're still focusing; send one per heartbeat interval while the session runs
+    Heartbeat {
+        session_id: u64,
+        /// Session owner, when signing with their session key
+        #[arg(long)]
+        owner: Option<Pubkey>,
+    },
+    /// Answer the spot-check that a heartbeat scheduled, while its window is open
+    AnswerChallenge {
+        session_id: u64,
+        /// Session owner, when signing with their session key
+        #[arg(long)]
+        owner: Option<Pubkey>,
+    },
+    /// Give up on an active session
+    Fail { session_id: u64 },
+    /// Mark a task of a completed session as done (or not done)
+    UpdateTask {
+        session_id: u64,
+        task_index: u8,
+        #[arg(long)]
+        undo: bool,
+        /// Session owner, when signing with their session key
+        #[arg(long)]
+        owner: Option<Pubkey>,
+    },
+    /// Claim the refund of a completed session
+    Claim { session_id: u64 },
+    /// Show protocol pools and a user's track record and sessions
+    Status {
+        /// Defaults to the keypair's address
+        #[arg(long)]
+        user: Option<Pubkey>,
+    },
+    /// Initialize global state and vaults (admin)
+    Init,
+    /// Upgrade the first deployment's global state to the current layout (admin)
+    MigrateConfig,
+    /// Upgrade a user state from the first deployment, moving its session to session 0
+    MigrateUser {
+        /// Defaults to the keypair's address
+        #[arg(long)]
+        user: Option<Pubkey>,
+    },
+    /// Withdraw from the focus pool (admin)
+    WithdrawFocus {
+        /// Amount in SOL
+        amount: String,
+        /// Defaults to the keypair's address
+        #[arg(long)]
+        recipient: Option<Pubkey>,
+    },
+    /// Withdraw from the failure pool (admin)
+    WithdrawFailure {
+        /// Amount in SOL
+        amount: String,
+        /// Defaults to the keypair's address
+        #[arg(long)]
+        recipient: Option<Pubkey>,
+    },
+    /// Check the vaults against what they owe; pauses pool withdrawals on a shortfall
+    Reconcile,
+    /// Expire one overdue session, or every overdue SOL session when none is given
+    Expire {
+        #[arg(long, requires = "session_id")]
+        user: Option<Pubkey>,
+        #[arg(long, requires = "user")]
+        session_id: Option<u64>,
+    },
+}
+
+struct App {
+    rpc: RpcClient,
+    payer: Keypair,
+}
+
+impl App {
+    fn send(&self, instructions: &[Instruction]) -> Result<()> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            blockhash,
+        );
+        let signature = self.rpc.send_and_confirm(&transaction)?;
+        println!("Signature: {signature}");
+        Ok(())
+    }
+
+    fn global_state(&self) -> Result<GlobalState> {
+        let data = self
+            .rpc
+            .get_account_data(&pda::global_state())?
+            .ok_or_else(|| anyhow!("program is not initialized"))?;
+        Ok(state::decode_global_state(&data)?)
+    }
+
+    fn user_state(&self, user: &Pubkey) -> Result<Option<UserState>> {
+        self.rpc
+            .get_account_data(&pda::user_state(user))?
+            .map(|data| Ok(state::decode_user_state(&data)?))
+            .transpose()
+    }
+
+    fn next_session_id(&self, user: &Pubkey) -> Result<u64> {
+        Ok(self
+            .user_state(user)?
+            .map_or(0, |user_state| user_state.next_session_id))
+    }
+
+    fn user_stats(&self, user: &Pubkey) -> Result<Option<UserStats>> {
+        self.rpc
+            .get_account_data(&pda::user_stats(user))?
+            .map(|data| Ok(state::decode_user_stats(&data)?))
+            .transpose()
+    }
+
+    fn focus_session(&self, user: &Pubkey, session_id: u64) -> Result<Option<FocusSession>> {
+        self.rpc
+            .get_account_data(&pda::focus_session(user, session_id))?
+            .map(|data| Ok(state::decode_focus_session(&data)?))
+            .transpose()
+    }
+
+    /// Whoever paid the session's rent, which closing it refunds.
+    fn rent_payer(&self, user: &Pubkey, session_id: u64) -> Result<Pubkey> {
+        self.focus_session(user, session_id)?
+            .map(|session| session.rent_payer)
+            .ok_or_else(|| anyhow!("session {session_id} of {user} not found"))
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let keypair_path = expand_home(&cli.keypair);
+    let payer = read_keypair_file(&keypair_path)
+        .map_err(|err| anyhow!("reading keypair {}: {err}", keypair_path.display()))?;
+    let app = App {
+        rpc: RpcClient::new(cli.url),
+        payer,
+    };
+    let me = app.payer.pubkey();
+
+    match cli.command {
+        Command::Start {
+            stake,
+            minutes,
+            tasks,
+            partner,
+            session_key,
+            session_key_hours,
+        } => {
+            let session_id = app.next_session_id(&me)?;
+            let tasks = tasks
+                .into_iter()
+                .map(|description| Task {
+                    description,
+                    completed: false,
+                })
+                .collect();
+            let session_key = session_key
+                .map(|key| {
+                    Ok::<_, anyhow::Error>(SessionKey {
+                        key,
+                        expires_at: unix_now()? + session_key_hours * 3_600,
+                    })
+                })
+                .transpose()?;
+            app.send(&[instruction::start_focus_session(
+                &me,
+                &me,
+                session_id,
+                parse_sol(&stake)?,
+                minutes,
+                tasks,
+                partner,
+                session_key,
+            )])?;
+            println!("Started session {session_id}");
+        }
+        Command::Complete {
+            session_id,
+            owner,
+            attestation,
+            focused_minutes,
+            distractions,
+        } => {
+            let user = owner.unwrap_or(me);
+            let global_state = app.global_state()?;
+            let mut instructions = Vec::new();
+            if let Some(attestor) = global_state.attestor {
+                let signature = attestation.ok_or_else(|| {
+                    anyhow!("completion needs a focus report signed by {attestor} (--attestation)")
+                })?;
+                let session = app
+                    .focus_session(&user, session_id)?
+                    .ok_or_else(|| anyhow!("session {session_id} not found"))?;
+                let report = FocusReport {
+                    user,
+                    session_start: session.start_time,
+                    focused_minutes: focused_minutes.unwrap_or_default(),
+                    distractions: distractions.unwrap_or_default(),
+                };
+                instructions.push(instruction::verify_focus_report(
+                    &attestor,
+                    &report,
+                    signature.as_array(),
+                ));
+            }
+            instructions.push(instruction::complete_focus_session(
+                &me,
+                &user,
+                session_id,
+                global_state.current_epoch,
+            ));
+            app.send(&instructions)?;
+        }
+        Command::Heartbeat { session_id, owner } => {
+            app.send(&[instruction::heartbeat(
+                &me,
+                &owner.unwrap_or(me),
+                session_id,
+            )])?;
+        }
+        Command::AnswerChallenge { session_id, owner } => {
+            app.send(&[instruction::answer_challenge(
+                &me,
+                &owner.unwrap_or(me),
+                session_id,
+            )])?;
+        }
+        Command::Fail { session_id } => {
+            let rent_payer = app.rent_payer(&me, session_id)?;
+            app.send(&[instruction::fail_focus_session(
+                &me,
+                &rent_payer,
+                session_id,
+            )])?;
+        }
+        Command::UpdateTask {
+            session_id,
+            task_index,
+            undo,
+            owner,
+        } => {
+            app.send(&[instruction::update_task(
+                &me,
+                &owner.unwrap_or(me),
+                session_id,
+                None,
+                task_index,
+                !undo,
+            )])?;
+        }
+        Command::Claim { session_id } => {
+            let rent_payer = app.rent_payer(&me, session_id)?;
+            app.send(&[instruction::claim_rewards(&me, &rent_payer, session_id)])?;
+        }
+        Command::Status { user } => status(&app, &user.unwrap_or(me))?,
+        Command::Init => {
+            app.send(&[instruction::initialize(&me)])?;
+        }
+        Command::MigrateConfig => {
+            app.send(&[instruction::migrate_config(&me)])?;
+        }
+        Command::MigrateUser { user } => {
+            app.send(&[instruction::migrate_user_state(&me, &user.unwrap_or(me))])?;
+        }
+        Command::WithdrawFocus { amount, recipient } => {
+            app.send(&[instruction::withdraw_focus_pool(
+                &me,
+                &recipient.unwrap_or(me),
+                parse_sol(&amount)?,
+            )])?;
+        }
+        Command::WithdrawFailure { amount, recipient } => {
+            app.send(&[instruction::withdraw_failure_pool(
+                &me,
+                &recipient.unwrap_or(me),
+                parse_sol(&amount)?,
+            )])?;
+        }
+        Command::Expire {
+            user: Some(user),
+            session_id: Some(session_id),
+        } => {
+            let rent_payer = app.rent_payer(&user, session_id)?;
+            app.send(&[instruction::expire_focus_session(
+                &me,
+                &user,
+                &rent_payer,
+                session_id,
+            )])?;
+        }
+        Command::Expire { .. } => expire_overdue(&app)?,
+        Command::Reconcile => reconcile(&app)?,
+    }
+
+    Ok(())
+}
+
+fn status(app: &App, user: &Pubkey) -> Result<()> {
+    let global_state = app.global_state()?;
+    println!("Focus pool:   {} SOL", format_sol(global_state.focus_pool));
+    println!(
+        "Failure pool: {} SOL",
+        format_sol(global_state.failure_pool)
+    );
+    println!(
+        "Owed:         {} SOL staked, {} SOL to claim",
+        format_sol(global_state.total_active_stake),
+        format_sol(global_state.total_pending_balance)
+    );
+    println!("Epoch:        {}", global_state.current_epoch);
+    println!();
+
+    if let Some(stats) = app.user_stats(user)? {
+        println!(
+            "Sessions:     {} started, {} completed, {} failed, {} expired",
+            stats.sessions_started,
+            stats.sessions_completed,
+            stats.sessions_failed,
+            stats.sessions_expired
+        );
+        println!(
+            "Focused:      {} min, streak {} (best {})",
+            stats.minutes_focused, stats.current_streak, stats.best_streak
+        );
+        println!(
+            "Staked:       {} SOL, {} SOL refunded, {} SOL forfeited",
+            format_sol(stats.total_staked),
+            format_sol(stats.total_refunded),
+            format_sol(stats.total_forfeited)
+        );
+        println!();
+    }
+
+    let now = unix_now()?;
+    let mut found = false;
+    for session_id in 0..app.next_session_id(user)? {
+        // settled sessions are closed
+        let Some(session) = app.focus_session(user, session_id)? else {
+            continue;
+        };
+        found = true;
+
+        let state = if session.is_active {
+            if session.is_paused() {
+                "paused"
+            } else {
+                "active"
+            }
+        } else {
+            "completed"
+        };
+        println!(
+            "Session {session_id} ({state}): {} min, {} min focused",
+            session.duration_minutes,
+            session.focused_seconds(now) / 60
+        );
+        if session.is_active {
+            println!("  Stake:   {} SOL", format_sol(session.stake_amount));
+        }
+        if let Some(challenge) = session.challenge {
+            if now < challenge.opens_at {
+                println!("  Spot-check: opens in {} s", challenge.opens_at - now);
+            } else if now <= challenge.closes_at {
+                println!(
+                    "  Spot-check: open, answer within {} s",
+                    challenge.closes_at - now
+                );
+            } else {
+                println!("  Spot-check: missed");
+            }
+        }
+        for (index, task) in session.tasks.iter().enumerate() {
+            let mark = if task.completed { "x" } else { " " };
+            println!("  [{mark}] {index}: {}", task.description);
+        }
+        if let Ok(claim) = preview::preview_claim(&session, global_state.focus_pool) {
+            println!(
+                "  Claim:   {} SOL refund, {} SOL penalty, {} SOL streak bonus",
+                format_sol(claim.refund_amount),
+                format_sol(claim.penalty_amount),
+                format_sol(claim.streak_bonus)
+            );
+        }
+    }
+    if !found {
+        println!("No open sessions for {user}");
+    }
+    Ok(())
+}
+
+fn expire_overdue(app: &App) -> Result<()> {
+    let grace_minutes = app.global_state()?.config.grace_minutes;
+    let now = unix_now()?;
+
+    let mut overdue = Vec::new();
+    for (_, data) in app
+        .rpc
+        .get_program_accounts(&deepwork_client::PROGRAM_ID, FocusSession::DISCRIMINATOR)?
+    {
+        let session = state::decode_focus_session(&data)?;
+        if !session.is_active || session.mint.is_some() {
+            continue;
+        }
+        if session.require_expirable(grace_minutes, now).is_ok() {
+            overdue.push((session.user, session.session_id, session.rent_payer));
+        }
+    }
+    if overdue.is_empty() {
+        println!("No overdue sessions");
+        return Ok(());
+    }
+
+    for batch in overdue.chunks(EXPIRE_BATCH_SIZE) {
+        app.send(&[instruction::expire_focus_sessions_batch(
+            &app.payer.pubkey(),
+            batch,
+        )])?;
+    }
+    println!("Expired {} sessions", overdue.len());
+    Ok(())
+}
+
+fn reconcile(app: &App) -> Result<()> {
+    app.send(&[instruction::reconcile()])?;
+
+    let global_state = app.global_state()?;
+    let rent = app.rpc.get_minimum_balance_for_rent_exemption(0)?;
+    let held =
+        |vault: &Pubkey| -> Result<u64> { Ok(app.rpc.get_balance(vault)?.saturating_sub(rent)) };
+    for (name, vault, recorded) in [
+        ("Vault:       ", pda::vault(), global_state.total_owed()?),
+        (
+            "Focus pool:  ",
+            pda::focus_pool_vault(),
+            global_state.focus_pool,
+        ),
+        (
+            "Failure pool:",
+            pda::failure_pool_vault(),
+            global_state.failure_pool,
+        ),
+    ] {
+        println!(
+            "{name} {} SOL held, {} SOL owed",
+            format_sol(held(&vault)?),
+            format_sol(recorded)
+        );
+    }
+    if global_state.circuit_breaker.withdrawals_paused {
+        println!("Pool withdrawals are paused");
+    }
+    Ok(())
+}
+
+/// Parses a decimal SOL amount into lamports without going through floats.
+fn parse_sol(amount: &str) -> Result<u64> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        bail!("invalid amount {amount}");
+    }
+    // nothing is smaller than a lamport, so only zeros may follow the ninth decimal
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > 9 {
+        bail!("{amount}: SOL has at most 9 decimals");
+    }
+    let too_large = || anyhow!("{amount}: amount too large");
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| too_large())?
+    };
+    let fraction: u64 = if fraction.is_empty() {
+        0
+    } else {
+        format!("{fraction:0<9}").parse()?
+    };
+    whole
+        .checked_mul(LAMPORTS_PER_SOL)
+        .and_then(|lamports| lamports.checked_add(fraction))
+        .ok_or_else(too_large)
+}
+
+fn format_sol(lamports: u64) -> String {
+    format!(
+        "{}.{:09}",
+        lamports / LAMPORTS_PER_SOL,
+        lamports % LAMPORTS_PER_SOL
+    )
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn unix_now() -> Result<i64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sol_is_exact_to_the_lamport() {
+        for (amount, lamports) in [
+            ("0", 0),
+            ("1", LAMPORTS_PER_SOL),
+            ("0.05", 50_000_000),
+            (".5", 500_000_000),
+            ("2.", 2 * LAMPORTS_PER_SOL),
+            ("0.1", 100_000_000),
+            ("0.000000001", 1),
+            ("1.999999999", 2 * LAMPORTS_PER_SOL - 1),
+            ("007.10", 7_100_000_000),
+            // trailing zeros past the ninth decimal don't change the amount
+            ("1.5000000000000", 1_500_000_000),
+            ("18446744073.709551615", u64::MAX),
+        ] {
+            assert_eq!(parse_sol(amount).unwrap(), lamports, "{amount}");
+        }
+    }
+
+    #[test]
+    fn parse_sol_rejects_what_it_cannot_represent() {
+        for amount in [
+            // below a lamport, never rounded
+            "0.0000000001",
+            "1.0000000005",
+            // negative or signed
+            "-1",
+            "-0.5",
+            "+1",
+            "1.+5",
+            "1.-5",
+            // overflowing u64 lamports
+            "18446744073.709551616",
+            "18446744074",
+            "99999999999999999999999",
+            // not a number
+            "",
+            ".",
+            "1.2.3",
+            "1,5",
+            " 1",
+            "1e9",
+            "one",
+        ] {
+            assert!(parse_sol(amount).is_err(), "{amount}");
+        }
+    }
+
+    #[test]
+    fn format_sol_prints_every_decimal() {
+        assert_eq!(format_sol(0), "0.000000000");
+        assert_eq!(format_sol(1), "0.000000001");
+        assert_eq!(format_sol(50_000_000), "0.050000000");
+        assert_eq!(format_sol(LAMPORTS_PER_SOL), "1.000000000");
+        assert_eq!(format_sol(u64::MAX), "18446744073.709551615");
+        for lamports in [0, 1, 999_999_999, 1_000_000_001, 123_456_789_012, u64::MAX] {
+            assert_eq!(parse_sol(&format_sol(lamports)).unwrap(), lamports);
+        }
+    }
+
+    /// Runs start, complete, update-task and claim against a local validator
+    /// with the program deployed, paid by the default Solana keypair.
+    #[test]
+    #[ignore = "needs a local validator with the program deployed"]
+    fn start_complete_claim_on_a_local_validator() -> Result<()> {
+        let url = std::env::var("KAIZEN_RPC_URL").unwrap_or("http://127.0.0.1:8899".into());
+        let keypair_path = expand_home("~/.config/solana/id.json");
+        let payer = read_keypair_file(&keypair_path)
+            .map_err(|err| anyhow!("reading keypair {}: {err}", keypair_path.display()))?;
+        let app = App {
+            rpc: RpcClient::new(url),
+            payer,
+        };
+        let me = app.payer.pubkey();
+        if app.global_state().is_err() {
+            app.send(&[instruction::initialize(&me)])?;
+        }
+        let global_state = app.global_state()?;
+        let completed_before = app
+            .user_stats(&me)?
+            .map_or(0, |stats| stats.sessions_completed);
+
+        // no longer than the grace window, so it can complete straight away
+        let session_id = app.next_session_id(&me)?;
+        let stake = parse_sol("0.05")?;
+        app.send(&[instruction::start_focus_session(
+            &me,
+            &me,
+            session_id,
+            stake,
+            global_state.config.grace_minutes.max(1),
+            vec![Task {
+                description: "Smoke test".to_string(),
+                completed: false,
+            }],
+            None,
+            None,
+        )])?;
+        let session = app
+            .focus_session(&me, session_id)?
+            .ok_or_else(|| anyhow!("session {session_id} was not created"))?;
+        assert!(session.is_active);
+
+        app.send(&[instruction::complete_focus_session(
+            &me,
+            &me,
+            session_id,
+            global_state.current_epoch,
+        )])?;
+        app.send(&[instruction::update_task(
+            &me, &me, session_id, None, 0, true,
+        )])?;
+        let balance_before = app.rpc.get_balance(&me)?;
+        app.send(&[instruction::claim_rewards(&me, &me, session_id)])?;
+
+        assert!(app.focus_session(&me, session_id)?.is_none());
+        assert!(app.rpc.get_balance(&me)? > balance_before);
+        let stats = app.user_stats(&me)?.expect("user stats");
+        assert_eq!(stats.sessions_completed, completed_before + 1);
+        Ok(())
+    }
+}
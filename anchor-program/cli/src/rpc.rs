@@ -0,0 +1,141 @@
+//! Minimal JSON-RPC client for the handful of calls the CLI needs.
+
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use deepwork_client::rpc::JsonRpc;
+use serde_json::{json, Value};
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct RpcClient {
+    rpc: JsonRpc,
+}
+
+impl RpcClient {
+    pub fn new(url: String) -> Self {
+        RpcClient {
+            rpc: JsonRpc::new(url),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        self.rpc.call(method, params)
+    }
+
+    pub fn get_account_data(&self, pubkey: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let result = self.call(
+            "getAccountInfo",
+            json!([pubkey.to_string(), { "encoding": "base64", "commitment": "confirmed" }]),
+        )?;
+        if result["value"].is_null() {
+            return Ok(None);
+        }
+        decode_data(&result["value"]["data"]).map(Some)
+    }
+
+    pub fn get_balance(&self, pubkey: &Pubkey) -> Result<u64> {
+        let result = self.call(
+            "getBalance",
+            json!([pubkey.to_string(), { "commitment": "confirmed" }]),
+        )?;
+        result["value"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("unexpected getBalance response"))
+    }
+
+    pub fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> Result<u64> {
+        self.call("getMinimumBalanceForRentExemption", json!([data_len]))?
+            .as_u64()
+            .ok_or_else(|| anyhow!("unexpected getMinimumBalanceForRentExemption response"))
+    }
+
+    /// Accounts owned by `program_id` whose data starts with `discriminator`.
+    pub fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        discriminator: &[u8],
+    ) -> Result<Vec<(Pubkey, Vec<u8>)>> {
+        let filter = json!({
+            "memcmp": {
+                "offset": 0,
+                "bytes": BASE64_STANDARD.encode(discriminator),
+                "encoding": "base64",
+            }
+        });
+        let result = self.call(
+            "getProgramAccounts",
+            json!([program_id.to_string(), {
+                "encoding": "base64",
+                "commitment": "confirmed",
+                "filters": [filter],
+            }]),
+        )?;
+        result
+            .as_array()
+            .ok_or_else(|| anyhow!("unexpected getProgramAccounts response"))?
+            .iter()
+            .map(|entry| {
+                let pubkey = Pubkey::from_str(entry["pubkey"].as_str().unwrap_or_default())?;
+                Ok((pubkey, decode_data(&entry["account"]["data"])?))
+            })
+            .collect()
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<Hash> {
+        let result = self.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))?;
+        let blockhash = result["value"]["blockhash"]
+            .as_str()
+            .ok_or_else(|| anyhow!("unexpected getLatestBlockhash response"))?;
+        Ok(Hash::from_str(blockhash)?)
+    }
+
+    pub fn send_and_confirm(&self, transaction: &Transaction) -> Result<Signature> {
+        let wire = bincode::serialize(transaction)?;
+        let result = self.call(
+            "sendTransaction",
+            json!([BASE64_STANDARD.encode(wire), {
+                "encoding": "base64",
+                "preflightCommitment": "confirmed",
+            }]),
+        )?;
+        let signature = Signature::from_str(
+            result
+                .as_str()
+                .ok_or_else(|| anyhow!("unexpected sendTransaction response"))?,
+        )?;
+
+        let started = Instant::now();
+        while started.elapsed() < CONFIRM_TIMEOUT {
+            let result = self.call("getSignatureStatuses", json!([[signature.to_string()]]))?;
+            let status = &result["value"][0];
+            if !status.is_null() {
+                if !status["err"].is_null() {
+                    bail!("transaction {signature} failed: {}", status["err"]);
+                }
+                if matches!(
+                    status["confirmationStatus"].as_str(),
+                    Some("confirmed") | Some("finalized")
+                ) {
+                    return Ok(signature);
+                }
+            }
+            sleep(Duration::from_millis(500));
+        }
+        bail!("transaction {signature} was not confirmed in time")
+    }
+}
+
+fn decode_data(data: &Value) -> Result<Vec<u8>> {
+    let encoded = data[0]
+        .as_str()
+        .ok_or_else(|| anyhow!("unexpected account data encoding"))?;
+    Ok(BASE64_STANDARD.decode(encoded)?)
+}
@@ -0,0 +1,47 @@
+//! Decoders for the program's Anchor events.
+//!
+//! `emit!` logs each event as a `Program data:` line holding its discriminator
+//! followed by the borsh-encoded fields; [`decode_event`] takes those bytes
+//! after base64 decoding.
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+
+use crate::{
+    EmergencyWithdrawn, EpochRewardsClaimed, Initialized, PoolWithdrawn, Reconciled,
+    RewardsClaimed, SessionCompleted, SessionExpired, SessionFailed, SessionStarted, TaskUpdated,
+};
+
+pub enum Event {
+    Initialized(Initialized),
+    SessionStarted(SessionStarted),
+    SessionCompleted(SessionCompleted),
+    SessionFailed(SessionFailed),
+    SessionExpired(SessionExpired),
+    TaskUpdated(TaskUpdated),
+    RewardsClaimed(RewardsClaimed),
+    EmergencyWithdrawn(EmergencyWithdrawn),
+    EpochRewardsClaimed(EpochRewardsClaimed),
+    PoolWithdrawn(PoolWithdrawn),
+    Reconciled(Reconciled),
+}
+
+fn parse<T: Discriminator + AnchorDeserialize>(data: &[u8]) -> Option<T> {
+    let mut fields = data.strip_prefix(T::DISCRIMINATOR)?;
+    T::deserialize(&mut fields).ok()
+}
+
+/// `None` for data that isn't one of this program's events.
+pub fn decode_event(data: &[u8]) -> Option<Event> {
+    parse(data)
+        .map(Event::Initialized)
+        .or_else(|| parse(data).map(Event::SessionStarted))
+        .or_else(|| parse(data).map(Event::SessionCompleted))
+        .or_else(|| parse(data).map(Event::SessionFailed))
+        .or_else(|| parse(data).map(Event::SessionExpired))
+        .or_else(|| parse(data).map(Event::TaskUpdated))
+        .or_else(|| parse(data).map(Event::RewardsClaimed))
+        .or_else(|| parse(data).map(Event::EmergencyWithdrawn))
+        .or_else(|| parse(data).map(Event::EpochRewardsClaimed))
+        .or_else(|| parse(data).map(Event::PoolWithdrawn))
+        .or_else(|| parse(data).map(Event::Reconciled))
+}
@@ -0,0 +1,738 @@
+//! Typed builders for every deepwork instruction.
+//!
+//! Session instructions take the `session_id` of the focus_session PDA; a new
+//! session uses the user's `UserState::next_session_id` (0 for a first session).
+
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::system_program;
+use anchor_lang::solana_program::sysvar::instructions as instructions_sysvar;
+use anchor_lang::solana_program::sysvar::slot_hashes;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_program::{accounts, instruction as ix};
+use solana_ed25519_program::new_ed25519_instruction_with_signature;
+
+use crate::{
+    pda, CircuitBreaker, Config, FocusReport, RefundCurve, SessionKey, StreakTier, Task, PROGRAM_ID,
+};
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+pub fn initialize(authority: &Pubkey) -> Instruction {
+    build(
+        accounts::Initialize {
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            focus_pool_vault: pda::focus_pool_vault(),
+            failure_pool_vault: pda::failure_pool_vault(),
+            epoch: pda::epoch(0),
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        ix::Initialize {},
+    )
+}
+
+/// `payer` covers the rent of the new accounts and gets the session's back on close;
+/// pass `user` unless a relayer sponsors it.
+#[allow(clippy::too_many_arguments)]
+pub fn start_focus_session(
+    payer: &Pubkey,
+    user: &Pubkey,
+    session_id: u64,
+    stake_amount: u64,
+    duration_minutes: u64,
+    tasks: Vec<Task>,
+    partner: Option<Pubkey>,
+    session_key: Option<SessionKey>,
+) -> Instruction {
+    build(
+        accounts::StartFocusSession {
+            user_state: pda::user_state(user),
+            focus_session: pda::focus_session(user, session_id),
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            focus_pool_vault: pda::focus_pool_vault(),
+            user: *user,
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        ix::StartFocusSession {
+            stake_amount,
+            duration_minutes,
+            tasks,
+            partner,
+            session_key,
+        },
+    )
+}
+
+/// Legacy start with a single default task.
+pub fn start_focus_session_v1(
+    payer: &Pubkey,
+    user: &Pubkey,
+    session_id: u64,
+    stake_amount: u64,
+    duration_minutes: u64,
+) -> Instruction {
+    build(
+        accounts::StartFocusSession {
+            user_state: pda::user_state(user),
+            focus_session: pda::focus_session(user, session_id),
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            focus_pool_vault: pda::focus_pool_vault(),
+            user: *user,
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        ix::StartFocusSessionV1 {
+            stake_amount,
+            duration_minutes,
+        },
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_focus_session_token(
+    payer: &Pubkey,
+    user: &Pubkey,
+    session_id: u64,
+    mint: &Pubkey,
+    user_token_account: &Pubkey,
+    token_program: &Pubkey,
+    stake_amount: u64,
+    duration_minutes: u64,
+    tasks: Vec<Task>,
+    partner: Option<Pubkey>,
+    session_key: Option<SessionKey>,
+) -> Instruction {
+    build(
+        accounts::StartFocusSessionToken {
+            user_state: pda::user_state(user),
+            focus_session: pda::focus_session(user, session_id),
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            mint_config: pda::mint_config(mint),
+            mint: *mint,
+            user_token_account: *user_token_account,
+            token_vault: pda::token_vault(mint),
+            token_focus_pool: pda::token_focus_pool(mint),
+            user: *user,
+            payer: *payer,
+            token_program: *token_program,
+            system_program: system_program::ID,
+        },
+        ix::StartFocusSessionToken {
+            stake_amount,
+            duration_minutes,
+            tasks,
+            partner,
+            session_key,
+        },
+    )
+}
+
+/// `current_epoch` is `GlobalState::current_epoch`, the epoch the session earns shares in.
+/// `signer` is the owner or the session's unexpired session key, and pays for the epoch share.
+pub fn complete_focus_session(
+    signer: &Pubkey,
+    user: &Pubkey,
+    session_id: u64,
+    current_epoch: u64,
+) -> Instruction {
+    build(
+        accounts::CompleteFocusSession {
+            focus_session: pda::focus_session(user, session_id),
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            epoch: pda::epoch(current_epoch),
+            epoch_share: pda::epoch_share(current_epoch, user),
+            user: *user,
+            signer: *signer,
+            system_program: system_program::ID,
+            instructions: instructions_sysvar::ID,
+        },
+        ix::CompleteFocusSession {},
+    )
+}
+
+/// Ed25519 check of the attestor's `signature` over `report`; send it right before
+/// `complete_focus_session` while an attestor is registered.
+pub fn verify_focus_report(
+    attestor: &Pubkey,
+    report: &FocusReport,
+    signature: &[u8; 64],
+) -> Instruction {
+    new_ed25519_instruction_with_signature(&report.message(), signature, &attestor.to_bytes())
+}
+
+/// Legacy completion that returns the stake immediately and closes the session.
+pub fn complete_focus_session_v1(
+    user: &Pubkey,
+    rent_payer: &Pubkey,
+    session_id: u64,
+) -> Instruction {
+    build(
+        accounts::CompleteFocusSessionClose {
+            focus_session: pda::focus_session(user, session_id),
+            rent_payer: *rent_payer,
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            user: *user,
+            system_program: system_program::ID,
+        },
+        ix::CompleteFocusSessionV1 {},
+    )
+}
+
+pub fn fail_focus_session(user: &Pubkey, rent_payer: &Pubkey, session_id: u64) -> Instruction {
+    build(
+        accounts::FailFocusSession {
+            focus_session: pda::focus_session(user, session_id),
+            rent_payer: *rent_payer,
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            failure_pool_vault: pda::failure_pool_vault(),
+            user: *user,
+            system_program: system_program::ID,
+        },
+        ix::FailFocusSession {},
+    )
+}
+
+pub fn fail_focus_session_token(
+    user: &Pubkey,
+    rent_payer: &Pubkey,
+    session_id: u64,
+    mint: &Pubkey,
+    user_token_account: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::FailFocusSessionToken {
+            focus_session: pda::focus_session(user, session_id),
+            rent_payer: *rent_payer,
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            mint_config: pda::mint_config(mint),
+            mint: *mint,
+            user_token_account: *user_token_account,
+            token_vault: pda::token_vault(mint),
+            token_failure_pool: pda::token_failure_pool(mint),
+            user: *user,
+            token_program: *token_program,
+        },
+        ix::FailFocusSessionToken {},
+    )
+}
+
+pub fn expire_focus_session(
+    keeper: &Pubkey,
+    user: &Pubkey,
+    rent_payer: &Pubkey,
+    session_id: u64,
+) -> Instruction {
+    build(
+        accounts::ExpireFocusSession {
+            focus_session: pda::focus_session(user, session_id),
+            rent_payer: *rent_payer,
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            failure_pool_vault: pda::failure_pool_vault(),
+            user: *user,
+            keeper: *keeper,
+            system_program: system_program::ID,
+        },
+        ix::ExpireFocusSession {},
+    )
+}
+
+/// Expires many SOL sessions at once; `sessions` are `(user, session_id, rent_payer)` triples.
+pub fn expire_focus_sessions_batch(
+    keeper: &Pubkey,
+    sessions: &[(Pubkey, u64, Pubkey)],
+) -> Instruction {
+    let mut instruction = build(
+        accounts::ExpireFocusSessionsBatch {
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            failure_pool_vault: pda::failure_pool_vault(),
+            keeper: *keeper,
+            system_program: system_program::ID,
+        },
+        ix::ExpireFocusSessionsBatch {},
+    );
+    for (user, session_id, rent_payer) in sessions {
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(*user, false));
+        instruction.accounts.push(AccountMeta::new(
+            pda::focus_session(user, *session_id),
+            false,
+        ));
+        instruction
+            .accounts
+            .push(AccountMeta::new(pda::user_stats(user), false));
+        instruction
+            .accounts
+            .push(AccountMeta::new(*rent_payer, false));
+    }
+    instruction
+}
+
+pub fn expire_focus_session_token(
+    keeper: &Pubkey,
+    keeper_token_account: &Pubkey,
+    user: &Pubkey,
+    rent_payer: &Pubkey,
+    session_id: u64,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::ExpireFocusSessionToken {
+            focus_session: pda::focus_session(user, session_id),
+            rent_payer: *rent_payer,
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            mint_config: pda::mint_config(mint),
+            mint: *mint,
+            token_vault: pda::token_vault(mint),
+            token_failure_pool: pda::token_failure_pool(mint),
+            keeper_token_account: *keeper_token_account,
+            user: *user,
+            keeper: *keeper,
+            token_program: *token_program,
+        },
+        ix::ExpireFocusSessionToken {},
+    )
+}
+
+/// `signer` is the owner or the session's unexpired session key.
+pub fn heartbeat(signer: &Pubkey, user: &Pubkey, session_id: u64) -> Instruction {
+    build(
+        accounts::Heartbeat {
+            focus_session: pda::focus_session(user, session_id),
+            user: *user,
+            signer: *signer,
+            slot_hashes: slot_hashes::ID,
+        },
+        ix::Heartbeat {},
+    )
+}
+
+/// `signer` is the owner or the session's unexpired session key.
+pub fn answer_challenge(signer: &Pubkey, user: &Pubkey, session_id: u64) -> Instruction {
+    build(
+        accounts::AnswerChallenge {
+            focus_session: pda::focus_session(user, session_id),
+            user: *user,
+            signer: *signer,
+        },
+        ix::AnswerChallenge {},
+    )
+}
+
+pub fn pause_focus_session(user: &Pubkey, session_id: u64) -> Instruction {
+    build(
+        accounts::PauseFocusSession {
+            focus_session: pda::focus_session(user, session_id),
+            user: *user,
+        },
+        ix::PauseFocusSession {},
+    )
+}
+
+pub fn resume_focus_session(user: &Pubkey, session_id: u64) -> Instruction {
+    build(
+        accounts::ResumeFocusSession {
+            focus_session: pda::focus_session(user, session_id),
+            user: *user,
+        },
+        ix::ResumeFocusSession {},
+    )
+}
+
+/// `partner` co-signs to record a completion directly on a partnered session.
+/// `signer` is the owner or the session's unexpired session key.
+pub fn update_task(
+    signer: &Pubkey,
+    user: &Pubkey,
+    session_id: u64,
+    partner: Option<Pubkey>,
+    task_index: u8,
+    completed: bool,
+) -> Instruction {
+    build(
+        accounts::UpdateTask {
+            focus_session: pda::focus_session(user, session_id),
+            user: *user,
+            signer: *signer,
+            partner,
+        },
+        ix::UpdateTask {
+            task_index,
+            completed,
+        },
+    )
+}
+
+pub fn review_task(
+    partner: &Pubkey,
+    user: &Pubkey,
+    session_id: u64,
+    task_index: u8,
+    approved: bool,
+) -> Instruction {
+    build(
+        accounts::ReviewTask {
+            focus_session: pda::focus_session(user, session_id),
+            user: *user,
+            partner: *partner,
+        },
+        ix::ReviewTask {
+            task_index,
+            approved,
+        },
+    )
+}
+
+pub fn claim_rewards(user: &Pubkey, rent_payer: &Pubkey, session_id: u64) -> Instruction {
+    build(
+        accounts::ClaimRewards {
+            focus_session: pda::focus_session(user, session_id),
+            rent_payer: *rent_payer,
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            focus_pool_vault: pda::focus_pool_vault(),
+            failure_pool_vault: pda::failure_pool_vault(),
+            user: *user,
+            system_program: system_program::ID,
+        },
+        ix::ClaimRewards {},
+    )
+}
+
+pub fn claim_rewards_token(
+    user: &Pubkey,
+    rent_payer: &Pubkey,
+    session_id: u64,
+    mint: &Pubkey,
+    user_token_account: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::ClaimRewardsToken {
+            focus_session: pda::focus_session(user, session_id),
+            rent_payer: *rent_payer,
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            mint_config: pda::mint_config(mint),
+            mint: *mint,
+            user_token_account: *user_token_account,
+            token_vault: pda::token_vault(mint),
+            token_failure_pool: pda::token_failure_pool(mint),
+            user: *user,
+            token_program: *token_program,
+        },
+        ix::ClaimRewardsToken {},
+    )
+}
+
+pub fn emergency_withdraw(user: &Pubkey, rent_payer: &Pubkey, session_id: u64) -> Instruction {
+    build(
+        accounts::EmergencyWithdraw {
+            focus_session: pda::focus_session(user, session_id),
+            rent_payer: *rent_payer,
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            user: *user,
+            system_program: system_program::ID,
+        },
+        ix::EmergencyWithdraw {},
+    )
+}
+
+pub fn emergency_withdraw_token(
+    user: &Pubkey,
+    rent_payer: &Pubkey,
+    session_id: u64,
+    mint: &Pubkey,
+    user_token_account: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::EmergencyWithdrawToken {
+            focus_session: pda::focus_session(user, session_id),
+            rent_payer: *rent_payer,
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            mint_config: pda::mint_config(mint),
+            mint: *mint,
+            user_token_account: *user_token_account,
+            token_vault: pda::token_vault(mint),
+            user: *user,
+            token_program: *token_program,
+        },
+        ix::EmergencyWithdrawToken {},
+    )
+}
+
+/// `current_epoch` is `GlobalState::current_epoch`, the epoch being closed.
+pub fn close_epoch(payer: &Pubkey, current_epoch: u64) -> Instruction {
+    build(
+        accounts::CloseEpoch {
+            global_state: pda::global_state(),
+            epoch: pda::epoch(current_epoch),
+            next_epoch: pda::epoch(current_epoch + 1),
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        ix::CloseEpoch {},
+    )
+}
+
+pub fn claim_epoch_rewards(user: &Pubkey, epoch_id: u64) -> Instruction {
+    build(
+        accounts::ClaimEpochRewards {
+            global_state: pda::global_state(),
+            epoch: pda::epoch(epoch_id),
+            epoch_share: pda::epoch_share(epoch_id, user),
+            failure_pool_vault: pda::failure_pool_vault(),
+            user: *user,
+            system_program: system_program::ID,
+        },
+        ix::ClaimEpochRewards {},
+    )
+}
+
+pub fn propose_authority(authority: &Pubkey, new_authority: Option<Pubkey>) -> Instruction {
+    build(
+        accounts::ProposeAuthority {
+            global_state: pda::global_state(),
+            authority: *authority,
+        },
+        ix::ProposeAuthority { new_authority },
+    )
+}
+
+pub fn accept_authority(new_authority: &Pubkey) -> Instruction {
+    build(
+        accounts::AcceptAuthority {
+            global_state: pda::global_state(),
+            new_authority: *new_authority,
+        },
+        ix::AcceptAuthority {},
+    )
+}
+
+pub fn update_config(authority: &Pubkey, config: Config) -> Instruction {
+    build(
+        accounts::UpdateConfig {
+            global_state: pda::global_state(),
+            authority: *authority,
+        },
+        ix::UpdateConfig { config },
+    )
+}
+
+pub fn migrate_config(authority: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateConfig {
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        ix::MigrateConfig {},
+    )
+}
+
+pub fn migrate_user_state(payer: &Pubkey, user: &Pubkey) -> Instruction {
+    build(
+        accounts::MigrateUserState {
+            user_state: pda::user_state(user),
+            focus_session: pda::focus_session(user, 0),
+            user_stats: pda::user_stats(user),
+            global_state: pda::global_state(),
+            user: *user,
+            payer: *payer,
+            system_program: system_program::ID,
+        },
+        ix::MigrateUserState {},
+    )
+}
+
+pub fn set_refund_curve(authority: &Pubkey, refund_curve: RefundCurve) -> Instruction {
+    build(
+        accounts::SetRefundCurve {
+            global_state: pda::global_state(),
+            authority: *authority,
+        },
+        ix::SetRefundCurve { refund_curve },
+    )
+}
+
+pub fn set_streak_tiers(authority: &Pubkey, streak_tiers: Vec<StreakTier>) -> Instruction {
+    build(
+        accounts::SetStreakTiers {
+            global_state: pda::global_state(),
+            authority: *authority,
+        },
+        ix::SetStreakTiers { streak_tiers },
+    )
+}
+
+pub fn set_attestor(authority: &Pubkey, attestor: Option<Pubkey>) -> Instruction {
+    build(
+        accounts::SetAttestor {
+            global_state: pda::global_state(),
+            authority: *authority,
+        },
+        ix::SetAttestor { attestor },
+    )
+}
+
+pub fn set_circuit_breaker(authority: &Pubkey, circuit_breaker: CircuitBreaker) -> Instruction {
+    build(
+        accounts::SetCircuitBreaker {
+            global_state: pda::global_state(),
+            authority: *authority,
+        },
+        ix::SetCircuitBreaker { circuit_breaker },
+    )
+}
+
+pub fn withdraw_focus_pool(authority: &Pubkey, recipient: &Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::WithdrawFocusPool {
+            global_state: pda::global_state(),
+            focus_pool_vault: pda::focus_pool_vault(),
+            recipient: *recipient,
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        ix::WithdrawFocusPool { amount },
+    )
+}
+
+pub fn withdraw_failure_pool(authority: &Pubkey, recipient: &Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::WithdrawFailurePool {
+            global_state: pda::global_state(),
+            failure_pool_vault: pda::failure_pool_vault(),
+            recipient: *recipient,
+            authority: *authority,
+            system_program: system_program::ID,
+        },
+        ix::WithdrawFailurePool { amount },
+    )
+}
+
+/// Permissionless solvency check; needs no signer beyond the fee payer.
+pub fn reconcile() -> Instruction {
+    build(
+        accounts::Reconcile {
+            global_state: pda::global_state(),
+            vault: pda::vault(),
+            focus_pool_vault: pda::focus_pool_vault(),
+            failure_pool_vault: pda::failure_pool_vault(),
+        },
+        ix::Reconcile {},
+    )
+}
+
+pub fn allow_mint(
+    authority: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    min_stake: u64,
+) -> Instruction {
+    build(
+        accounts::AllowMint {
+            global_state: pda::global_state(),
+            mint_config: pda::mint_config(mint),
+            mint: *mint,
+            token_vault: pda::token_vault(mint),
+            token_focus_pool: pda::token_focus_pool(mint),
+            token_failure_pool: pda::token_failure_pool(mint),
+            authority: *authority,
+            token_program: *token_program,
+            system_program: system_program::ID,
+        },
+        ix::AllowMint { min_stake },
+    )
+}
+
+pub fn update_mint_config(
+    authority: &Pubkey,
+    mint: &Pubkey,
+    enabled: bool,
+    min_stake: u64,
+) -> Instruction {
+    build(
+        accounts::UpdateMintConfig {
+            global_state: pda::global_state(),
+            mint_config: pda::mint_config(mint),
+            authority: *authority,
+        },
+        ix::UpdateMintConfig { enabled, min_stake },
+    )
+}
+
+pub fn withdraw_focus_pool_token(
+    authority: &Pubkey,
+    mint: &Pubkey,
+    recipient_token_account: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    build(
+        accounts::WithdrawFocusPoolToken {
+            global_state: pda::global_state(),
+            mint_config: pda::mint_config(mint),
+            mint: *mint,
+            token_focus_pool: pda::token_focus_pool(mint),
+            recipient_token_account: *recipient_token_account,
+            authority: *authority,
+            token_program: *token_program,
+        },
+        ix::WithdrawFocusPoolToken { amount },
+    )
+}
+
+pub fn withdraw_failure_pool_token(
+    authority: &Pubkey,
+    mint: &Pubkey,
+    recipient_token_account: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    build(
+        accounts::WithdrawFailurePoolToken {
+            global_state: pda::global_state(),
+            mint_config: pda::mint_config(mint),
+            mint: *mint,
+            token_failure_pool: pda::token_failure_pool(mint),
+            recipient_token_account: *recipient_token_account,
+            authority: *authority,
+            token_program: *token_program,
+        },
+        ix::WithdrawFailurePoolToken { amount },
+    )
+}
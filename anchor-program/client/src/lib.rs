@@ -0,0 +1,22 @@
+//! Rust client for the deepwork program: PDA helpers, instruction builders,
+//! account and event decoders and claim previews.
+
+pub mod events;
+pub mod instruction;
+pub mod pda;
+pub mod preview;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod state;
+
+pub use anchor_lang;
+pub use anchor_program::ID as PROGRAM_ID;
+pub use anchor_program::{
+    Challenge, CircuitBreaker, Config, EpochShare, FocusReport, FocusSession, GlobalState,
+    MintConfig, RefundCurve, RefundStep, RewardEpoch, SessionKey, StreakTier, Task, UserState,
+    UserStats,
+};
+pub use anchor_program::{
+    EmergencyWithdrawn, EpochRewardsClaimed, Initialized, PoolKind, PoolWithdrawn, Reconciled,
+    RewardsClaimed, SessionCompleted, SessionExpired, SessionFailed, SessionStarted, TaskUpdated,
+};
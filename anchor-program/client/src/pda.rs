@@ -0,0 +1,62 @@
+//! Program-derived addresses, using the same seeds as the program.
+
+use anchor_lang::prelude::Pubkey;
+
+use crate::PROGRAM_ID;
+
+fn find(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &PROGRAM_ID).0
+}
+
+pub fn global_state() -> Pubkey {
+    find(&[b"global_state"])
+}
+
+/// Escrow holding active and pending SOL stakes.
+pub fn vault() -> Pubkey {
+    find(&[b"vault", global_state().as_ref()])
+}
+
+pub fn focus_pool_vault() -> Pubkey {
+    find(&[b"focus_pool_vault", global_state().as_ref()])
+}
+
+pub fn failure_pool_vault() -> Pubkey {
+    find(&[b"failure_pool_vault", global_state().as_ref()])
+}
+
+pub fn user_state(user: &Pubkey) -> Pubkey {
+    find(&[b"user_state", user.as_ref()])
+}
+
+pub fn user_stats(user: &Pubkey) -> Pubkey {
+    find(&[b"user_stats", user.as_ref()])
+}
+
+pub fn focus_session(user: &Pubkey, session_id: u64) -> Pubkey {
+    find(&[b"focus_session", user.as_ref(), &session_id.to_le_bytes()])
+}
+
+pub fn epoch(epoch_id: u64) -> Pubkey {
+    find(&[b"epoch", &epoch_id.to_le_bytes()])
+}
+
+pub fn epoch_share(epoch_id: u64, user: &Pubkey) -> Pubkey {
+    find(&[b"epoch_share", &epoch_id.to_le_bytes(), user.as_ref()])
+}
+
+pub fn mint_config(mint: &Pubkey) -> Pubkey {
+    find(&[b"mint_config", mint.as_ref()])
+}
+
+pub fn token_vault(mint: &Pubkey) -> Pubkey {
+    find(&[b"token_vault", mint.as_ref()])
+}
+
+pub fn token_focus_pool(mint: &Pubkey) -> Pubkey {
+    find(&[b"token_focus_pool", mint.as_ref()])
+}
+
+pub fn token_failure_pool(mint: &Pubkey) -> Pubkey {
+    find(&[b"token_failure_pool", mint.as_ref()])
+}
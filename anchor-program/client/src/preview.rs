@@ -0,0 +1,44 @@
+//! Payout previews computed with the program's own settlement math.
+
+use anchor_lang::Result;
+
+use crate::{EpochShare, FocusSession, RefundCurve, RewardEpoch};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClaimPreview {
+    pub refund_amount: u64,  // Paid to the user by claim_rewards
+    pub penalty_amount: u64, // Moved to the failure pool
+    pub streak_bonus: u64,   // Paid from the focus pool on top of the refund
+    pub completed_tasks: u8,
+    pub total_tasks: u8,
+}
+
+/// What `claim_rewards` would pay out for a completed session right now,
+/// given the focus pool total (`GlobalState::focus_pool` for SOL sessions).
+pub fn preview_claim(focus_session: &FocusSession, focus_pool: u64) -> Result<ClaimPreview> {
+    let (refund_amount, penalty_amount) = focus_session.claim_split()?;
+    Ok(ClaimPreview {
+        refund_amount,
+        penalty_amount,
+        streak_bonus: focus_session.streak_bonus(refund_amount)?.min(focus_pool),
+        completed_tasks: focus_session.completed_tasks(),
+        total_tasks: focus_session.tasks.len() as u8,
+    })
+}
+
+/// Refund `fail_focus_session` would pay at `now` under `refund_curve`.
+pub fn preview_early_exit(
+    focus_session: &FocusSession,
+    refund_curve: &RefundCurve,
+    now: i64,
+) -> Result<u64> {
+    focus_session.early_exit_refund(refund_curve, now)
+}
+
+/// Reward `claim_epoch_rewards` would pay for a closed epoch.
+pub fn preview_epoch_reward(epoch: &RewardEpoch, epoch_share: &EpochShare) -> Result<u64> {
+    if !epoch.closed {
+        return Ok(0);
+    }
+    epoch.reward_for(epoch_share.shares)
+}
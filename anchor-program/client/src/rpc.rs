@@ -0,0 +1,45 @@
+//! JSON-RPC transport shared by the CLI and the indexer, which each add the
+//! typed calls they need on top. Behind the `rpc` feature.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+
+pub struct JsonRpc {
+    url: String,
+}
+
+impl JsonRpc {
+    pub fn new(url: String) -> Self {
+        JsonRpc { url }
+    }
+
+    /// Sends one request and returns its `result`, or fails with the RPC error
+    /// and, for simulation failures, the program logs.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response: Value = ureq::post(&self.url)
+            .send_json(request)
+            .with_context(|| format!("{method} request to {}", self.url))?
+            .into_json()?;
+
+        if let Some(error) = response.get("error") {
+            bail!("{method} failed: {}{}", error["message"], logs(error));
+        }
+        Ok(response["result"].clone())
+    }
+}
+
+fn logs(error: &Value) -> String {
+    error
+        .pointer("/data/logs")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|log| format!("\n  {}", log.as_str().unwrap_or_default()))
+        .collect()
+}
@@ -0,0 +1,278 @@
+//! Decoders for the program's accounts from raw account data.
+
+use anchor_lang::{AccountDeserialize, Result};
+
+use crate::{EpochShare, FocusSession, GlobalState, MintConfig, RewardEpoch, UserState, UserStats};
+
+fn decode<T: AccountDeserialize>(mut data: &[u8]) -> Result<T> {
+    // checks the discriminator before deserializing
+    T::try_deserialize(&mut data)
+}
+
+pub fn decode_global_state(data: &[u8]) -> Result<GlobalState> {
+    decode(data)
+}
+
+pub fn decode_user_state(data: &[u8]) -> Result<UserState> {
+    decode(data)
+}
+
+pub fn decode_user_stats(data: &[u8]) -> Result<UserStats> {
+    decode(data)
+}
+
+pub fn decode_focus_session(data: &[u8]) -> Result<FocusSession> {
+    decode(data)
+}
+
+pub fn decode_mint_config(data: &[u8]) -> Result<MintConfig> {
+    decode(data)
+}
+
+pub fn decode_reward_epoch(data: &[u8]) -> Result<RewardEpoch> {
+    decode(data)
+}
+
+pub fn decode_epoch_share(data: &[u8]) -> Result<EpochShare> {
+    decode(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Challenge, CircuitBreaker, Config, RefundCurve, RefundStep, StreakTier, Task};
+    use anchor_lang::prelude::Pubkey;
+    use anchor_lang::{AccountSerialize, Discriminator, Space};
+    use anchor_program::PauseInterval;
+
+    /// Account data as the program leaves it: discriminator, fields, then zero
+    /// padding up to the allocated space.
+    fn account_data<T: AccountSerialize + Space>(account: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data.resize(8 + T::INIT_SPACE, 0);
+        data
+    }
+
+    /// Decodes the account back and checks it re-encodes to the same bytes.
+    fn round_trip<T: AccountSerialize + Space>(account: &T, decode: fn(&[u8]) -> Result<T>) -> T {
+        let data = account_data(account);
+        let decoded = decode(&data).unwrap();
+        assert_eq!(account_data(&decoded), data);
+        decoded
+    }
+
+    fn global_state() -> GlobalState {
+        GlobalState {
+            authority: Pubkey::new_unique(),
+            pending_authority: Some(Pubkey::new_unique()),
+            focus_pool: 2_000_000,
+            failure_pool: 3_000_000,
+            total_sessions: 12,
+            vault_bump: 254,
+            focus_pool_bump: 253,
+            failure_pool_bump: 252,
+            config: Config {
+                focus_pool_fee_bps: 250,
+                heartbeat_interval_seconds: 300,
+                ..Config::default()
+            },
+            refund_curve: RefundCurve::Stepped {
+                steps: vec![RefundStep {
+                    min_elapsed_bps: 5_000,
+                    refund_bps: 2_500,
+                }],
+            },
+            current_epoch: 4,
+            failure_pool_inflow: 9_000_000,
+            failure_pool_reserved: 1_000_000,
+            circuit_breaker: CircuitBreaker {
+                settlements_paused: true,
+                ..CircuitBreaker::default()
+            },
+            total_active_stake: 5_000_000,
+            total_pending_balance: 6_000_000,
+            streak_tiers: vec![StreakTier {
+                min_streak: 3,
+                bonus_bps: 500,
+            }],
+            attestor: Some(Pubkey::new_unique()),
+        }
+    }
+
+    fn focus_session() -> FocusSession {
+        let mut heartbeats = [0; 256];
+        heartbeats[0] = 0b1011;
+        heartbeats[255] = 0x80;
+        FocusSession {
+            user: Pubkey::new_unique(),
+            session_id: 7,
+            mint: Some(Pubkey::new_unique()),
+            is_active: true,
+            stake_amount: 99_000_000,
+            start_time: 1_700_000_000,
+            duration_minutes: 50,
+            pending_balance: 0,
+            tasks: vec![
+                Task {
+                    description: "Write spec".to_string(),
+                    completed: true,
+                },
+                Task {
+                    description: "Review PR".to_string(),
+                    completed: false,
+                },
+            ],
+            partner: Some(Pubkey::new_unique()),
+            pending_approvals: 1,
+            max_pause_seconds: 900,
+            paused_at: 1_700_000_600,
+            pauses: vec![PauseInterval {
+                start: 1_700_000_100,
+                end: 1_700_000_200,
+            }],
+            streak_bonus_bps: 150,
+            session_key: Some(crate::SessionKey {
+                key: Pubkey::new_unique(),
+                expires_at: 1_700_086_400,
+            }),
+            rent_payer: Pubkey::new_unique(),
+            heartbeat_interval_seconds: 300,
+            min_heartbeat_coverage_bps: 8_000,
+            max_missed_heartbeats: 3,
+            last_heartbeat: 1_700_000_900,
+            last_heartbeat_slot: 42,
+            heartbeats,
+            challenge_rate_bps: 1_000,
+            challenge_window_seconds: 120,
+            attested_focus_bps: 9_500,
+            challenge: Some(Challenge {
+                opens_at: 1_700_000_900,
+                closes_at: 1_700_001_020,
+            }),
+        }
+    }
+
+    #[test]
+    fn global_state_round_trips() {
+        let account = global_state();
+        let decoded = round_trip(&account, decode_global_state);
+        assert_eq!(decoded.authority, account.authority);
+        assert!(decoded.config == account.config);
+        assert!(decoded.refund_curve == account.refund_curve);
+        assert!(decoded.streak_tiers == account.streak_tiers);
+        assert_eq!(decoded.attestor, account.attestor);
+    }
+
+    #[test]
+    fn user_state_round_trips() {
+        let account = UserState {
+            user: Pubkey::new_unique(),
+            next_session_id: 3,
+        };
+        let decoded = round_trip(&account, decode_user_state);
+        assert_eq!(decoded.user, account.user);
+        assert_eq!(decoded.next_session_id, 3);
+    }
+
+    #[test]
+    fn user_stats_round_trips() {
+        let account = UserStats {
+            user: Pubkey::new_unique(),
+            sessions_started: 9,
+            sessions_completed: 6,
+            sessions_failed: 2,
+            sessions_expired: 1,
+            minutes_focused: 310,
+            total_staked: 9_000_000_000,
+            total_refunded: 5_500_000_000,
+            total_forfeited: 2_900_000_000,
+            total_streak_bonus: 40_000_000,
+            current_streak: 4,
+            best_streak: 5,
+            last_session_at: 1_700_000_000,
+            last_streak_completion: 1_700_003_000,
+        };
+        let decoded = round_trip(&account, decode_user_stats);
+        assert_eq!(decoded.user, account.user);
+        assert_eq!(decoded.total_forfeited, 2_900_000_000);
+        assert_eq!(decoded.last_streak_completion, 1_700_003_000);
+    }
+
+    #[test]
+    fn focus_session_round_trips() {
+        let account = focus_session();
+        let decoded = round_trip(&account, decode_focus_session);
+        assert_eq!(decoded.user, account.user);
+        assert_eq!(decoded.mint, account.mint);
+        assert_eq!(decoded.tasks.len(), 2);
+        assert_eq!(decoded.tasks[0].description, "Write spec");
+        assert_eq!(decoded.pauses.len(), 1);
+        assert_eq!(decoded.pauses[0].end, 1_700_000_200);
+        assert_eq!(decoded.heartbeats, account.heartbeats);
+        assert!(decoded.challenge == account.challenge);
+        assert_eq!(decoded.attested_focus_bps, 9_500);
+    }
+
+    #[test]
+    fn mint_config_round_trips() {
+        let account = MintConfig {
+            mint: Pubkey::new_unique(),
+            token_program: Pubkey::new_unique(),
+            enabled: true,
+            min_stake: 1_000_000,
+            focus_pool: 20_000,
+            failure_pool: 30_000,
+            bump: 255,
+            vault_bump: 254,
+            focus_pool_bump: 253,
+            failure_pool_bump: 252,
+        };
+        let decoded = round_trip(&account, decode_mint_config);
+        assert_eq!(decoded.mint, account.mint);
+        assert_eq!(decoded.token_program, account.token_program);
+        assert_eq!(decoded.failure_pool_bump, 252);
+    }
+
+    #[test]
+    fn reward_epoch_round_trips() {
+        let account = RewardEpoch {
+            epoch_id: 4,
+            start_time: 1_700_000_000,
+            inflow_start: 8_000_000,
+            total_shares: u64::MAX as u128 * 3,
+            rewards: 1_000_000,
+            claimed_rewards: 250_000,
+            closed: true,
+            bump: 251,
+        };
+        let decoded = round_trip(&account, decode_reward_epoch);
+        assert_eq!(decoded.total_shares, u64::MAX as u128 * 3);
+        assert!(decoded.closed);
+    }
+
+    #[test]
+    fn epoch_share_round_trips() {
+        let account = EpochShare {
+            epoch_id: 4,
+            user: Pubkey::new_unique(),
+            shares: 123_456_789_000,
+        };
+        let decoded = round_trip(&account, decode_epoch_share);
+        assert_eq!(decoded.user, account.user);
+        assert_eq!(decoded.shares, 123_456_789_000);
+    }
+
+    #[test]
+    fn decoders_reject_other_accounts_and_short_data() {
+        let data = account_data(&global_state());
+        assert!(decode_user_state(&data).is_err());
+        assert!(decode_focus_session(&data).is_err());
+        assert!(decode_global_state(&data[..8]).is_err());
+        assert!(decode_global_state(&data[..4]).is_err());
+
+        let mut data = account_data(&focus_session());
+        data[..8].copy_from_slice(UserStats::DISCRIMINATOR);
+        assert!(decode_focus_session(&data).is_err());
+    }
+}
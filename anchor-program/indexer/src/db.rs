@@ -0,0 +1,407 @@
+//! SQLite store the decoded events are written to.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use deepwork_client::events::Event;
+use deepwork_client::{pda, PoolKind};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+
+use crate::decode::Decoded;
+
+const SCHEMA: &str = include_str!("schema.sql");
+
+pub struct Db {
+    conn: Connection,
+}
+
+impl Db {
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Db { conn })
+    }
+
+    /// Newest transaction already indexed.
+    pub fn cursor(&self) -> Result<Option<Signature>> {
+        let signature: Option<String> = self
+            .conn
+            .query_row("SELECT signature FROM cursor WHERE id = 0", [], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(signature.map(|s| Signature::from_str(&s)).transpose()?)
+    }
+
+    /// Writes a transaction's events and moves the cursor past it atomically,
+    /// so an interrupted run resumes without double counting.
+    pub fn apply(&mut self, signature: &Signature, slot: u64, decoded: &Decoded) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let signature_str = signature.to_string();
+        for event in &decoded.events {
+            apply_event(&tx, &signature_str, decoded, event)?;
+        }
+        set_cursor(&tx, &signature_str, slot)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Moves the cursor past a transaction with nothing to index.
+    pub fn skip(&mut self, signature: &Signature, slot: u64) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        set_cursor(&tx, &signature.to_string(), slot)?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn set_cursor(tx: &Transaction, signature: &str, slot: u64) -> Result<()> {
+    tx.execute(
+        "INSERT INTO cursor (id, signature, slot) VALUES (0, ?1, ?2)
+         ON CONFLICT (id) DO UPDATE SET signature = ?1, slot = ?2",
+        params![signature, slot],
+    )?;
+    Ok(())
+}
+
+fn key(pubkey: &Pubkey) -> String {
+    pubkey.to_string()
+}
+
+fn optional_key(pubkey: &Option<Pubkey>) -> Option<String> {
+    pubkey.as_ref().map(key)
+}
+
+fn apply_event(tx: &Transaction, signature: &str, decoded: &Decoded, event: &Event) -> Result<()> {
+    match event {
+        Event::SessionStarted(e) => {
+            let address = pda::focus_session(&e.user, e.session_id);
+            tx.execute(
+                "INSERT OR REPLACE INTO sessions (user, session_id, address, mint, stake_amount,
+                     focus_pool_fee, duration_minutes, partner, start_time, status,
+                     pending_balance, focused_seconds, settled_at, start_signature)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 'active', 0, NULL, NULL, ?10)",
+                params![
+                    key(&e.user),
+                    e.session_id,
+                    key(&address),
+                    optional_key(&e.mint),
+                    e.stake_amount,
+                    e.focus_pool_fee,
+                    e.duration_minutes,
+                    optional_key(&e.partner),
+                    e.start_time,
+                    signature,
+                ],
+            )?;
+
+            // Sessions started through a CPI have no task descriptions here
+            let tasks = decoded.started_tasks.get(&address);
+            tx.execute(
+                "DELETE FROM tasks WHERE user = ?1 AND session_id = ?2",
+                params![key(&e.user), e.session_id],
+            )?;
+            for index in 0..e.task_count {
+                let task = tasks.and_then(|tasks| tasks.get(index as usize));
+                tx.execute(
+                    "INSERT INTO tasks (user, session_id, task_index, description, completed)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        key(&e.user),
+                        e.session_id,
+                        index,
+                        task.map(|task| task.description.as_str()),
+                        task.is_some_and(|task| task.completed),
+                    ],
+                )?;
+            }
+
+            pool_movement(
+                tx,
+                signature,
+                PoolKind::Focus,
+                &e.mint,
+                "fee",
+                e.focus_pool_fee as i64,
+                e.focus_pool,
+                Some(&e.user),
+                e.start_time,
+            )?;
+        }
+        Event::SessionCompleted(e) => {
+            tx.execute(
+                "UPDATE sessions SET status = 'completed', pending_balance = ?3,
+                     focused_seconds = ?4, settled_at = ?5
+                 WHERE user = ?1 AND session_id = ?2",
+                params![
+                    key(&e.user),
+                    e.session_id,
+                    e.pending_balance,
+                    e.focused_seconds,
+                    e.timestamp,
+                ],
+            )?;
+            settlement(
+                tx,
+                signature,
+                &e.user,
+                e.session_id,
+                &e.mint,
+                "completed",
+                e.returned_amount,
+                0,
+                None,
+                0,
+                e.timestamp,
+            )?;
+        }
+        Event::SessionFailed(e) => {
+            tx.execute(
+                "UPDATE sessions SET status = 'failed', pending_balance = 0,
+                     focused_seconds = ?3, settled_at = ?4
+                 WHERE user = ?1 AND session_id = ?2",
+                params![key(&e.user), e.session_id, e.focused_seconds, e.timestamp],
+            )?;
+            settlement(
+                tx,
+                signature,
+                &e.user,
+                e.session_id,
+                &e.mint,
+                "failed",
+                e.refund_amount,
+                e.forfeited_amount,
+                None,
+                0,
+                e.timestamp,
+            )?;
+            pool_movement(
+                tx,
+                signature,
+                PoolKind::Failure,
+                &e.mint,
+                "forfeit",
+                e.forfeited_amount as i64,
+                e.failure_pool,
+                Some(&e.user),
+                e.timestamp,
+            )?;
+        }
+        Event::SessionExpired(e) => {
+            tx.execute(
+                "UPDATE sessions SET status = 'expired', pending_balance = 0, settled_at = ?3
+                 WHERE user = ?1 AND session_id = ?2",
+                params![key(&e.user), e.session_id, e.timestamp],
+            )?;
+            settlement(
+                tx,
+                signature,
+                &e.user,
+                e.session_id,
+                &e.mint,
+                "expired",
+                0,
+                e.forfeited_amount,
+                Some(&e.keeper),
+                e.keeper_bounty,
+                e.timestamp,
+            )?;
+            pool_movement(
+                tx,
+                signature,
+                PoolKind::Failure,
+                &e.mint,
+                "forfeit",
+                e.forfeited_amount as i64,
+                e.failure_pool,
+                Some(&e.user),
+                e.timestamp,
+            )?;
+        }
+        Event::TaskUpdated(e) => {
+            tx.execute(
+                "UPDATE tasks SET completed = ?4, pending_approval = ?5, approved_by = ?6
+                 WHERE user = ?1 AND session_id = ?2 AND task_index = ?3",
+                params![
+                    key(&e.user),
+                    e.session_id,
+                    e.task_index,
+                    e.completed,
+                    e.pending_approval,
+                    optional_key(&e.approved_by),
+                ],
+            )?;
+        }
+        Event::RewardsClaimed(e) => {
+            tx.execute(
+                "UPDATE sessions SET status = 'claimed', pending_balance = 0
+                 WHERE user = ?1 AND session_id = ?2",
+                params![key(&e.user), e.session_id],
+            )?;
+            settlement(
+                tx,
+                signature,
+                &e.user,
+                e.session_id,
+                &e.mint,
+                "claimed",
+                e.refund_amount,
+                e.penalty_amount,
+                None,
+                0,
+                e.timestamp,
+            )?;
+            if e.streak_bonus > 0 {
+                pool_movement(
+                    tx,
+                    signature,
+                    PoolKind::Focus,
+                    &e.mint,
+                    "streak_bonus",
+                    -(e.streak_bonus as i64),
+                    e.focus_pool,
+                    Some(&e.user),
+                    e.timestamp,
+                )?;
+            }
+            if e.penalty_amount > 0 {
+                pool_movement(
+                    tx,
+                    signature,
+                    PoolKind::Failure,
+                    &e.mint,
+                    "penalty",
+                    e.penalty_amount as i64,
+                    e.failure_pool,
+                    Some(&e.user),
+                    e.timestamp,
+                )?;
+            }
+        }
+        Event::EmergencyWithdrawn(e) => {
+            tx.execute(
+                "UPDATE sessions SET status = 'withdrawn', pending_balance = 0,
+                     settled_at = COALESCE(settled_at, ?3)
+                 WHERE user = ?1 AND session_id = ?2",
+                params![key(&e.user), e.session_id, e.timestamp],
+            )?;
+            settlement(
+                tx,
+                signature,
+                &e.user,
+                e.session_id,
+                &e.mint,
+                "withdrawn",
+                e.amount,
+                0,
+                None,
+                0,
+                e.timestamp,
+            )?;
+        }
+        Event::EpochRewardsClaimed(e) => {
+            pool_movement(
+                tx,
+                signature,
+                PoolKind::Failure,
+                &None,
+                "epoch_reward",
+                -(e.reward as i64),
+                e.failure_pool,
+                Some(&e.user),
+                e.timestamp,
+            )?;
+        }
+        Event::PoolWithdrawn(e) => {
+            pool_movement(
+                tx,
+                signature,
+                e.pool,
+                &e.mint,
+                "withdrawal",
+                -(e.amount as i64),
+                e.pool_balance,
+                Some(&e.recipient),
+                e.timestamp,
+            )?;
+        }
+        // Nothing to index
+        Event::Initialized(_) | Event::Reconciled(_) => {}
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn settlement(
+    tx: &Transaction,
+    signature: &str,
+    user: &Pubkey,
+    session_id: u64,
+    mint: &Option<Pubkey>,
+    kind: &str,
+    refund_amount: u64,
+    forfeited_amount: u64,
+    keeper: Option<&Pubkey>,
+    keeper_bounty: u64,
+    timestamp: i64,
+) -> Result<()> {
+    tx.execute(
+        "INSERT INTO settlements (signature, user, session_id, mint, kind, refund_amount,
+             forfeited_amount, keeper, keeper_bounty, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            signature,
+            key(user),
+            session_id,
+            optional_key(mint),
+            kind,
+            refund_amount,
+            forfeited_amount,
+            keeper.map(key),
+            keeper_bounty,
+            timestamp,
+        ],
+    )?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pool_movement(
+    tx: &Transaction,
+    signature: &str,
+    pool: PoolKind,
+    mint: &Option<Pubkey>,
+    kind: &str,
+    amount: i64,
+    pool_balance: u64,
+    counterparty: Option<&Pubkey>,
+    timestamp: i64,
+) -> Result<()> {
+    let pool = match pool {
+        PoolKind::Focus => "focus",
+        PoolKind::Failure => "failure",
+    };
+    tx.execute(
+        "INSERT INTO pool_movements (signature, pool, mint, kind, amount, pool_balance,
+             counterparty, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            signature,
+            pool,
+            optional_key(mint),
+            kind,
+            amount,
+            pool_balance,
+            counterparty.map(key),
+            timestamp,
+        ],
+    )?;
+    Ok(())
+}
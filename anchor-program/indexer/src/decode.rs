@@ -0,0 +1,259 @@
+//! Extracts the deepwork instructions and events from a fetched transaction.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anchor_program::instruction as ix;
+use anyhow::{anyhow, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use deepwork_client::anchor_lang::{AnchorDeserialize, Discriminator};
+use deepwork_client::events::{decode_event, Event};
+use deepwork_client::{Task, PROGRAM_ID};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::rpc::FetchedTransaction;
+
+// Position of focus_session in the start instructions' accounts
+const START_FOCUS_SESSION_ACCOUNT: usize = 1;
+
+pub struct Decoded {
+    // Task lists of the sessions started by top-level instructions, by
+    // focus_session address; events only carry the task count
+    pub started_tasks: HashMap<Pubkey, Vec<Task>>,
+    // Events logged by the program itself, in order
+    pub events: Vec<Event>,
+}
+
+pub fn decode_transaction(transaction: &FetchedTransaction) -> Result<Decoded> {
+    let program_id = PROGRAM_ID;
+    let mut started_tasks = HashMap::new();
+    for instruction in transaction.transaction.message.instructions() {
+        if transaction
+            .account_keys
+            .get(instruction.program_id_index as usize)
+            != Some(&program_id)
+        {
+            continue;
+        }
+        let Some(tasks) = start_tasks(&instruction.data)? else {
+            continue;
+        };
+        let focus_session = instruction
+            .accounts
+            .get(START_FOCUS_SESSION_ACCOUNT)
+            .and_then(|&index| transaction.account_keys.get(index as usize))
+            .ok_or_else(|| anyhow!("start instruction without a focus_session account"))?;
+        started_tasks.insert(*focus_session, tasks);
+    }
+
+    Ok(Decoded {
+        started_tasks,
+        events: program_events(&transaction.log_messages, &program_id.to_string()),
+    })
+}
+
+/// Tasks passed to any of the start instructions, `None` for other instructions.
+fn start_tasks(data: &[u8]) -> Result<Option<Vec<Task>>> {
+    fn args<T: Discriminator + AnchorDeserialize>(data: &[u8]) -> Result<Option<T>> {
+        match data.strip_prefix(T::DISCRIMINATOR) {
+            Some(mut args) => Ok(Some(T::deserialize(&mut args)?)),
+            None => Ok(None),
+        }
+    }
+
+    if let Some(args) = args::<ix::StartFocusSession>(data)? {
+        return Ok(Some(args.tasks));
+    }
+    if let Some(args) = args::<ix::StartFocusSessionToken>(data)? {
+        return Ok(Some(args.tasks));
+    }
+    if args::<ix::StartFocusSessionV1>(data)?.is_some() {
+        // the default task start_focus_session_v1 creates
+        return Ok(Some(vec![Task {
+            description: "Focus Session".to_string(),
+            completed: false,
+        }]));
+    }
+    Ok(None)
+}
+
+/// Decodes `Program data:` lines logged while the program itself is executing,
+/// skipping anything logged by programs it invokes or that invoke it.
+fn program_events(logs: &[String], program_id: &str) -> Vec<Event> {
+    let mut invocations: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+    for log in logs {
+        let Some(rest) = log.strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(data) = rest.strip_prefix("data: ") {
+            if invocations.last() != Some(&program_id) {
+                continue;
+            }
+            if let Some(event) = BASE64_STANDARD
+                .decode(data)
+                .ok()
+                .and_then(|data| decode_event(&data))
+            {
+                events.push(event);
+            }
+            continue;
+        }
+        let Some((id, status)) = rest.split_once(' ') else {
+            continue;
+        };
+        if Pubkey::from_str(id).is_err() {
+            continue;
+        }
+        if status.starts_with("invoke [") {
+            invocations.push(id);
+        } else if status == "success" || status.starts_with("failed") {
+            invocations.pop();
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deepwork_client::anchor_lang::Event as _;
+    use deepwork_client::{instruction, pda, TaskUpdated};
+    use solana_sdk::instruction::Instruction;
+    use solana_sdk::message::{Message, VersionedMessage};
+    use solana_sdk::signature::Signature;
+    use solana_sdk::transaction::VersionedTransaction;
+
+    const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+
+    /// The line `emit!` logs for a TaskUpdated event about `task_index`.
+    fn task_updated(task_index: u8) -> String {
+        let event = TaskUpdated {
+            user: Pubkey::new_unique(),
+            session_id: 3,
+            task_index,
+            completed: true,
+            pending_approval: false,
+            approved_by: None,
+            completed_tasks: 1,
+            task_count: 2,
+            timestamp: 1_700_000_000,
+        };
+        format!("Program data: {}", BASE64_STANDARD.encode(event.data()))
+    }
+
+    fn task_indices(events: Vec<Event>) -> Vec<u8> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                Event::TaskUpdated(event) => event.task_index,
+                _ => panic!("unexpected event"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn events_are_taken_only_from_the_program_itself() {
+        let program = PROGRAM_ID.to_string();
+        let caller = Pubkey::new_unique().to_string();
+        let logs = [
+            format!("Program {caller} invoke [1]"),
+            task_updated(10),
+            format!("Program {program} invoke [2]"),
+            "Program log: Instruction: UpdateTask".to_string(),
+            format!("Program {SYSTEM_PROGRAM} invoke [3]"),
+            task_updated(11),
+            format!("Program {SYSTEM_PROGRAM} success"),
+            task_updated(0),
+            format!("Program log: {}", task_updated(12)),
+            "Program data: not base64!".to_string(),
+            format!("Program data: {}", BASE64_STANDARD.encode([1, 2, 3])),
+            format!("Program {program} consumed 8123 of 200000 compute units"),
+            format!("Program return: {program} AQ=="),
+            format!("Program {program} success"),
+            task_updated(13),
+            format!("Program {caller} success"),
+            format!("Program {program} invoke [1]"),
+            task_updated(1),
+            format!("Program {program} failed: custom program error: 0x1771"),
+            task_updated(14),
+        ];
+        let events = program_events(&logs, &program);
+        assert_eq!(task_indices(events), [0, 1]);
+    }
+
+    #[test]
+    fn truncated_logs_keep_the_events_before_the_cut() {
+        let program = PROGRAM_ID.to_string();
+        let logs = [
+            format!("Program {program} invoke [1]"),
+            task_updated(0),
+            "Log truncated".to_string(),
+        ];
+        let events = program_events(&logs, &program);
+        assert_eq!(task_indices(events), [0]);
+    }
+
+    #[test]
+    fn start_instructions_are_decoded_with_their_tasks() {
+        let user = Pubkey::new_unique();
+        let tasks = vec![
+            Task {
+                description: "Write spec".to_string(),
+                completed: false,
+            },
+            Task {
+                description: "Review PR".to_string(),
+                completed: false,
+            },
+        ];
+        let instructions = [
+            // another program's instruction, e.g. a memo
+            Instruction::new_with_bytes(Pubkey::new_unique(), b"deep work", Vec::new()),
+            instruction::start_focus_session(
+                &user,
+                &user,
+                0,
+                1_000_000_000,
+                25,
+                tasks.clone(),
+                None,
+                None,
+            ),
+            instruction::start_focus_session_v1(&user, &user, 1, 1_000_000_000, 25),
+            instruction::update_task(&user, &user, 0, None, 1, true),
+        ];
+        let message = Message::new(&instructions, Some(&user));
+        let transaction = FetchedTransaction {
+            slot: 7,
+            account_keys: message.account_keys.clone(),
+            transaction: VersionedTransaction {
+                signatures: vec![Signature::default()],
+                message: VersionedMessage::Legacy(message),
+            },
+            log_messages: Vec::new(),
+        };
+
+        let decoded = decode_transaction(&transaction).unwrap();
+        let descriptions = |session_id| -> Vec<String> {
+            decoded.started_tasks[&pda::focus_session(&user, session_id)]
+                .iter()
+                .map(|task| task.description.clone())
+                .collect()
+        };
+        assert_eq!(decoded.started_tasks.len(), 2);
+        assert_eq!(descriptions(0), ["Write spec", "Review PR"]);
+        assert_eq!(descriptions(1), ["Focus Session"]);
+        assert!(decoded.events.is_empty());
+    }
+
+    #[test]
+    fn malformed_start_arguments_are_an_error() {
+        let mut data = ix::StartFocusSession::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[1, 2, 3]);
+        assert!(start_tasks(&data).is_err());
+        assert!(start_tasks(ix::UpdateConfig::DISCRIMINATOR)
+            .unwrap()
+            .is_none());
+    }
+}
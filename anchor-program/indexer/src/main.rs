@@ -0,0 +1,474 @@
+//! `kaizen-indexer`: follows the deepwork program's transactions over RPC and
+//! writes sessions, tasks, settlements and pool movements to SQLite.
+
+mod db;
+mod decode;
+mod rpc;
+
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::Parser;
+use deepwork_client::PROGRAM_ID;
+use solana_sdk::signature::Signature;
+
+use crate::db::Db;
+use crate::rpc::{RpcClient, Transport};
+
+// getSignaturesForAddress page size (the RPC maximum)
+const SIGNATURE_PAGE: usize = 1000;
+
+#[derive(Parser)]
+#[command(
+    name = "kaizen-indexer",
+    about = "Index deepwork program activity into SQLite",
+    version
+)]
+struct Args {
+    /// RPC endpoint
+    #[arg(long, short = 'u', default_value = "http://127.0.0.1:8899")]
+    url: String,
+
+    /// SQLite database, created if missing
+    #[arg(long, default_value = "kaizen.db")]
+    db: String,
+
+    /// Seconds between polls
+    #[arg(long, default_value_t = 5)]
+    interval: u64,
+
+    /// Index what's there and exit instead of polling
+    #[arg(long)]
+    once: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let rpc = RpcClient::new(args.url);
+    let mut db = Db::open(&args.db)?;
+
+    loop {
+        let indexed = sync(&rpc, &mut db)?;
+        if indexed > 0 {
+            println!("Indexed {indexed} transaction(s)");
+        }
+        if args.once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(args.interval));
+    }
+}
+
+/// Indexes every confirmed transaction newer than the cursor, oldest first.
+fn sync<T: Transport>(rpc: &RpcClient<T>, db: &mut Db) -> Result<usize> {
+    let cursor = db.cursor()?;
+
+    // Pages come back newest first; walk back until the cursor
+    let mut pending = Vec::new();
+    let mut before: Option<Signature> = None;
+    loop {
+        let page = rpc.get_signatures_for_address(
+            &PROGRAM_ID,
+            before.as_ref(),
+            cursor.as_ref(),
+            SIGNATURE_PAGE,
+        )?;
+        let done = page.len() < SIGNATURE_PAGE;
+        before = page.last().map(|info| info.signature);
+        pending.extend(page);
+        if done {
+            break;
+        }
+    }
+
+    let mut indexed = 0;
+    for info in pending.into_iter().rev() {
+        let Some(transaction) = rpc.get_transaction(&info.signature)? else {
+            // Not visible at this commitment yet; pick it up next poll
+            break;
+        };
+        // Failed transactions change nothing but still move the cursor
+        if info.failed {
+            db.skip(&info.signature, transaction.slot)?;
+            continue;
+        }
+        let decoded = decode::decode_transaction(&transaction)?;
+        db.apply(&info.signature, transaction.slot, &decoded)?;
+        indexed += 1;
+    }
+    Ok(indexed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anyhow::bail;
+    use base64::prelude::{Engine, BASE64_STANDARD};
+    use deepwork_client::anchor_lang::Event;
+    use deepwork_client::{
+        instruction, RewardsClaimed, SessionCompleted, SessionFailed, SessionStarted, Task,
+    };
+    use serde_json::{json, Value};
+    use solana_sdk::instruction::Instruction;
+    use solana_sdk::message::{Message, VersionedMessage};
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::transaction::VersionedTransaction;
+
+    use super::*;
+
+    const STAKE: u64 = 990_000_000;
+    const FEE: u64 = 10_000_000;
+    const START_TIME: i64 = 1_700_000_000;
+
+    /// getSignaturesForAddress and getTransaction responses for the program,
+    /// answered the way a node does.
+    #[derive(Clone, Default)]
+    struct Ledger {
+        // getSignaturesForAddress entries, newest first
+        signatures: Vec<Value>,
+        // getTransaction results by signature; missing ones aren't visible yet
+        transactions: HashMap<String, Value>,
+    }
+
+    impl Ledger {
+        /// Records a transaction sent by `payer` that logged `events`, or
+        /// failed when `events` is `None`.
+        fn record<E: Event>(
+            &mut self,
+            payer: &Pubkey,
+            instructions: &[Instruction],
+            events: Option<&[E]>,
+        ) -> Signature {
+            let slot = 10 + self.signatures.len() as u64;
+            let signature = Signature::from([slot as u8; 64]);
+            let message = Message::new(instructions, Some(payer));
+            let transaction = VersionedTransaction {
+                signatures: vec![signature],
+                message: VersionedMessage::Legacy(message),
+            };
+
+            let program = PROGRAM_ID.to_string();
+            let mut logs = vec![format!("Program {program} invoke [1]")];
+            let err = match events {
+                Some(events) => {
+                    logs.extend(events.iter().map(|event| {
+                        format!("Program data: {}", BASE64_STANDARD.encode(event.data()))
+                    }));
+                    logs.push(format!("Program {program} success"));
+                    Value::Null
+                }
+                None => {
+                    logs.push(format!(
+                        "Program {program} failed: custom program error: 0x1771"
+                    ));
+                    json!({ "InstructionError": [0, { "Custom": 6001 }] })
+                }
+            };
+
+            self.signatures.insert(
+                0,
+                json!({
+                    "signature": signature.to_string(),
+                    "slot": slot,
+                    "err": err,
+                    "memo": null,
+                    "blockTime": START_TIME + slot as i64,
+                    "confirmationStatus": "confirmed",
+                }),
+            );
+            let encoded = BASE64_STANDARD.encode(bincode::serialize(&transaction).unwrap());
+            self.transactions.insert(
+                signature.to_string(),
+                json!({
+                    "slot": slot,
+                    "blockTime": START_TIME + slot as i64,
+                    "transaction": [encoded, "base64"],
+                    "meta": {
+                        "err": err,
+                        "fee": 5000,
+                        "logMessages": logs,
+                        "loadedAddresses": { "writable": [], "readonly": [] },
+                    },
+                    "version": "legacy",
+                }),
+            );
+            signature
+        }
+    }
+
+    impl Transport for Ledger {
+        fn call(&self, method: &str, params: Value) -> Result<Value> {
+            match method {
+                "getSignaturesForAddress" => {
+                    assert_eq!(params[0], PROGRAM_ID.to_string());
+                    let config = &params[1];
+                    let position = |bound: &str| {
+                        config[bound].as_str().map(|signature| {
+                            self.signatures
+                                .iter()
+                                .position(|entry| entry["signature"] == signature)
+                                .unwrap()
+                        })
+                    };
+                    let start = position("before").map_or(0, |index| index + 1);
+                    let end = position("until").unwrap_or(self.signatures.len());
+                    let limit = config["limit"].as_u64().unwrap() as usize;
+                    Ok(json!(self.signatures[start..end.max(start)]
+                        .iter()
+                        .take(limit)
+                        .collect::<Vec<_>>()))
+                }
+                "getTransaction" => Ok(self
+                    .transactions
+                    .get(params[0].as_str().unwrap())
+                    .cloned()
+                    .unwrap_or(Value::Null)),
+                _ => bail!("unexpected {method} request"),
+            }
+        }
+    }
+
+    fn tasks(descriptions: &[&str]) -> Vec<Task> {
+        descriptions
+            .iter()
+            .map(|description| Task {
+                description: description.to_string(),
+                completed: false,
+            })
+            .collect()
+    }
+
+    fn started(user: &Pubkey, session_id: u64, task_count: u8) -> SessionStarted {
+        SessionStarted {
+            user: *user,
+            session_id,
+            mint: None,
+            stake_amount: STAKE,
+            focus_pool_fee: FEE,
+            duration_minutes: 25,
+            task_count,
+            partner: None,
+            start_time: START_TIME,
+            focus_pool: FEE * (session_id + 1),
+        }
+    }
+
+    fn start(ledger: &mut Ledger, user: &Pubkey, session_id: u64, descriptions: &[&str]) {
+        let instruction = instruction::start_focus_session(
+            user,
+            user,
+            session_id,
+            STAKE + FEE,
+            25,
+            tasks(descriptions),
+            None,
+            None,
+        );
+        let event = started(user, session_id, descriptions.len() as u8);
+        ledger.record(user, &[instruction], Some(&[event]));
+    }
+
+    fn session_row(db: &Db, user: &Pubkey, session_id: u64) -> (String, u64) {
+        db.connection()
+            .query_row(
+                "SELECT status, pending_balance FROM sessions WHERE user = ?1 AND session_id = ?2",
+                rusqlite::params![user.to_string(), session_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    fn settlements(db: &Db) -> Vec<(String, u64, String, u64, u64)> {
+        let mut statement = db
+            .connection()
+            .prepare(
+                "SELECT user, session_id, kind, refund_amount, forfeited_amount
+                 FROM settlements ORDER BY rowid",
+            )
+            .unwrap();
+        statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    fn cursor_slot(db: &Db) -> u64 {
+        db.connection()
+            .query_row("SELECT slot FROM cursor WHERE id = 0", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn sync_indexes_recorded_transactions_and_advances_the_cursor() {
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ledger = Ledger::default();
+        start(&mut ledger, &alice, 0, &["Write spec", "Review PR"]);
+        start(&mut ledger, &bob, 0, &["Inbox zero"]);
+        // completing too early fails and changes nothing
+        ledger.record::<SessionCompleted>(
+            &alice,
+            &[instruction::complete_focus_session(&alice, &alice, 0, 0)],
+            None,
+        );
+        ledger.record(
+            &bob,
+            &[instruction::fail_focus_session(&bob, &bob, 0)],
+            Some(&[SessionFailed {
+                user: bob,
+                session_id: 0,
+                mint: None,
+                stake_amount: STAKE,
+                refund_amount: 0,
+                forfeited_amount: STAKE,
+                duration_minutes: 25,
+                focused_seconds: 300,
+                task_count: 1,
+                failure_pool: STAKE,
+                timestamp: START_TIME + 300,
+            }]),
+        );
+        let completed = ledger.record(
+            &alice,
+            &[instruction::complete_focus_session(&alice, &alice, 0, 0)],
+            Some(&[SessionCompleted {
+                user: alice,
+                session_id: 0,
+                mint: None,
+                stake_amount: STAKE,
+                returned_amount: 0,
+                pending_balance: STAKE,
+                duration_minutes: 25,
+                focused_seconds: 1_500,
+                task_count: 2,
+                timestamp: START_TIME + 1_500,
+            }]),
+        );
+
+        let mut db = Db::open(":memory:").unwrap();
+        assert_eq!(db.cursor().unwrap(), None);
+        let indexed = sync(&RpcClient::with_transport(ledger.clone()), &mut db).unwrap();
+        assert_eq!(indexed, 4);
+        assert_eq!(db.cursor().unwrap(), Some(completed));
+        assert_eq!(cursor_slot(&db), 14);
+        assert_eq!(
+            session_row(&db, &alice, 0),
+            ("completed".to_string(), STAKE)
+        );
+        assert_eq!(session_row(&db, &bob, 0), ("failed".to_string(), 0));
+        let descriptions: Vec<String> = db
+            .connection()
+            .prepare("SELECT description FROM tasks WHERE user = ?1 ORDER BY task_index")
+            .unwrap()
+            .query_map([alice.to_string()], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(descriptions, ["Write spec", "Review PR"]);
+        assert_eq!(
+            settlements(&db),
+            [
+                (bob.to_string(), 0, "failed".to_string(), 0, STAKE),
+                (alice.to_string(), 0, "completed".to_string(), 0, 0),
+            ]
+        );
+
+        // nothing new: the cursor stays put
+        assert_eq!(
+            sync(&RpcClient::with_transport(ledger.clone()), &mut db).unwrap(),
+            0
+        );
+        assert_eq!(db.cursor().unwrap(), Some(completed));
+
+        // a claim, then a start not visible at this commitment yet
+        let claimed = ledger.record(
+            &alice,
+            &[instruction::claim_rewards(&alice, &alice, 0)],
+            Some(&[RewardsClaimed {
+                user: alice,
+                session_id: 0,
+                mint: None,
+                refund_amount: STAKE / 2,
+                penalty_amount: STAKE / 2,
+                streak_bonus: 0,
+                completed_tasks: 1,
+                task_count: 2,
+                failure_pool: STAKE + STAKE / 2,
+                focus_pool: 2 * FEE,
+                timestamp: START_TIME + 1_600,
+            }]),
+        );
+        start(&mut ledger, &alice, 1, &["Deep work"]);
+        let mut lagging = ledger.clone();
+        let latest = lagging.signatures[0]["signature"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        lagging.transactions.remove(&latest);
+
+        assert_eq!(
+            sync(&RpcClient::with_transport(lagging), &mut db).unwrap(),
+            1
+        );
+        assert_eq!(db.cursor().unwrap(), Some(claimed));
+        assert_eq!(session_row(&db, &alice, 0), ("claimed".to_string(), 0));
+        assert_eq!(settlements(&db).len(), 3);
+        assert_eq!(
+            settlements(&db)[2],
+            (
+                alice.to_string(),
+                0,
+                "claimed".to_string(),
+                STAKE / 2,
+                STAKE / 2
+            )
+        );
+
+        // the next poll picks it up
+        assert_eq!(
+            sync(&RpcClient::with_transport(ledger), &mut db).unwrap(),
+            1
+        );
+        assert_eq!(db.cursor().unwrap().unwrap().to_string(), latest);
+        assert_eq!(cursor_slot(&db), 16);
+        assert_eq!(session_row(&db, &alice, 1), ("active".to_string(), 0));
+    }
+
+    /// Indexes a local validator the program is deployed to, after some
+    /// activity and with nothing else sending to it meanwhile, e.g. after
+    /// `anchor test --detach`. Set KAIZEN_RPC_URL to use another node.
+    #[test]
+    #[ignore = "needs a validator with the program deployed"]
+    fn sync_indexes_a_validator() {
+        let url =
+            std::env::var("KAIZEN_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+        let rpc = RpcClient::new(url);
+        let newest = rpc
+            .get_signatures_for_address(&PROGRAM_ID, None, None, 1)
+            .unwrap();
+        assert!(!newest.is_empty(), "no program transactions to index");
+
+        let mut db = Db::open(":memory:").unwrap();
+        assert!(sync(&rpc, &mut db).unwrap() > 0);
+        assert_eq!(db.cursor().unwrap(), Some(newest[0].signature));
+        let sessions: u64 = db
+            .connection()
+            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert!(sessions > 0);
+
+        // a second poll resumes from the cursor
+        assert_eq!(sync(&rpc, &mut db).unwrap(), 0);
+        assert_eq!(db.cursor().unwrap(), Some(newest[0].signature));
+    }
+}
@@ -0,0 +1,140 @@
+//! Minimal JSON-RPC client for following the program's transactions.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use deepwork_client::rpc::JsonRpc;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+
+/// Where the requests go: a node over HTTP, or recorded responses in tests.
+pub trait Transport {
+    fn call(&self, method: &str, params: Value) -> Result<Value>;
+}
+
+impl Transport for JsonRpc {
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        JsonRpc::call(self, method, params)
+    }
+}
+
+pub struct RpcClient<T = JsonRpc> {
+    rpc: T,
+}
+
+pub struct SignatureInfo {
+    pub signature: Signature,
+    pub failed: bool,
+}
+
+/// A confirmed transaction with the parts of its metadata the indexer reads.
+pub struct FetchedTransaction {
+    pub slot: u64,
+    pub transaction: VersionedTransaction,
+    // static keys followed by keys loaded from address lookup tables
+    pub account_keys: Vec<Pubkey>,
+    pub log_messages: Vec<String>,
+}
+
+impl RpcClient {
+    pub fn new(url: String) -> Self {
+        Self::with_transport(JsonRpc::new(url))
+    }
+}
+
+impl<T: Transport> RpcClient<T> {
+    pub fn with_transport(rpc: T) -> Self {
+        RpcClient { rpc }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        self.rpc.call(method, params)
+    }
+
+    /// Signatures involving `address`, newest first, strictly between
+    /// `before` and `until` when given.
+    pub fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<&Signature>,
+        until: Option<&Signature>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let mut config = json!({ "commitment": "confirmed", "limit": limit });
+        if let Some(before) = before {
+            config["before"] = json!(before.to_string());
+        }
+        if let Some(until) = until {
+            config["until"] = json!(until.to_string());
+        }
+        let result = self.call(
+            "getSignaturesForAddress",
+            json!([address.to_string(), config]),
+        )?;
+        result
+            .as_array()
+            .ok_or_else(|| anyhow!("unexpected getSignaturesForAddress response"))?
+            .iter()
+            .map(|entry| {
+                Ok(SignatureInfo {
+                    signature: Signature::from_str(
+                        entry["signature"].as_str().unwrap_or_default(),
+                    )?,
+                    failed: !entry["err"].is_null(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn get_transaction(&self, signature: &Signature) -> Result<Option<FetchedTransaction>> {
+        let result = self.call(
+            "getTransaction",
+            json!([signature.to_string(), {
+                "encoding": "base64",
+                "commitment": "confirmed",
+                "maxSupportedTransactionVersion": 0,
+            }]),
+        )?;
+        if result.is_null() {
+            return Ok(None);
+        }
+
+        let encoded = result["transaction"][0]
+            .as_str()
+            .ok_or_else(|| anyhow!("unexpected transaction encoding"))?;
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&BASE64_STANDARD.decode(encoded)?)?;
+
+        let meta = &result["meta"];
+        let mut account_keys = transaction.message.static_account_keys().to_vec();
+        for list in ["writable", "readonly"] {
+            account_keys.extend(pubkeys(&meta["loadedAddresses"][list])?);
+        }
+
+        Ok(Some(FetchedTransaction {
+            slot: result["slot"].as_u64().unwrap_or_default(),
+            transaction,
+            account_keys,
+            log_messages: meta["logMessages"]
+                .as_array()
+                .map(|logs| {
+                    logs.iter()
+                        .filter_map(|log| log.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }))
+    }
+}
+
+fn pubkeys(value: &Value) -> Result<Vec<Pubkey>> {
+    let Some(keys) = value.as_array() else {
+        return Ok(Vec::new());
+    };
+    keys.iter()
+        .map(|key| Ok(Pubkey::from_str(key.as_str().unwrap_or_default())?))
+        .collect()
+}
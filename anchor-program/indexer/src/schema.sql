@@ -0,0 +1,64 @@
+-- Amounts are in lamports for SOL sessions and base units for token sessions.
+-- Pubkeys are base58 strings; mint is NULL for SOL.
+
+CREATE TABLE IF NOT EXISTS sessions (
+    user TEXT NOT NULL,
+    session_id INTEGER NOT NULL,
+    address TEXT NOT NULL UNIQUE,      -- focus_session PDA
+    mint TEXT,
+    stake_amount INTEGER NOT NULL,     -- Locked in the vault, after the focus pool fee
+    focus_pool_fee INTEGER NOT NULL,
+    duration_minutes INTEGER NOT NULL,
+    partner TEXT,
+    start_time INTEGER NOT NULL,
+    status TEXT NOT NULL,              -- active, completed, failed, expired, claimed, withdrawn
+    pending_balance INTEGER NOT NULL DEFAULT 0,
+    focused_seconds INTEGER,           -- Set when the session completes or fails
+    settled_at INTEGER,
+    start_signature TEXT NOT NULL,
+    PRIMARY KEY (user, session_id)
+);
+
+CREATE TABLE IF NOT EXISTS tasks (
+    user TEXT NOT NULL,
+    session_id INTEGER NOT NULL,
+    task_index INTEGER NOT NULL,
+    description TEXT,                  -- NULL when the session was started through a CPI
+    completed INTEGER NOT NULL DEFAULT 0,
+    pending_approval INTEGER NOT NULL DEFAULT 0,
+    approved_by TEXT,
+    PRIMARY KEY (user, session_id, task_index)
+);
+
+CREATE TABLE IF NOT EXISTS settlements (
+    signature TEXT NOT NULL,
+    user TEXT NOT NULL,
+    session_id INTEGER NOT NULL,
+    mint TEXT,
+    kind TEXT NOT NULL,                -- completed, failed, expired, claimed, withdrawn
+    refund_amount INTEGER NOT NULL,    -- Paid to the user
+    forfeited_amount INTEGER NOT NULL, -- Moved to the failure pool
+    keeper TEXT,
+    keeper_bounty INTEGER NOT NULL DEFAULT 0,
+    timestamp INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS settlements_by_session ON settlements (user, session_id);
+
+CREATE TABLE IF NOT EXISTS pool_movements (
+    signature TEXT NOT NULL,
+    pool TEXT NOT NULL,                -- focus or failure
+    mint TEXT,
+    kind TEXT NOT NULL,                -- fee, forfeit, penalty, streak_bonus, withdrawal, epoch_reward
+    amount INTEGER NOT NULL,           -- Positive into the pool, negative out of it
+    pool_balance INTEGER NOT NULL,     -- Pool total afterwards
+    counterparty TEXT,                 -- User, or the recipient of a withdrawal
+    timestamp INTEGER NOT NULL
+);
+
+-- Newest transaction indexed so far
+CREATE TABLE IF NOT EXISTS cursor (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL
+);
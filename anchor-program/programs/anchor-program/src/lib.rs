@@ -1,21 +1,95 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as instructions_sysvar, load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::solana_program::sysvar::slot_hashes;
 use anchor_lang::system_program;
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    default_account_state::DefaultAccountState, BaseStateWithExtensions, ExtensionType,
+    StateWithExtensions,
+};
+use anchor_spl::token_2022::spl_token_2022::state::AccountState;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+use solana_sha256_hasher::hashv;
 
 declare_id!("as6C6SkX7KmKZ3XjELQSpiTHSk7xXnt1AK8h1y2XwYJ");
 
+const MAX_PAUSES: usize = 10;
+const MAX_REFUND_STEPS: usize = 5;
+const MAX_STREAK_TIERS: usize = 5;
+// Intervals kept in FocusSession::heartbeats; covers the longest session plus pauses
+// at the shortest interval
+const HEARTBEAT_SLOTS: u64 = 2_048;
+const BPS_DENOMINATOR: u64 = 10_000;
+
+// Layout version of `Config`; bump whenever a field is added
+const CONFIG_VERSION: u8 = 6;
+// Storage cap for FocusSession::tasks, the upper bound for Config::max_tasks
+const MAX_TASKS: u8 = 20;
+
+// Bounds enforced by update_config
+const MAX_FOCUS_POOL_FEE_BPS: u16 = 1_000;
+const MAX_KEEPER_BOUNTY_BPS: u16 = 1_000;
+const MAX_STREAK_BONUS_BPS: u16 = 2_000;
+const MIN_STAKE_FLOOR: u64 = 1_000_000;
+const MAX_DURATION_LIMIT_MINUTES: u64 = 1_440;
+const MAX_GRACE_MINUTES: u64 = 60;
+const MAX_PAUSE_LIMIT_MINUTES: u64 = 240;
+const MIN_EPOCH_DURATION_SECONDS: i64 = 3_600;
+const MAX_EPOCH_DURATION_SECONDS: i64 = 90 * 86_400;
+const MIN_HEARTBEAT_INTERVAL_SECONDS: u32 = 60;
+const MAX_HEARTBEAT_INTERVAL_SECONDS: u32 = 3_600;
+const MIN_CHALLENGE_WINDOW_SECONDS: u32 = 60;
+const MAX_CHALLENGE_WINDOW_SECONDS: u32 = 3_600;
+// Longest a session key may stay valid, counted from the session start
+const MAX_SESSION_KEY_SECONDS: i64 = 7 * 86_400;
+
+const ED25519_PROGRAM_ID: Pubkey = pubkey!("Ed25519SigVerify111111111111111111111111111");
+// Start of the Ed25519 instruction's data after its signature count and one offsets entry
+const ED25519_DATA_START: usize = 16;
+// Signed ahead of every focus report so the attestor's signatures can't be replayed elsewhere
+pub const FOCUS_REPORT_DOMAIN: &[u8] = b"kaizen-focus-report-v1";
+
 #[program]
 pub mod deepwork {
     use super::*;
 
     pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
         let global_state = &mut ctx.accounts.global_state;
-        global_state.authority = ctx.accounts.authority.key();
-        global_state.focus_pool = 0;
-        global_state.failure_pool = 0;
-        global_state.total_sessions = 0;
-        global_state.vault_bump = ctx.bumps.vault;
-        global_state.focus_pool_bump = ctx.bumps.focus_pool_vault;
-        global_state.failure_pool_bump = ctx.bumps.failure_pool_vault;
+
+        // global_state is init_if_needed: once set up, re-running initialize only
+        // recreates missing vaults and never touches authority, pools or counters
+        if global_state.authority == Pubkey::default() {
+            global_state.authority = ctx.accounts.authority.key();
+            global_state.pending_authority = None;
+            global_state.focus_pool = 0;
+            global_state.failure_pool = 0;
+            global_state.total_sessions = 0;
+            global_state.vault_bump = ctx.bumps.vault;
+            global_state.focus_pool_bump = ctx.bumps.focus_pool_vault;
+            global_state.failure_pool_bump = ctx.bumps.failure_pool_vault;
+            global_state.config = Config::default();
+            global_state.refund_curve = RefundCurve::None;
+            global_state.streak_tiers = Vec::new();
+            global_state.attestor = None;
+            global_state.current_epoch = 0;
+            global_state.failure_pool_inflow = 0;
+            global_state.failure_pool_reserved = 0;
+            global_state.circuit_breaker = CircuitBreaker::default();
+            global_state.total_active_stake = 0;
+            global_state.total_pending_balance = 0;
+        } else {
+            require_keys_eq!(global_state.authority, ctx.accounts.authority.key());
+        }
+
+        // reward epochs start counting from the first initialization
+        let epoch = &mut ctx.accounts.epoch;
+        if epoch.start_time == 0 {
+            epoch.epoch_id = 0;
+            epoch.start_time = Clock::get()?.unix_timestamp;
+            epoch.inflow_start = 0;
+            epoch.bump = ctx.bumps.epoch;
+        }
 
         let rent_lamports = Rent::get()?.minimum_balance(0);
 
@@ -89,6 +163,14 @@ pub mod deepwork {
             )?;
         }
 
+        let global_state = &ctx.accounts.global_state;
+        emit!(Initialized {
+            authority: global_state.authority,
+            config: global_state.config.clone(),
+            current_epoch: global_state.current_epoch,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
         Ok(())
     }
 
@@ -97,27 +179,36 @@ pub mod deepwork {
         stake_amount: u64,
         duration_minutes: u64,
         tasks: Vec<Task>,
+        partner: Option<Pubkey>,
+        session_key: Option<SessionKey>,
     ) -> Result<()> {
-        require!(stake_amount >= 10_000_000, ErrorCode::StakeTooLow);
         require!(
-            duration_minutes > 0 && duration_minutes <= 480,
-            ErrorCode::InvalidDuration
+            !ctx.accounts
+                .global_state
+                .circuit_breaker
+                .new_sessions_paused,
+            ErrorCode::NewSessionsPaused
         );
-        require!(tasks.len() > 0, ErrorCode::NoTasksProvided);
-        require!(tasks.len() <= 20, ErrorCode::TooManyTasks);
+        let config = ctx.accounts.global_state.config.clone();
+        require!(stake_amount >= config.min_stake, ErrorCode::StakeTooLow);
+        validate_session_params(
+            &config,
+            ctx.accounts.user.key(),
+            duration_minutes,
+            &tasks,
+            partner,
+            session_key.as_ref(),
+        )?;
 
-        let user_state = &mut ctx.accounts.user_state;
         let global_state = &mut ctx.accounts.global_state;
 
-        require!(!user_state.is_active, ErrorCode::SessionAlreadyActive);
-
         // calc amounts
-        let focus_pool_amount = stake_amount / 100;
+        let focus_pool_amount = config.focus_pool_fee(stake_amount)?;
         let vault_amount = stake_amount
             .checked_sub(focus_pool_amount)
             .ok_or(ErrorCode::MathError)?;
 
-        // transfer 1% to focus pool vault
+        // transfer the fee (1% by default) to focus pool vault
         let to_focus_ctx = CpiContext::new(
             ctx.accounts.system_program.to_account_info(),
             system_program::Transfer {
@@ -127,7 +218,7 @@ pub mod deepwork {
         );
         system_program::transfer(to_focus_ctx, focus_pool_amount)?;
 
-        // transfer 99% to main escrow vault
+        // transfer the rest to main escrow vault
         let to_vault_ctx = CpiContext::new(
             ctx.accounts.system_program.to_account_info(),
             system_program::Transfer {
@@ -139,15 +230,118 @@ pub mod deepwork {
 
         global_state.focus_pool += focus_pool_amount;
         global_state.total_sessions += 1;
+        global_state.lock_stake(vault_amount)?;
+
+        open_focus_session(
+            &mut ctx.accounts.user_state,
+            &mut ctx.accounts.focus_session,
+            ctx.accounts.user.key(),
+            None,
+            vault_amount,
+            duration_minutes,
+            tasks,
+            partner,
+            session_key,
+            ctx.accounts.payer.key(),
+            &config,
+        )?;
+        ctx.accounts
+            .user_stats
+            .record_start(&ctx.accounts.focus_session, stake_amount)?;
+
+        emit!(SessionStarted::new(
+            &ctx.accounts.focus_session,
+            focus_pool_amount,
+            ctx.accounts.global_state.focus_pool,
+        ));
+
+        Ok(())
+    }
+
+    pub fn start_focus_session_token(
+        ctx: Context<StartFocusSessionToken>,
+        stake_amount: u64,
+        duration_minutes: u64,
+        tasks: Vec<Task>,
+        partner: Option<Pubkey>,
+        session_key: Option<SessionKey>,
+    ) -> Result<()> {
+        require!(
+            !ctx.accounts
+                .global_state
+                .circuit_breaker
+                .new_sessions_paused,
+            ErrorCode::NewSessionsPaused
+        );
+        let config = ctx.accounts.global_state.config.clone();
+        let mint_config = &mut ctx.accounts.mint_config;
+        require!(mint_config.enabled, ErrorCode::MintNotAllowed);
+        require!(
+            stake_amount >= mint_config.min_stake,
+            ErrorCode::StakeTooLow
+        );
+        validate_session_params(
+            &config,
+            ctx.accounts.user.key(),
+            duration_minutes,
+            &tasks,
+            partner,
+            session_key.as_ref(),
+        )?;
+
+        let focus_pool_amount = config.focus_pool_fee(stake_amount)?;
+        let vault_amount = stake_amount
+            .checked_sub(focus_pool_amount)
+            .ok_or(ErrorCode::MathError)?;
+
+        let decimals = ctx.accounts.mint.decimals;
+        for (to, amount) in [
+            (&ctx.accounts.token_focus_pool, focus_pool_amount),
+            (&ctx.accounts.token_vault, vault_amount),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(cpi_ctx, amount, decimals)?;
+        }
+
+        mint_config.focus_pool = mint_config
+            .focus_pool
+            .checked_add(focus_pool_amount)
+            .ok_or(ErrorCode::MathError)?;
+        ctx.accounts.global_state.total_sessions += 1;
+
+        open_focus_session(
+            &mut ctx.accounts.user_state,
+            &mut ctx.accounts.focus_session,
+            ctx.accounts.user.key(),
+            Some(ctx.accounts.mint.key()),
+            vault_amount,
+            duration_minutes,
+            tasks,
+            partner,
+            session_key,
+            ctx.accounts.payer.key(),
+            &config,
+        )?;
+        ctx.accounts
+            .user_stats
+            .record_start(&ctx.accounts.focus_session, stake_amount)?;
 
-        // update user state
-        user_state.user = ctx.accounts.user.key();
-        user_state.is_active = true;
-        user_state.stake_amount = vault_amount;
-        user_state.start_time = Clock::get()?.unix_timestamp;
-        user_state.duration_minutes = duration_minutes;
-        user_state.pending_balance = 0;
-        user_state.tasks = tasks;
+        emit!(SessionStarted::new(
+            &ctx.accounts.focus_session,
+            focus_pool_amount,
+            ctx.accounts.mint_config.focus_pool,
+        ));
 
         Ok(())
     }
@@ -162,607 +356,4233 @@ pub mod deepwork {
             description: "Focus Session".to_string(),
             completed: false,
         };
-        start_focus_session(ctx, stake_amount, duration_minutes, vec![default_task])
+        start_focus_session(
+            ctx,
+            stake_amount,
+            duration_minutes,
+            vec![default_task],
+            None,
+            None,
+        )
     }
 
     pub fn complete_focus_session(ctx: Context<CompleteFocusSession>) -> Result<()> {
-        let user_state = &mut ctx.accounts.user_state;
-        let _global_state = &ctx.accounts.global_state;
+        let focus_session = &mut ctx.accounts.focus_session;
+        let global_state = &mut ctx.accounts.global_state;
 
-        require!(user_state.is_active, ErrorCode::NoActiveSession);
+        require!(focus_session.is_active, ErrorCode::NoActiveSession);
+        require!(!focus_session.is_paused(), ErrorCode::SessionPaused);
 
         let current_time = Clock::get()?.unix_timestamp;
-        let elapsed_minutes = (current_time - user_state.start_time) / 60;
+        focus_session.require_completable(global_state.config.grace_minutes, current_time)?;
 
-        // allow a small grace window for completion confirmation
-        const GRACE_MINUTES: i64 = 5;
-        let required_minutes = (user_state.duration_minutes as i64).saturating_sub(GRACE_MINUTES);
-        require!(
-            elapsed_minutes >= required_minutes,
-            ErrorCode::SessionNotComplete
-        );
+        focus_session.require_signer(ctx.accounts.signer.key(), current_time)?;
+        focus_session.require_heartbeat_coverage(current_time)?;
+        focus_session.require_challenges_answered(current_time)?;
+
+        // with an attestor registered, the desktop agent's signed report gates the
+        // completion and scales the refund
+        if let Some(attestor) = global_state.attestor {
+            let report = attested_focus_report(&ctx.accounts.instructions, &attestor)?;
+            focus_session.attested_focus_bps =
+                focus_session.attested_focus_share(&report, &global_state.config, current_time)?;
+        }
+
+        // completed SOL sessions earn a share of the epoch's failure-pool inflow
+        let epoch = &mut ctx.accounts.epoch;
+        let epoch_share = &mut ctx.accounts.epoch_share;
+        epoch_share.epoch_id = epoch.epoch_id;
+        epoch_share.user = ctx.accounts.user.key();
+        if focus_session.mint.is_none() {
+            let shares =
+                focus_session.stake_amount as u128 * focus_session.duration_minutes as u128;
+            epoch.total_shares = epoch
+                .total_shares
+                .checked_add(shares)
+                .ok_or(ErrorCode::MathError)?;
+            epoch_share.shares = epoch_share
+                .shares
+                .checked_add(shares)
+                .ok_or(ErrorCode::MathError)?;
+        }
 
         // Don't automatically return SOL - mark session as complete but keep stake locked
         // Move the current session's stake into pending_balance so user can start another session
         // without overwriting the previous session's claimable funds.
-        user_state.is_active = false;
-        user_state.pending_balance = user_state
+        let stake_amount = focus_session.stake_amount;
+        if focus_session.mint.is_none() {
+            global_state.release_stake(stake_amount)?;
+            global_state.credit_pending(stake_amount)?;
+        }
+        let user_stats = &mut ctx.accounts.user_stats;
+        let extends_streak = user_stats.record_completed(
+            focus_session,
+            focus_session.extends_streak(global_state.config.grace_minutes, current_time),
+            current_time,
+        )?;
+        // the streak bonus is paid in SOL from the focus pool at claim
+        if extends_streak && focus_session.mint.is_none() {
+            focus_session.streak_bonus_bps =
+                global_state.streak_bonus_bps(user_stats.current_streak);
+        }
+        focus_session.is_active = false;
+        focus_session.pending_balance = focus_session
             .pending_balance
-            .checked_add(user_state.stake_amount)
+            .checked_add(focus_session.stake_amount)
             .ok_or(ErrorCode::MathError)?;
-        user_state.stake_amount = 0;
+        focus_session.stake_amount = 0;
+
+        emit!(SessionCompleted {
+            user: focus_session.user,
+            session_id: focus_session.session_id,
+            mint: focus_session.mint,
+            stake_amount,
+            returned_amount: 0,
+            pending_balance: focus_session.pending_balance,
+            duration_minutes: focus_session.duration_minutes,
+            focused_seconds: focus_session.focused_seconds(current_time),
+            task_count: focus_session.tasks.len() as u8,
+            timestamp: current_time,
+        });
 
         Ok(())
     }
 
-    // Backwards-compatible completion that returns stake and closes user_state (legacy behavior)
+    // Backwards-compatible completion that returns stake and closes the session (legacy behavior)
     pub fn complete_focus_session_v1(ctx: Context<CompleteFocusSessionClose>) -> Result<()> {
-        let user_state = &mut ctx.accounts.user_state;
-        let _global_state = &ctx.accounts.global_state;
+        let focus_session = &mut ctx.accounts.focus_session;
+        let global_state = &mut ctx.accounts.global_state;
 
-        require!(user_state.is_active, ErrorCode::NoActiveSession);
+        require!(focus_session.is_active, ErrorCode::NoActiveSession);
+        require!(!focus_session.is_paused(), ErrorCode::SessionPaused);
 
         let current_time = Clock::get()?.unix_timestamp;
-        let elapsed_minutes = (current_time - user_state.start_time) / 60;
-
-        const GRACE_MINUTES: i64 = 5;
-        let required_minutes = (user_state.duration_minutes as i64).saturating_sub(GRACE_MINUTES);
+        focus_session.require_completable(global_state.config.grace_minutes, current_time)?;
+        focus_session.require_heartbeat_coverage(current_time)?;
+        focus_session.require_challenges_answered(current_time)?;
+        // the legacy flow can't carry a focus report
         require!(
-            elapsed_minutes >= required_minutes,
-            ErrorCode::SessionNotComplete
+            global_state.attestor.is_none(),
+            ErrorCode::FocusReportRequired
         );
 
-        let return_amount = user_state.stake_amount;
+        let return_amount = focus_session.stake_amount;
         let from = ctx.accounts.vault.to_account_info();
         let to = ctx.accounts.user.to_account_info();
         **from.try_borrow_mut_lamports()? -= return_amount;
         **to.try_borrow_mut_lamports()? += return_amount;
+        global_state.release_stake(return_amount)?;
+
+        let full_length =
+            focus_session.extends_streak(global_state.config.grace_minutes, current_time);
+        let user_stats = &mut ctx.accounts.user_stats;
+        user_stats.record_completed(focus_session, full_length, current_time)?;
+        user_stats.record_payout(focus_session, return_amount, 0)?;
 
-        user_state.is_active = false;
-        user_state.stake_amount = 0;
+        focus_session.is_active = false;
+        focus_session.stake_amount = 0;
+
+        emit!(SessionCompleted {
+            user: focus_session.user,
+            session_id: focus_session.session_id,
+            mint: focus_session.mint,
+            stake_amount: return_amount,
+            returned_amount: return_amount,
+            pending_balance: focus_session.pending_balance,
+            duration_minutes: focus_session.duration_minutes,
+            focused_seconds: focus_session.focused_seconds(current_time),
+            task_count: focus_session.tasks.len() as u8,
+            timestamp: current_time,
+        });
 
         Ok(())
     }
 
     pub fn fail_focus_session(ctx: Context<FailFocusSession>) -> Result<()> {
-        let user_state = &mut ctx.accounts.user_state;
+        let focus_session = &mut ctx.accounts.focus_session;
         let global_state = &mut ctx.accounts.global_state;
 
-        require!(user_state.is_active, ErrorCode::NoActiveSession);
+        require!(focus_session.is_active, ErrorCode::NoActiveSession);
+
+        // voluntary early exit refunds part of the stake according to the refund curve
+        let current_time = Clock::get()?.unix_timestamp;
+        let refund_amount =
+            focus_session.early_exit_refund(&global_state.refund_curve, current_time)?;
+        let amount = focus_session
+            .stake_amount
+            .checked_sub(refund_amount)
+            .ok_or(ErrorCode::MathError)?;
+
+        if refund_amount > 0 {
+            let from = ctx.accounts.vault.to_account_info();
+            let to = ctx.accounts.user.to_account_info();
+            **from.try_borrow_mut_lamports()? -= refund_amount;
+            **to.try_borrow_mut_lamports()? += refund_amount;
+        }
 
-        // move 99% from vault to failure pool vault (direct lamport mutation)
-        let amount = user_state.stake_amount;
+        // move the rest from vault to failure pool vault (direct lamport mutation)
         let from = ctx.accounts.vault.to_account_info();
         let to = ctx.accounts.failure_pool_vault.to_account_info();
         **from.try_borrow_mut_lamports()? -= amount;
         **to.try_borrow_mut_lamports()? += amount;
 
-        global_state.failure_pool += amount;
+        global_state.credit_failure_pool(amount)?;
+        global_state.release_stake(focus_session.stake_amount)?;
+        ctx.accounts.user_stats.record_failed(
+            focus_session,
+            current_time,
+            refund_amount,
+            amount,
+        )?;
+
+        emit!(SessionFailed::new(
+            focus_session,
+            refund_amount,
+            amount,
+            global_state.failure_pool,
+            current_time,
+        ));
 
-        // reset user state
-        user_state.is_active = false;
-        user_state.stake_amount = 0;
+        // reset session state
+        focus_session.is_active = false;
+        focus_session.stake_amount = 0;
 
         Ok(())
     }
 
     pub fn expire_focus_session(ctx: Context<ExpireFocusSession>) -> Result<()> {
-        let user_state = &mut ctx.accounts.user_state;
+        let focus_session = &mut ctx.accounts.focus_session;
         let global_state = &mut ctx.accounts.global_state;
 
-        require!(user_state.is_active, ErrorCode::NoActiveSession);
+        require!(focus_session.is_active, ErrorCode::NoActiveSession);
 
         let current_time = Clock::get()?.unix_timestamp;
-        let elapsed_minutes = (current_time - user_state.start_time) / 60;
-        const GRACE_MINUTES: i64 = 5;
-        let required_minutes = user_state.duration_minutes as i64 + GRACE_MINUTES;
-        require!(
-            elapsed_minutes >= required_minutes,
-            ErrorCode::SessionNotComplete
-        );
+        focus_session.require_expirable(global_state.config.grace_minutes, current_time)?;
+
+        // the caller earns a cut of the forfeited stake for cranking the expiry
+        let bounty = global_state
+            .config
+            .keeper_bounty(focus_session.stake_amount)?;
+        let amount = focus_session
+            .stake_amount
+            .checked_sub(bounty)
+            .ok_or(ErrorCode::MathError)?;
 
-        // move funds to failure pool vault (direct lamport mutation)
-        let amount = user_state.stake_amount;
+        // move funds to keeper and failure pool vault (direct lamport mutation)
         let from = ctx.accounts.vault.to_account_info();
+        let to = ctx.accounts.keeper.to_account_info();
+        **from.try_borrow_mut_lamports()? -= bounty;
+        **to.try_borrow_mut_lamports()? += bounty;
+
         let to = ctx.accounts.failure_pool_vault.to_account_info();
         **from.try_borrow_mut_lamports()? -= amount;
         **to.try_borrow_mut_lamports()? += amount;
 
-        global_state.failure_pool += amount;
+        global_state.credit_failure_pool(amount)?;
+        global_state.release_stake(focus_session.stake_amount)?;
+        ctx.accounts.user_stats.record_expired(focus_session)?;
+
+        emit!(SessionExpired::new(
+            focus_session,
+            amount,
+            ctx.accounts.keeper.key(),
+            bounty,
+            global_state.failure_pool,
+            current_time,
+        ));
 
-        user_state.is_active = false;
-        user_state.stake_amount = 0;
+        focus_session.is_active = false;
+        focus_session.stake_amount = 0;
 
         Ok(())
     }
 
-    pub fn withdraw_focus_pool(ctx: Context<WithdrawFocusPool>, amount: u64) -> Result<()> {
-        require_keys_eq!(
-            ctx.accounts.global_state.authority,
-            ctx.accounts.authority.key()
+    // remaining_accounts: (user, focus_session, user_stats, rent_payer) groups,
+    // user read-only, the rest writable
+    pub fn expire_focus_sessions_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExpireFocusSessionsBatch<'info>>,
+    ) -> Result<()> {
+        require!(
+            ctx.remaining_accounts.len() % 4 == 0,
+            ErrorCode::InvalidBatchAccounts
         );
 
-        let gs = &ctx.accounts.global_state;
-        let gs_key = gs.key();
-        let seeds = &[b"focus_pool_vault", gs_key.as_ref(), &[gs.focus_pool_bump]];
-        let _signer = &[&seeds[..]];
+        let current_time = Clock::get()?.unix_timestamp;
+        let config = ctx.accounts.global_state.config.clone();
+        let keeper = ctx.accounts.keeper.key();
 
-        // ensure we don't drop below rent-exempt balance
-        let from = ctx.accounts.focus_pool_vault.to_account_info();
-        let rent_min = Rent::get()?.minimum_balance(0);
-        let lamports = **from.lamports.borrow();
-        let available = lamports.saturating_sub(rent_min);
-        require!(amount <= available, ErrorCode::MathError);
-        // move from focus pool vault to recipient (direct lamport mutation)
-        let to = ctx.accounts.recipient.to_account_info();
-        **from.try_borrow_mut_lamports()? -= amount;
-        **to.try_borrow_mut_lamports()? += amount;
+        let mut total_bounty: u64 = 0;
+        let mut total_forfeited: u64 = 0;
+        let mut total_stake: u64 = 0;
+        let mut events = Vec::new();
+        for group in ctx.remaining_accounts.chunks(4) {
+            let (user, session_info, stats_info, rent_payer) =
+                (&group[0], &group[1], &group[2], &group[3]);
+            // sessions settled since the keeper built the batch are already closed
+            if session_info.owner != ctx.program_id {
+                continue;
+            }
+            let Ok(focus_session) = Account::<'info, FocusSession>::try_from(session_info) else {
+                continue;
+            };
+            let (expected, _) = Pubkey::find_program_address(
+                &[
+                    b"focus_session",
+                    user.key().as_ref(),
+                    &focus_session.session_id.to_le_bytes(),
+                ],
+                ctx.program_id,
+            );
+            require_keys_eq!(
+                session_info.key(),
+                expected,
+                ErrorCode::InvalidBatchAccounts
+            );
+
+            if !focus_session.is_active || focus_session.mint.is_some() {
+                continue;
+            }
+            // same rules as expire_focus_session; anything not yet expirable is left alone
+            if focus_session
+                .require_expirable(config.grace_minutes, current_time)
+                .is_err()
+            {
+                continue;
+            }
+
+            let bounty = config.keeper_bounty(focus_session.stake_amount)?;
+            let amount = focus_session
+                .stake_amount
+                .checked_sub(bounty)
+                .ok_or(ErrorCode::MathError)?;
+            total_bounty = total_bounty
+                .checked_add(bounty)
+                .ok_or(ErrorCode::MathError)?;
+            total_forfeited = total_forfeited
+                .checked_add(amount)
+                .ok_or(ErrorCode::MathError)?;
+            total_stake = total_stake
+                .checked_add(focus_session.stake_amount)
+                .ok_or(ErrorCode::MathError)?;
+            events.push(SessionExpired::new(
+                &focus_session,
+                amount,
+                keeper,
+                bounty,
+                0,
+                current_time,
+            ));
+
+            let mut user_stats: Account<'info, UserStats> = Account::try_from(stats_info)?;
+            let (expected, _) =
+                Pubkey::find_program_address(&[b"user_stats", user.key().as_ref()], ctx.program_id);
+            require_keys_eq!(stats_info.key(), expected, ErrorCode::InvalidBatchAccounts);
+            user_stats.record_expired(&focus_session)?;
+            user_stats.exit(ctx.program_id)?;
+
+            require_keys_eq!(
+                rent_payer.key(),
+                focus_session.rent_payer,
+                ErrorCode::InvalidBatchAccounts
+            );
+            focus_session.close(rent_payer.clone())?;
+        }
+
+        let from = ctx.accounts.vault.to_account_info();
+        let to = ctx.accounts.keeper.to_account_info();
+        **from.try_borrow_mut_lamports()? -= total_bounty;
+        **to.try_borrow_mut_lamports()? += total_bounty;
+
+        let to = ctx.accounts.failure_pool_vault.to_account_info();
+        **from.try_borrow_mut_lamports()? -= total_forfeited;
+        **to.try_borrow_mut_lamports()? += total_forfeited;
 
         let global_state = &mut ctx.accounts.global_state;
-        global_state.focus_pool = global_state.focus_pool.saturating_sub(amount);
+        global_state.credit_failure_pool(total_forfeited)?;
+        global_state.release_stake(total_stake)?;
+
+        for event in events {
+            emit!(SessionExpired {
+                failure_pool: global_state.failure_pool,
+                ..event
+            });
+        }
 
         Ok(())
     }
 
-    pub fn withdraw_failure_pool(ctx: Context<WithdrawFailurePool>, amount: u64) -> Result<()> {
-        require_keys_eq!(
-            ctx.accounts.global_state.authority,
-            ctx.accounts.authority.key()
-        );
+    pub fn pause_focus_session(ctx: Context<PauseFocusSession>) -> Result<()> {
+        let focus_session = &mut ctx.accounts.focus_session;
 
-        let gs = &ctx.accounts.global_state;
-        let gs_key = gs.key();
-        let seeds = &[
-            b"failure_pool_vault",
-            gs_key.as_ref(),
-            &[gs.failure_pool_bump],
-        ];
-        let _signer = &[&seeds[..]];
+        require!(focus_session.is_active, ErrorCode::NoActiveSession);
+        require!(!focus_session.is_paused(), ErrorCode::SessionPaused);
+        require!(
+            focus_session.pauses.len() < MAX_PAUSES,
+            ErrorCode::TooManyPauses
+        );
 
-        // ensure we don't drop below rent-exempt balance
-        let from = ctx.accounts.failure_pool_vault.to_account_info();
-        let rent_min = Rent::get()?.minimum_balance(0);
-        let lamports = **from.lamports.borrow();
-        let available = lamports.saturating_sub(rent_min);
-        require!(amount <= available, ErrorCode::MathError);
-        // move from failure pool vault to recipient (direct lamport mutation)
-        let to = ctx.accounts.recipient.to_account_info();
-        **from.try_borrow_mut_lamports()? -= amount;
-        **to.try_borrow_mut_lamports()? += amount;
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            focus_session.paused_seconds(current_time) < focus_session.max_pause_seconds as i64,
+            ErrorCode::PauseBudgetExhausted
+        );
+        require!(
+            !focus_session.heartbeat_lapsed(current_time),
+            ErrorCode::HeartbeatLapsed
+        );
 
-        let global_state = &mut ctx.accounts.global_state;
-        global_state.failure_pool = global_state.failure_pool.saturating_sub(amount);
+        focus_session.paused_at = current_time;
 
         Ok(())
     }
 
-    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
-        let user_state = &mut ctx.accounts.user_state;
-        let global_state = &mut ctx.accounts.global_state;
-
-        require!(!user_state.is_active, ErrorCode::SessionStillActive);
+    pub fn resume_focus_session(ctx: Context<ResumeFocusSession>) -> Result<()> {
+        let focus_session = &mut ctx.accounts.focus_session;
 
-        let total_tasks = user_state.tasks.len() as u64;
-        let completed_tasks = user_state.tasks.iter().filter(|t| t.completed).count() as u64;
+        require!(focus_session.is_active, ErrorCode::NoActiveSession);
+        require!(focus_session.is_paused(), ErrorCode::SessionNotPaused);
 
-        require!(total_tasks > 0, ErrorCode::NoTasksProvided);
-        require!(user_state.pending_balance > 0, ErrorCode::NoPendingBalance);
+        // record the full interval; anything beyond the budget is counted as focus time
+        let current_time = Clock::get()?.unix_timestamp;
+        let interval = PauseInterval {
+            start: focus_session.paused_at,
+            end: current_time,
+        };
+        focus_session.pauses.push(interval);
+        focus_session.paused_at = 0;
 
-        // Calculate reward: SOL refunded based on completed tasks
-        // If all tasks completed, get full stake back
-        // If some tasks incomplete, lose a percentage
-        let refund_percentage = (completed_tasks * 100) / total_tasks;
-        let refund_amount = (user_state.pending_balance * refund_percentage) / 100;
+        Ok(())
+    }
 
-        // Penalty goes to failure pool
-        let penalty_amount = user_state.pending_balance.saturating_sub(refund_amount);
+    // Proof of focus, sent every heartbeat interval while the session runs
+    pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+        let focus_session = &mut ctx.accounts.focus_session;
+        let current_time = Clock::get()?.unix_timestamp;
 
-        if refund_amount > 0 {
-            // Transfer refund to user
-            let from = ctx.accounts.vault.to_account_info();
-            let to = ctx.accounts.user.to_account_info();
-            **from.try_borrow_mut_lamports()? -= refund_amount;
-            **to.try_borrow_mut_lamports()? += refund_amount;
-        }
+        focus_session.require_signer(ctx.accounts.signer.key(), current_time)?;
+        require!(focus_session.is_active, ErrorCode::NoActiveSession);
+        require!(!focus_session.is_paused(), ErrorCode::SessionPaused);
+        require!(
+            focus_session.heartbeat_interval_seconds > 0,
+            ErrorCode::HeartbeatsDisabled
+        );
+        require!(
+            !focus_session.heartbeat_lapsed(current_time),
+            ErrorCode::HeartbeatLapsed
+        );
 
-        if penalty_amount > 0 {
-            // Transfer penalty to failure pool
-            let from = ctx.accounts.vault.to_account_info();
-            let to = ctx.accounts.failure_pool_vault.to_account_info();
-            **from.try_borrow_mut_lamports()? -= penalty_amount;
-            **to.try_borrow_mut_lamports()? += penalty_amount;
-            global_state.failure_pool = global_state
-                .failure_pool
-                .checked_add(penalty_amount)
-                .ok_or(ErrorCode::MathError)?;
-        }
+        require!(
+            !focus_session.missed_challenge(current_time),
+            ErrorCode::ChallengeMissed
+        );
+        focus_session.record_heartbeat(current_time);
 
-        // Reset claim-related state so user can continue using the same PDA
-        user_state.pending_balance = 0;
-        user_state.tasks.clear();
+        let slot_hashes = ctx.accounts.slot_hashes.try_borrow_data()?;
+        // entries are (slot, hash) after a u64 length, newest first
+        require!(slot_hashes.len() >= 48, ErrorCode::SlotHashesUnavailable);
+        focus_session.draw_challenge(&slot_hashes[16..48], current_time);
 
         Ok(())
     }
 
-    pub fn update_task(ctx: Context<UpdateTask>, task_index: u8, completed: bool) -> Result<()> {
-        let user_state = &mut ctx.accounts.user_state;
+    // Answers the session's open spot-check inside its window
+    pub fn answer_challenge(ctx: Context<AnswerChallenge>) -> Result<()> {
+        let focus_session = &mut ctx.accounts.focus_session;
+        let current_time = Clock::get()?.unix_timestamp;
 
-        require!(!user_state.is_active, ErrorCode::SessionStillActive);
+        focus_session.require_signer(ctx.accounts.signer.key(), current_time)?;
+        require!(focus_session.is_active, ErrorCode::NoActiveSession);
         require!(
-            (task_index as usize) < user_state.tasks.len(),
-            ErrorCode::InvalidTaskIndex
+            !focus_session.missed_challenge(current_time),
+            ErrorCode::ChallengeMissed
+        );
+        let challenge = focus_session.challenge.ok_or(ErrorCode::NoOpenChallenge)?;
+        require!(
+            current_time >= challenge.opens_at,
+            ErrorCode::NoOpenChallenge
+        );
+        focus_session.challenge = None;
+
+        Ok(())
+    }
+
+    // First step of an authority transfer; `None` cancels a pending proposal
+    pub fn propose_authority(
+        ctx: Context<ProposeAuthority>,
+        new_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
         );
 
-        user_state.tasks[task_index as usize].completed = completed;
+        ctx.accounts.global_state.pending_authority = new_authority;
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init_if_needed,
-        payer = authority,
-        space = 8 + GlobalState::INIT_SPACE,
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+        require!(
+            global_state.pending_authority == Some(ctx.accounts.new_authority.key()),
+            ErrorCode::NotPendingAuthority
+        );
 
-    /// CHECK: Vault PDA to hold staked SOL
-    #[account(
-        mut,
-        seeds = [b"vault", global_state.key().as_ref()],
-        bump
-    )]
-    pub vault: UncheckedAccount<'info>,
+        global_state.authority = ctx.accounts.new_authority.key();
+        global_state.pending_authority = None;
 
-    /// CHECK: Focus pool vault PDA (1% fees)
-    #[account(
-        mut,
-        seeds = [b"focus_pool_vault", global_state.key().as_ref()],
-        bump
-    )]
-    pub focus_pool_vault: UncheckedAccount<'info>,
+        Ok(())
+    }
 
-    /// CHECK: Failure pool vault PDA (failed stakes)
-    #[account(
-        mut,
-        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
-        bump
-    )]
-    pub failure_pool_vault: UncheckedAccount<'info>,
+    pub fn update_config(ctx: Context<UpdateConfig>, config: Config) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+        config.validate()?;
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        ctx.accounts.global_state.config = Config {
+            version: CONFIG_VERSION,
+            ..config
+        };
 
-#[derive(Accounts)]
-pub struct StartFocusSession<'info> {
-    #[account(
-        init_if_needed,
-        payer = user,
-        space = 8 + UserState::INIT_SPACE,
-        seeds = [b"user_state", user.key().as_ref()],
-        bump
-    )]
-    pub user_state: Account<'info, UserState>,
+        Ok(())
+    }
 
-    #[account(
+    /// Upgrades the first deployment's global state: grows the account to the
+    /// current size, keeps its pools and counters, and fills everything added since
+    /// with defaults. Run initialize afterwards to open reward epoch 0, then
+    /// migrate_user_state for each user.
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        let info = ctx.accounts.global_state.to_account_info();
+        let legacy = GlobalStateV0::load(&info.try_borrow_data()?)?;
+        require_keys_eq!(legacy.authority, ctx.accounts.authority.key());
+
+        // the first deployment kept no totals; everything in the vault belongs to its
+        // sessions, counted as active until migrate_user_state finds them completed
+        let rent = Rent::get()?;
+        let vault_owed = ctx
+            .accounts
+            .vault
+            .lamports()
+            .saturating_sub(rent.minimum_balance(0));
+        let global_state = legacy.migrate(vault_owed);
+
+        // the authority pays the rent for the extra space
+        let space = 8 + GlobalState::INIT_SPACE;
+        let top_up = rent.minimum_balance(space).saturating_sub(info.lamports());
+        if top_up > 0 {
+            let top_up_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: info.clone(),
+                },
+            );
+            system_program::transfer(top_up_ctx, top_up)?;
+        }
+        info.resize(space)?;
+
+        let mut data = info.try_borrow_mut_data()?;
+        data.fill(0);
+        global_state.try_serialize(&mut &mut data[..])?;
+
+        Ok(())
+    }
+
+    /// Converts a user state still holding the user's only session, as first deployed,
+    /// into the current layout; a session that is active or awaiting a claim becomes
+    /// focus_session 0. Anyone can crank it, paying that session's rent.
+    pub fn migrate_user_state(ctx: Context<MigrateUserState>) -> Result<()> {
+        let info = ctx.accounts.user_state.to_account_info();
+        let legacy = UserStateV0::load(&info.try_borrow_data()?)?;
+        let user = ctx.accounts.user.key();
+        require_keys_eq!(legacy.user, user, ErrorCode::UnknownStateLayout);
+        let rent = Rent::get()?;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        let has_session = legacy.is_active || legacy.pending_balance > 0;
+        if has_session {
+            let session_info = ctx.accounts.focus_session.to_account_info();
+            let space = 8 + FocusSession::INIT_SPACE;
+            let session_id = 0u64.to_le_bytes();
+            let bump = [ctx.bumps.focus_session];
+            let seeds: &[&[u8]] = &[b"focus_session", user.as_ref(), &session_id, &bump];
+            let signer_seeds = &[seeds];
+            let create_ctx = CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: session_info.clone(),
+                },
+                signer_seeds,
+            );
+            system_program::create_account(
+                create_ctx,
+                rent.minimum_balance(space),
+                space as u64,
+                ctx.program_id,
+            )?;
+            let focus_session = legacy.migrate(ctx.accounts.payer.key());
+            focus_session.try_serialize(&mut &mut session_info.try_borrow_mut_data()?[..])?;
+
+            let user_stats = &mut ctx.accounts.user_stats;
+            user_stats.record_start(&focus_session, focus_session.principal()?)?;
+            if !legacy.is_active {
+                // migrate_config counted the stake as active, but it was completed already
+                let global_state = &mut ctx.accounts.global_state;
+                global_state.release_stake(legacy.pending_balance)?;
+                global_state.credit_pending(legacy.pending_balance)?;
+                user_stats.record_completed(&focus_session, false, current_time)?;
+            }
+        }
+        ctx.accounts.user_stats.user = user;
+
+        // the old layout held the session itself, so the rent it no longer needs
+        // goes back to the user
+        let space = 8 + UserState::INIT_SPACE;
+        info.resize(space)?;
+        let surplus = info.lamports().saturating_sub(rent.minimum_balance(space));
+        **info.try_borrow_mut_lamports()? -= surplus;
+        **ctx.accounts.user.try_borrow_mut_lamports()? += surplus;
+
+        let user_state = UserState {
+            user,
+            next_session_id: has_session as u64,
+        };
+        let mut data = info.try_borrow_mut_data()?;
+        data.fill(0);
+        user_state.try_serialize(&mut &mut data[..])?;
+
+        Ok(())
+    }
+
+    pub fn set_refund_curve(ctx: Context<SetRefundCurve>, refund_curve: RefundCurve) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+        refund_curve.validate()?;
+
+        ctx.accounts.global_state.refund_curve = refund_curve;
+
+        Ok(())
+    }
+
+    pub fn set_streak_tiers(
+        ctx: Context<SetStreakTiers>,
+        streak_tiers: Vec<StreakTier>,
+    ) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+        validate_streak_tiers(&streak_tiers)?;
+
+        ctx.accounts.global_state.streak_tiers = streak_tiers;
+
+        Ok(())
+    }
+
+    pub fn set_attestor(ctx: Context<SetAttestor>, attestor: Option<Pubkey>) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+
+        ctx.accounts.global_state.attestor = attestor;
+
+        Ok(())
+    }
+
+    pub fn set_circuit_breaker(
+        ctx: Context<SetCircuitBreaker>,
+        circuit_breaker: CircuitBreaker,
+    ) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+
+        ctx.accounts.global_state.circuit_breaker = circuit_breaker;
+
+        Ok(())
+    }
+
+    // Incident exit: while new sessions are paused, users take back their principal
+    // (active stake plus unclaimed pending balance) with no penalty
+    pub fn emergency_withdraw(ctx: Context<EmergencyWithdraw>) -> Result<()> {
+        require!(
+            ctx.accounts
+                .global_state
+                .circuit_breaker
+                .new_sessions_paused,
+            ErrorCode::EmergencyWithdrawDisabled
+        );
+
+        let focus_session = &ctx.accounts.focus_session;
+        let amount = focus_session.principal()?;
+        let from = ctx.accounts.vault.to_account_info();
+        let to = ctx.accounts.user.to_account_info();
+        **from.try_borrow_mut_lamports()? -= amount;
+        **to.try_borrow_mut_lamports()? += amount;
+
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.release_stake(focus_session.stake_amount)?;
+        global_state.release_pending(focus_session.pending_balance)?;
+        ctx.accounts
+            .user_stats
+            .record_payout(focus_session, amount, 0)?;
+
+        emit!(EmergencyWithdrawn::new(
+            focus_session,
+            amount,
+            Clock::get()?.unix_timestamp,
+        ));
+
+        Ok(())
+    }
+
+    pub fn emergency_withdraw_token(ctx: Context<EmergencyWithdrawToken>) -> Result<()> {
+        require!(
+            ctx.accounts
+                .global_state
+                .circuit_breaker
+                .new_sessions_paused,
+            ErrorCode::EmergencyWithdrawDisabled
+        );
+
+        let amount = ctx.accounts.focus_session.principal()?;
+        let accounts = &ctx.accounts;
+        transfer_from_pool(
+            &accounts.token_program,
+            &accounts.token_vault,
+            &accounts.user_token_account,
+            &accounts.mint,
+            &accounts.global_state,
+            ctx.bumps.global_state,
+            amount,
+        )?;
+        ctx.accounts
+            .user_stats
+            .record_payout(&ctx.accounts.focus_session, amount, 0)?;
+
+        emit!(EmergencyWithdrawn::new(
+            &ctx.accounts.focus_session,
+            amount,
+            Clock::get()?.unix_timestamp,
+        ));
+
+        Ok(())
+    }
+
+    pub fn withdraw_focus_pool(ctx: Context<WithdrawFocusPool>, amount: u64) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+        require!(
+            !ctx.accounts.global_state.circuit_breaker.withdrawals_paused,
+            ErrorCode::WithdrawalsPaused
+        );
+
+        let gs = &ctx.accounts.global_state;
+        let gs_key = gs.key();
+        let seeds = &[b"focus_pool_vault", gs_key.as_ref(), &[gs.focus_pool_bump]];
+        let _signer = &[&seeds[..]];
+
+        // ensure we don't drop below rent-exempt balance
+        let from = ctx.accounts.focus_pool_vault.to_account_info();
+        let rent_min = Rent::get()?.minimum_balance(0);
+        let lamports = **from.lamports.borrow();
+        let available = lamports.saturating_sub(rent_min);
+        require!(amount <= available, ErrorCode::MathError);
+        // move from focus pool vault to recipient (direct lamport mutation)
+        let to = ctx.accounts.recipient.to_account_info();
+        **from.try_borrow_mut_lamports()? -= amount;
+        **to.try_borrow_mut_lamports()? += amount;
+
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.focus_pool = global_state.focus_pool.saturating_sub(amount);
+
+        emit!(PoolWithdrawn {
+            pool: PoolKind::Focus,
+            mint: None,
+            amount,
+            recipient: ctx.accounts.recipient.key(),
+            pool_balance: global_state.focus_pool,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_failure_pool(ctx: Context<WithdrawFailurePool>, amount: u64) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+        require!(
+            !ctx.accounts.global_state.circuit_breaker.withdrawals_paused,
+            ErrorCode::WithdrawalsPaused
+        );
+
+        let gs = &ctx.accounts.global_state;
+        let gs_key = gs.key();
+        let seeds = &[
+            b"failure_pool_vault",
+            gs_key.as_ref(),
+            &[gs.failure_pool_bump],
+        ];
+        let _signer = &[&seeds[..]];
+
+        // ensure we don't drop below rent-exempt balance
+        let from = ctx.accounts.failure_pool_vault.to_account_info();
+        let rent_min = Rent::get()?.minimum_balance(0);
+        let lamports = **from.lamports.borrow();
+        let available = lamports.saturating_sub(rent_min);
+        require!(amount <= available, ErrorCode::MathError);
+        // closed epochs' rewards belong to their participants
+        require!(
+            amount <= gs.failure_pool.saturating_sub(gs.failure_pool_reserved),
+            ErrorCode::PoolFundsReserved
+        );
+        // move from failure pool vault to recipient (direct lamport mutation)
+        let to = ctx.accounts.recipient.to_account_info();
+        **from.try_borrow_mut_lamports()? -= amount;
+        **to.try_borrow_mut_lamports()? += amount;
+
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.failure_pool = global_state.failure_pool.saturating_sub(amount);
+
+        emit!(PoolWithdrawn {
+            pool: PoolKind::Failure,
+            mint: None,
+            amount,
+            recipient: ctx.accounts.recipient.key(),
+            pool_balance: global_state.failure_pool,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless solvency check: compares the vaults against what GlobalState says
+    // they owe and pauses pool withdrawals if any of them comes up short
+    pub fn reconcile(ctx: Context<Reconcile>) -> Result<()> {
+        let rent_min = Rent::get()?.minimum_balance(0);
+        let vault_balance = ctx.accounts.vault.lamports().saturating_sub(rent_min);
+        let focus_pool_balance = ctx
+            .accounts
+            .focus_pool_vault
+            .lamports()
+            .saturating_sub(rent_min);
+        let failure_pool_balance = ctx
+            .accounts
+            .failure_pool_vault
+            .lamports()
+            .saturating_sub(rent_min);
+
+        let global_state = &mut ctx.accounts.global_state;
+        let vault_owed = global_state.total_owed()?;
+        // surpluses (e.g. lamports sent straight to a vault) are harmless
+        let shortfall = [
+            vault_owed.saturating_sub(vault_balance),
+            global_state.focus_pool.saturating_sub(focus_pool_balance),
+            global_state
+                .failure_pool
+                .saturating_sub(failure_pool_balance),
+        ]
+        .into_iter()
+        .try_fold(0u64, |total, missing| total.checked_add(missing))
+        .ok_or(ErrorCode::MathError)?;
+        if shortfall > 0 {
+            global_state.circuit_breaker.withdrawals_paused = true;
+        }
+
+        emit!(Reconciled {
+            vault_balance,
+            vault_owed,
+            focus_pool_balance,
+            focus_pool: global_state.focus_pool,
+            failure_pool_balance,
+            failure_pool: global_state.failure_pool,
+            shortfall,
+            withdrawals_paused: global_state.circuit_breaker.withdrawals_paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+        let focus_session = &mut ctx.accounts.focus_session;
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(!focus_session.is_active, ErrorCode::SessionStillActive);
+        require!(
+            !global_state.circuit_breaker.settlements_paused,
+            ErrorCode::SettlementsPaused
+        );
+
+        // SOL refunded based on completed tasks; penalty goes to failure pool
+        let (refund_amount, penalty_amount) = focus_session.claim_split()?;
+
+        if refund_amount > 0 {
+            // Transfer refund to user
+            let from = ctx.accounts.vault.to_account_info();
+            let to = ctx.accounts.user.to_account_info();
+            **from.try_borrow_mut_lamports()? -= refund_amount;
+            **to.try_borrow_mut_lamports()? += refund_amount;
+        }
+
+        if penalty_amount > 0 {
+            // Transfer penalty to failure pool
+            let from = ctx.accounts.vault.to_account_info();
+            let to = ctx.accounts.failure_pool_vault.to_account_info();
+            **from.try_borrow_mut_lamports()? -= penalty_amount;
+            **to.try_borrow_mut_lamports()? += penalty_amount;
+            global_state.credit_failure_pool(penalty_amount)?;
+        }
+        global_state.release_pending(focus_session.pending_balance)?;
+
+        // streak bonus comes out of the focus pool, as far as it reaches
+        let streak_bonus = focus_session
+            .streak_bonus(refund_amount)?
+            .min(global_state.focus_pool);
+        if streak_bonus > 0 {
+            let from = ctx.accounts.focus_pool_vault.to_account_info();
+            let to = ctx.accounts.user.to_account_info();
+            **from.try_borrow_mut_lamports()? -= streak_bonus;
+            **to.try_borrow_mut_lamports()? += streak_bonus;
+            global_state.focus_pool -= streak_bonus;
+        }
+
+        let user_stats = &mut ctx.accounts.user_stats;
+        user_stats.record_payout(focus_session, refund_amount, penalty_amount)?;
+        user_stats.total_streak_bonus = user_stats
+            .total_streak_bonus
+            .checked_add(streak_bonus)
+            .ok_or(ErrorCode::MathError)?;
+
+        emit!(RewardsClaimed::new(
+            focus_session,
+            refund_amount,
+            penalty_amount,
+            streak_bonus,
+            global_state.failure_pool,
+            global_state.focus_pool,
+            Clock::get()?.unix_timestamp,
+        ));
+
+        Ok(())
+    }
+
+    pub fn close_epoch(ctx: Context<CloseEpoch>) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+        let epoch = &mut ctx.accounts.epoch;
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            current_time >= epoch.start_time + global_state.config.epoch_duration_seconds,
+            ErrorCode::EpochNotOver
+        );
+
+        let inflow = global_state
+            .failure_pool_inflow
+            .checked_sub(epoch.inflow_start)
+            .ok_or(ErrorCode::MathError)?;
+
+        let next_epoch = &mut ctx.accounts.next_epoch;
+        next_epoch.epoch_id = epoch.epoch_id + 1;
+        next_epoch.start_time = current_time;
+        next_epoch.bump = ctx.bumps.next_epoch;
+
+        if epoch.total_shares > 0 {
+            // reserve the inflow for this epoch's participants, minus anything already withdrawn
+            let unreserved = global_state
+                .failure_pool
+                .saturating_sub(global_state.failure_pool_reserved);
+            epoch.rewards = inflow.min(unreserved);
+            global_state.failure_pool_reserved = global_state
+                .failure_pool_reserved
+                .checked_add(epoch.rewards)
+                .ok_or(ErrorCode::MathError)?;
+            next_epoch.inflow_start = global_state.failure_pool_inflow;
+        } else {
+            // nobody to pay: roll the inflow into the next epoch
+            next_epoch.inflow_start = epoch.inflow_start;
+        }
+
+        epoch.closed = true;
+        global_state.current_epoch = next_epoch.epoch_id;
+
+        Ok(())
+    }
+
+    pub fn claim_epoch_rewards(ctx: Context<ClaimEpochRewards>) -> Result<()> {
+        let epoch = &mut ctx.accounts.epoch;
+        let epoch_share = &ctx.accounts.epoch_share;
+
+        require!(epoch.closed, ErrorCode::EpochNotClosed);
+        require!(
+            !ctx.accounts.global_state.circuit_breaker.settlements_paused,
+            ErrorCode::SettlementsPaused
+        );
+
+        let reward = epoch.reward_for(epoch_share.shares)?;
+        if reward > 0 {
+            let from = ctx.accounts.failure_pool_vault.to_account_info();
+            let to = ctx.accounts.user.to_account_info();
+            **from.try_borrow_mut_lamports()? -= reward;
+            **to.try_borrow_mut_lamports()? += reward;
+        }
+
+        epoch.claimed_rewards = epoch
+            .claimed_rewards
+            .checked_add(reward)
+            .ok_or(ErrorCode::MathError)?;
+
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.failure_pool = global_state
+            .failure_pool
+            .checked_sub(reward)
+            .ok_or(ErrorCode::MathError)?;
+        global_state.failure_pool_reserved = global_state
+            .failure_pool_reserved
+            .checked_sub(reward)
+            .ok_or(ErrorCode::MathError)?;
+
+        emit!(EpochRewardsClaimed {
+            user: ctx.accounts.user.key(),
+            epoch_id: epoch.epoch_id,
+            shares: epoch_share.shares,
+            reward,
+            failure_pool: global_state.failure_pool,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn fail_focus_session_token(ctx: Context<FailFocusSessionToken>) -> Result<()> {
+        let focus_session = &mut ctx.accounts.focus_session;
+
+        require!(focus_session.is_active, ErrorCode::NoActiveSession);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let refund_amount = focus_session
+            .early_exit_refund(&ctx.accounts.global_state.refund_curve, current_time)?;
+        let amount = focus_session
+            .stake_amount
+            .checked_sub(refund_amount)
+            .ok_or(ErrorCode::MathError)?;
+
+        let accounts = &ctx.accounts;
+        let bump = ctx.bumps.global_state;
+        transfer_from_pool(
+            &accounts.token_program,
+            &accounts.token_vault,
+            &accounts.user_token_account,
+            &accounts.mint,
+            &accounts.global_state,
+            bump,
+            refund_amount,
+        )?;
+        transfer_from_pool(
+            &accounts.token_program,
+            &accounts.token_vault,
+            &accounts.token_failure_pool,
+            &accounts.mint,
+            &accounts.global_state,
+            bump,
+            amount,
+        )?;
+
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.failure_pool = mint_config
+            .failure_pool
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathError)?;
+
+        let focus_session = &mut ctx.accounts.focus_session;
+        ctx.accounts.user_stats.record_failed(
+            focus_session,
+            current_time,
+            refund_amount,
+            amount,
+        )?;
+        emit!(SessionFailed::new(
+            focus_session,
+            refund_amount,
+            amount,
+            mint_config.failure_pool,
+            current_time,
+        ));
+
+        focus_session.is_active = false;
+        focus_session.stake_amount = 0;
+
+        Ok(())
+    }
+
+    pub fn expire_focus_session_token(ctx: Context<ExpireFocusSessionToken>) -> Result<()> {
+        let focus_session = &ctx.accounts.focus_session;
+
+        require!(focus_session.is_active, ErrorCode::NoActiveSession);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        focus_session
+            .require_expirable(ctx.accounts.global_state.config.grace_minutes, current_time)?;
+
+        let bounty = ctx
+            .accounts
+            .global_state
+            .config
+            .keeper_bounty(focus_session.stake_amount)?;
+        let amount = focus_session
+            .stake_amount
+            .checked_sub(bounty)
+            .ok_or(ErrorCode::MathError)?;
+
+        let accounts = &ctx.accounts;
+        let bump = ctx.bumps.global_state;
+        transfer_from_pool(
+            &accounts.token_program,
+            &accounts.token_vault,
+            &accounts.keeper_token_account,
+            &accounts.mint,
+            &accounts.global_state,
+            bump,
+            bounty,
+        )?;
+        transfer_from_pool(
+            &accounts.token_program,
+            &accounts.token_vault,
+            &accounts.token_failure_pool,
+            &accounts.mint,
+            &accounts.global_state,
+            bump,
+            amount,
+        )?;
+
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.failure_pool = mint_config
+            .failure_pool
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathError)?;
+
+        let focus_session = &mut ctx.accounts.focus_session;
+        ctx.accounts.user_stats.record_expired(focus_session)?;
+        emit!(SessionExpired::new(
+            focus_session,
+            amount,
+            ctx.accounts.keeper.key(),
+            bounty,
+            mint_config.failure_pool,
+            current_time,
+        ));
+
+        focus_session.is_active = false;
+        focus_session.stake_amount = 0;
+
+        Ok(())
+    }
+
+    pub fn claim_rewards_token(ctx: Context<ClaimRewardsToken>) -> Result<()> {
+        let focus_session = &ctx.accounts.focus_session;
+
+        require!(!focus_session.is_active, ErrorCode::SessionStillActive);
+        require!(
+            !ctx.accounts.global_state.circuit_breaker.settlements_paused,
+            ErrorCode::SettlementsPaused
+        );
+
+        let (refund_amount, penalty_amount) = focus_session.claim_split()?;
+
+        let accounts = &ctx.accounts;
+        let bump = ctx.bumps.global_state;
+        transfer_from_pool(
+            &accounts.token_program,
+            &accounts.token_vault,
+            &accounts.user_token_account,
+            &accounts.mint,
+            &accounts.global_state,
+            bump,
+            refund_amount,
+        )?;
+        transfer_from_pool(
+            &accounts.token_program,
+            &accounts.token_vault,
+            &accounts.token_failure_pool,
+            &accounts.mint,
+            &accounts.global_state,
+            bump,
+            penalty_amount,
+        )?;
+
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.failure_pool = mint_config
+            .failure_pool
+            .checked_add(penalty_amount)
+            .ok_or(ErrorCode::MathError)?;
+        ctx.accounts.user_stats.record_payout(
+            &ctx.accounts.focus_session,
+            refund_amount,
+            penalty_amount,
+        )?;
+
+        emit!(RewardsClaimed::new(
+            &ctx.accounts.focus_session,
+            refund_amount,
+            penalty_amount,
+            0,
+            mint_config.failure_pool,
+            mint_config.focus_pool,
+            Clock::get()?.unix_timestamp,
+        ));
+
+        Ok(())
+    }
+
+    pub fn allow_mint(ctx: Context<AllowMint>, min_stake: u64) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+        require!(min_stake > 0, ErrorCode::InvalidConfig);
+        require_supported_mint(&ctx.accounts.mint.to_account_info())?;
+
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.mint = ctx.accounts.mint.key();
+        mint_config.token_program = ctx.accounts.token_program.key();
+        mint_config.enabled = true;
+        mint_config.min_stake = min_stake;
+        mint_config.focus_pool = 0;
+        mint_config.failure_pool = 0;
+        mint_config.bump = ctx.bumps.mint_config;
+        mint_config.vault_bump = ctx.bumps.token_vault;
+        mint_config.focus_pool_bump = ctx.bumps.token_focus_pool;
+        mint_config.failure_pool_bump = ctx.bumps.token_failure_pool;
+
+        Ok(())
+    }
+
+    // Disabling a mint only blocks new sessions; open ones still settle
+    pub fn update_mint_config(
+        ctx: Context<UpdateMintConfig>,
+        enabled: bool,
+        min_stake: u64,
+    ) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+        require!(min_stake > 0, ErrorCode::InvalidConfig);
+
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.enabled = enabled;
+        mint_config.min_stake = min_stake;
+
+        Ok(())
+    }
+
+    pub fn withdraw_focus_pool_token(
+        ctx: Context<WithdrawFocusPoolToken>,
+        amount: u64,
+    ) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+        require!(
+            !ctx.accounts.global_state.circuit_breaker.withdrawals_paused,
+            ErrorCode::WithdrawalsPaused
+        );
+        require!(
+            amount <= ctx.accounts.token_focus_pool.amount,
+            ErrorCode::MathError
+        );
+
+        let accounts = &ctx.accounts;
+        transfer_from_pool(
+            &accounts.token_program,
+            &accounts.token_focus_pool,
+            &accounts.recipient_token_account,
+            &accounts.mint,
+            &accounts.global_state,
+            ctx.bumps.global_state,
+            amount,
+        )?;
+
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.focus_pool = mint_config.focus_pool.saturating_sub(amount);
+
+        emit!(PoolWithdrawn {
+            pool: PoolKind::Focus,
+            mint: Some(mint_config.mint),
+            amount,
+            recipient: ctx.accounts.recipient_token_account.key(),
+            pool_balance: mint_config.focus_pool,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_failure_pool_token(
+        ctx: Context<WithdrawFailurePoolToken>,
+        amount: u64,
+    ) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.global_state.authority,
+            ctx.accounts.authority.key()
+        );
+        require!(
+            !ctx.accounts.global_state.circuit_breaker.withdrawals_paused,
+            ErrorCode::WithdrawalsPaused
+        );
+        require!(
+            amount <= ctx.accounts.token_failure_pool.amount,
+            ErrorCode::MathError
+        );
+
+        let accounts = &ctx.accounts;
+        transfer_from_pool(
+            &accounts.token_program,
+            &accounts.token_failure_pool,
+            &accounts.recipient_token_account,
+            &accounts.mint,
+            &accounts.global_state,
+            ctx.bumps.global_state,
+            amount,
+        )?;
+
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.failure_pool = mint_config.failure_pool.saturating_sub(amount);
+
+        emit!(PoolWithdrawn {
+            pool: PoolKind::Failure,
+            mint: Some(mint_config.mint),
+            amount,
+            recipient: ctx.accounts.recipient_token_account.key(),
+            pool_balance: mint_config.failure_pool,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_task(ctx: Context<UpdateTask>, task_index: u8, completed: bool) -> Result<()> {
+        let focus_session = &mut ctx.accounts.focus_session;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        focus_session.require_signer(ctx.accounts.signer.key(), current_time)?;
+        require!(!focus_session.is_active, ErrorCode::SessionStillActive);
+        require!(
+            (task_index as usize) < focus_session.tasks.len(),
+            ErrorCode::InvalidTaskIndex
+        );
+
+        let task_bit = 1u32 << task_index;
+
+        let mut approver = None;
+        if !completed {
+            // un-completing a task only lowers the refund, so the owner can always do it alone
+            focus_session.tasks[task_index as usize].completed = false;
+            focus_session.pending_approvals &= !task_bit;
+        } else {
+            match focus_session.partner {
+                None => {
+                    focus_session.tasks[task_index as usize].completed = true;
+                }
+                Some(partner) => match &ctx.accounts.partner {
+                    // co-signed by the accountability partner: record completion directly
+                    Some(signer) => {
+                        require_keys_eq!(signer.key(), partner, ErrorCode::InvalidPartner);
+                        focus_session.tasks[task_index as usize].completed = true;
+                        focus_session.pending_approvals &= !task_bit;
+                        approver = Some(partner);
+                    }
+                    // owner (or their session key) alone can only request approval; partner
+                    // settles it via review_task
+                    None => {
+                        focus_session.pending_approvals |= task_bit;
+                    }
+                },
+            }
+        }
+
+        emit!(TaskUpdated::new(
+            focus_session,
+            task_index,
+            approver,
+            current_time,
+        ));
+
+        Ok(())
+    }
+
+    pub fn review_task(ctx: Context<ReviewTask>, task_index: u8, approved: bool) -> Result<()> {
+        let focus_session = &mut ctx.accounts.focus_session;
+
+        require!(!focus_session.is_active, ErrorCode::SessionStillActive);
+        require!(
+            (task_index as usize) < focus_session.tasks.len(),
+            ErrorCode::InvalidTaskIndex
+        );
+
+        focus_session.tasks[task_index as usize].completed = approved;
+        focus_session.pending_approvals &= !(1u32 << task_index);
+
+        emit!(TaskUpdated::new(
+            focus_session,
+            task_index,
+            Some(ctx.accounts.partner.key()),
+            Clock::get()?.unix_timestamp,
+        ));
+
+        Ok(())
+    }
+}
+
+fn validate_session_params(
+    config: &Config,
+    user: Pubkey,
+    duration_minutes: u64,
+    tasks: &[Task],
+    partner: Option<Pubkey>,
+    session_key: Option<&SessionKey>,
+) -> Result<()> {
+    require!(
+        duration_minutes > 0 && duration_minutes <= config.max_duration_minutes,
+        ErrorCode::InvalidDuration
+    );
+    require!(!tasks.is_empty(), ErrorCode::NoTasksProvided);
+    require!(
+        tasks.len() <= config.max_tasks as usize,
+        ErrorCode::TooManyTasks
+    );
+    if let Some(partner) = partner {
+        require_keys_neq!(partner, user, ErrorCode::InvalidPartner);
+    }
+    if let Some(session_key) = session_key {
+        require_keys_neq!(session_key.key, user, ErrorCode::InvalidSessionKey);
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            session_key.expires_at > now && session_key.expires_at <= now + MAX_SESSION_KEY_SECONDS,
+            ErrorCode::InvalidSessionKey
+        );
+    }
+    Ok(())
+}
+
+fn validate_streak_tiers(streak_tiers: &[StreakTier]) -> Result<()> {
+    require!(
+        streak_tiers.len() <= MAX_STREAK_TIERS,
+        ErrorCode::InvalidStreakTiers
+    );
+    for tier in streak_tiers {
+        require!(
+            tier.min_streak > 0 && tier.bonus_bps <= MAX_STREAK_BONUS_BPS,
+            ErrorCode::InvalidStreakTiers
+        );
+    }
+    // thresholds strictly increasing, bonuses never decreasing
+    for pair in streak_tiers.windows(2) {
+        require!(
+            pair[0].min_streak < pair[1].min_streak && pair[0].bonus_bps <= pair[1].bonus_bps,
+            ErrorCode::InvalidStreakTiers
+        );
+    }
+    Ok(())
+}
+
+/// Focus report the attestor signed in the Ed25519 instruction right before this one.
+fn attested_focus_report(instructions: &AccountInfo, attestor: &Pubkey) -> Result<FocusReport> {
+    let current_index = load_current_index_checked(instructions)? as usize;
+    require!(current_index > 0, ErrorCode::FocusReportRequired);
+    let ed25519 = load_instruction_at_checked(current_index - 1, instructions)?;
+    require_keys_eq!(
+        ed25519.program_id,
+        ED25519_PROGRAM_ID,
+        ErrorCode::FocusReportRequired
+    );
+
+    // a single signature with its key and message inside the Ed25519 instruction,
+    // which the runtime has verified before any program runs
+    let data = &ed25519.data;
+    require!(
+        data.len() >= ED25519_DATA_START && data[0] == 1,
+        ErrorCode::InvalidFocusReport
+    );
+    let offsets: Vec<usize> = data[2..ED25519_DATA_START]
+        .chunks(2)
+        .map(|offset| u16::from_le_bytes([offset[0], offset[1]]) as usize)
+        .collect();
+    let (public_key_offset, message_offset, message_size) = (offsets[2], offsets[4], offsets[5]);
+    require!(
+        [offsets[1], offsets[3], offsets[6]]
+            .iter()
+            .all(|&index| index == u16::MAX as usize),
+        ErrorCode::InvalidFocusReport
+    );
+    let public_key = data
+        .get(public_key_offset..public_key_offset + 32)
+        .ok_or(ErrorCode::InvalidFocusReport)?;
+    require!(
+        public_key == attestor.as_ref(),
+        ErrorCode::InvalidFocusReport
+    );
+
+    let message = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(ErrorCode::InvalidFocusReport)?;
+    message
+        .strip_prefix(FOCUS_REPORT_DOMAIN)
+        .and_then(|report| FocusReport::try_from_slice(report).ok())
+        .ok_or(error!(ErrorCode::InvalidFocusReport))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn open_focus_session(
+    user_state: &mut Account<UserState>,
+    focus_session: &mut Account<FocusSession>,
+    user: Pubkey,
+    mint: Option<Pubkey>,
+    stake_amount: u64,
+    duration_minutes: u64,
+    tasks: Vec<Task>,
+    partner: Option<Pubkey>,
+    session_key: Option<SessionKey>,
+    rent_payer: Pubkey,
+    config: &Config,
+) -> Result<()> {
+    // sessions are indexed per user so several can be open and settle independently
+    user_state.user = user;
+    focus_session.session_id = user_state.next_session_id;
+    user_state.next_session_id = user_state
+        .next_session_id
+        .checked_add(1)
+        .ok_or(ErrorCode::MathError)?;
+
+    focus_session.user = user;
+    focus_session.mint = mint;
+    focus_session.is_active = true;
+    focus_session.stake_amount = stake_amount;
+    focus_session.start_time = Clock::get()?.unix_timestamp;
+    focus_session.duration_minutes = duration_minutes;
+    // tasks always start incomplete; completion is only recorded via update_task
+    focus_session.tasks = tasks
+        .into_iter()
+        .map(|task| Task {
+            completed: false,
+            ..task
+        })
+        .collect();
+    focus_session.partner = partner;
+    focus_session.session_key = session_key;
+    focus_session.rent_payer = rent_payer;
+    focus_session.max_pause_seconds = config.max_pause_minutes * 60;
+    focus_session.heartbeat_interval_seconds = config.heartbeat_interval_seconds;
+    focus_session.min_heartbeat_coverage_bps = config.min_heartbeat_coverage_bps;
+    focus_session.max_missed_heartbeats = config.max_missed_heartbeats;
+    focus_session.challenge_rate_bps = config.challenge_rate_bps;
+    focus_session.challenge_window_seconds = config.challenge_window_seconds;
+    focus_session.attested_focus_bps = BPS_DENOMINATOR as u16;
+
+    Ok(())
+}
+
+// Pays out of a token account owned by the global_state PDA
+fn transfer_from_pool<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    global_state: &Account<'info, GlobalState>,
+    global_state_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let seeds: &[&[u8]] = &[b"global_state", &[global_state_bump]];
+    let signer = &[seeds];
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: global_state.to_account_info(),
+        },
+        signer,
+    );
+    token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)
+}
+
+// Fee-on-transfer and transfer-hook mints would break the vault accounting; the
+// rest let a third party move, freeze or strand tokens held by the vaults
+const UNSUPPORTED_EXTENSIONS: [ExtensionType; 6] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::TransferHook,
+    ExtensionType::PermanentDelegate,
+    ExtensionType::Pausable,
+    ExtensionType::NonTransferable,
+    ExtensionType::MintCloseAuthority,
+];
+
+fn require_supported_mint(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != anchor_spl::token_2022::ID {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let state =
+        StateWithExtensions::<anchor_spl::token_2022::spl_token_2022::state::Mint>::unpack(&data)?;
+    let extensions = state.get_extension_types()?;
+    require!(
+        !extensions
+            .iter()
+            .any(|extension| UNSUPPORTED_EXTENSIONS.contains(extension)),
+        ErrorCode::UnsupportedMint
+    );
+    // new token accounts, the vaults included, would start out frozen
+    if let Ok(default_state) = state.get_extension::<DefaultAccountState>() {
+        require!(
+            default_state.state != AccountState::Frozen as u8,
+            ErrorCode::UnsupportedMint
+        );
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + GlobalState::INIT_SPACE,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Vault PDA to hold staked SOL
+    #[account(
+        mut,
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    /// CHECK: Focus pool vault PDA (1% fees)
+    #[account(
+        mut,
+        seeds = [b"focus_pool_vault", global_state.key().as_ref()],
+        bump
+    )]
+    pub focus_pool_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Failure pool vault PDA (failed stakes)
+    #[account(
+        mut,
+        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
+        bump
+    )]
+    pub failure_pool_vault: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + RewardEpoch::INIT_SPACE,
+        seeds = [b"epoch".as_ref(), &0u64.to_le_bytes()],
+        bump
+    )]
+    pub epoch: Account<'info, RewardEpoch>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StartFocusSession<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserState::INIT_SPACE,
+        seeds = [b"user_state", user.key().as_ref()],
+        bump
+    )]
+    pub user_state: Account<'info, UserState>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + FocusSession::INIT_SPACE,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &user_state.next_session_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserStats::INIT_SPACE,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Vault PDA
+    #[account(
+        mut,
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump = global_state.vault_bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    /// CHECK: Focus pool vault PDA
+    #[account(
+        mut,
+        seeds = [b"focus_pool_vault", global_state.key().as_ref()],
+        bump = global_state.focus_pool_bump
+    )]
+    pub focus_pool_vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    // Pays the rent, so a relayer can sponsor onboarding; may be the user
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteFocusSession<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Vault PDA
+    #[account(
+        mut,
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump = global_state.vault_bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"epoch".as_ref(), &global_state.current_epoch.to_le_bytes()],
+        bump = epoch.bump
+    )]
+    pub epoch: Account<'info, RewardEpoch>,
+
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = 8 + EpochShare::INIT_SPACE,
+        seeds = [
+            b"epoch_share".as_ref(),
+            &epoch.epoch_id.to_le_bytes(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub epoch_share: Account<'info, EpochShare>,
+
+    /// CHECK: Session owner, only used as seed for the PDAs
+    pub user: UncheckedAccount<'info>,
+
+    // The owner or their session key; pays the epoch share's rent on the user's
+    // first completion in an epoch, so a session key needs a little SOL
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Instructions sysvar, read for the attestor's focus report
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteFocusSessionClose<'info> {
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user,
+        constraint = focus_session.mint.is_none() @ ErrorCode::AssetMismatch
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Whoever paid the session's rent at start, refunded on close
+    #[account(mut, address = focus_session.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Vault PDA
+    #[account(
+        mut,
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump = global_state.vault_bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FailFocusSession<'info> {
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user,
+        constraint = focus_session.mint.is_none() @ ErrorCode::AssetMismatch
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Whoever paid the session's rent at start, refunded on close
+    #[account(mut, address = focus_session.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Vault PDA
+    #[account(
+        mut,
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump = global_state.vault_bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    /// CHECK: Failure pool vault PDA
+    #[account(
+        mut,
+        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
+        bump = global_state.failure_pool_bump
+    )]
+    pub failure_pool_vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireFocusSession<'info> {
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user,
+        constraint = focus_session.mint.is_none() @ ErrorCode::AssetMismatch
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Whoever paid the session's rent at start, refunded on close
+    #[account(mut, address = focus_session.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Vault PDA
+    #[account(
+        mut,
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump = global_state.vault_bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    /// CHECK: Failure pool vault PDA
+    #[account(
+        mut,
+        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
+        bump = global_state.failure_pool_bump
+    )]
+    pub failure_pool_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Session owner, not required to sign for permissionless expiry;
+    /// seed for the focus_session PDA
+    pub user: UncheckedAccount<'info>,
+
+    // anyone can crank the expiry and collect the keeper bounty
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireFocusSessionsBatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Vault PDA
+    #[account(
+        mut,
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump = global_state.vault_bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    /// CHECK: Failure pool vault PDA
+    #[account(
+        mut,
+        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
+        bump = global_state.failure_pool_bump
+    )]
+    pub failure_pool_vault: UncheckedAccount<'info>,
+
+    // anyone can crank the expiry and collect the keeper bounty
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PauseFocusSession<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResumeFocusSession<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Session owner, only used as seed for the PDAs
+    pub user: UncheckedAccount<'info>,
+
+    // The owner or their session key
+    pub signer: Signer<'info>,
+
+    /// CHECK: SlotHashes sysvar, read directly since it is too large to deserialize
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AnswerChallenge<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Session owner, only used as seed for the PDAs
+    pub user: UncheckedAccount<'info>,
+
+    // The owner or their session key
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    /// CHECK: Still in an older layout, decoded by migrate_config itself
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump,
+        owner = crate::ID
+    )]
+    pub global_state: UncheckedAccount<'info>,
+
+    /// CHECK: Vault PDA, read for the stake its sessions hold
+    #[account(
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserState<'info> {
+    /// CHECK: Still in the first deployment's layout, decoded by migrate_user_state itself
+    #[account(
+        mut,
+        seeds = [b"user_state", user.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub user_state: UncheckedAccount<'info>,
+
+    /// CHECK: Created by migrate_user_state when the old state holds a session
+    #[account(
+        mut,
+        seeds = [b"focus_session", user.key().as_ref(), &0u64.to_le_bytes()],
+        bump
+    )]
+    pub focus_session: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserStats::INIT_SPACE,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Owner of the user state, gets back the rent it no longer needs
+    #[account(mut)]
+    pub user: UncheckedAccount<'info>,
+
+    // Pays the rent of the migrated session and gets it back when it closes
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRefundCurve<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetStreakTiers<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAttestor<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCircuitBreaker<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user,
+        constraint = focus_session.mint.is_none() @ ErrorCode::AssetMismatch
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Whoever paid the session's rent at start, refunded on close
+    #[account(mut, address = focus_session.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Vault PDA
+    #[account(
+        mut,
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump = global_state.vault_bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyWithdrawToken<'info> {
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user,
+        constraint = focus_session.mint == Some(mint.key()) @ ErrorCode::AssetMismatch
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Whoever paid the session's rent at start, refunded on close
+    #[account(mut, address = focus_session.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        seeds = [b"mint_config", mint.key().as_ref()],
+        bump = mint_config.bump,
+        has_one = mint,
+        has_one = token_program
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+        token::token_program = token_program
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", mint.key().as_ref()],
+        bump = mint_config.vault_bump
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFocusPool<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Focus pool vault
+    #[account(
+        mut,
+        seeds = [b"focus_pool_vault", global_state.key().as_ref()],
+        bump = global_state.focus_pool_bump
+    )]
+    pub focus_pool_vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFailurePool<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Failure pool vault
+    #[account(
+        mut,
+        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
+        bump = global_state.failure_pool_bump
+    )]
+    pub failure_pool_vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Reconcile<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Vault PDA
+    #[account(
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump = global_state.vault_bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    /// CHECK: Focus pool vault PDA
+    #[account(
+        seeds = [b"focus_pool_vault", global_state.key().as_ref()],
+        bump = global_state.focus_pool_bump
+    )]
+    pub focus_pool_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Failure pool vault PDA
+    #[account(
+        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
+        bump = global_state.failure_pool_bump
+    )]
+    pub failure_pool_vault: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user,
+        constraint = focus_session.mint.is_none() @ ErrorCode::AssetMismatch
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Whoever paid the session's rent at start, refunded on close
+    #[account(mut, address = focus_session.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Vault PDA
+    #[account(
+        mut,
+        seeds = [b"vault", global_state.key().as_ref()],
+        bump = global_state.vault_bump
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    /// CHECK: Focus pool vault PDA
+    #[account(
+        mut,
+        seeds = [b"focus_pool_vault", global_state.key().as_ref()],
+        bump = global_state.focus_pool_bump
+    )]
+    pub focus_pool_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Failure pool vault PDA
+    #[account(
+        mut,
+        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
+        bump = global_state.failure_pool_bump
+    )]
+    pub failure_pool_vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StartFocusSessionToken<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserState::INIT_SPACE,
+        seeds = [b"user_state", user.key().as_ref()],
+        bump
+    )]
+    pub user_state: Account<'info, UserState>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + FocusSession::INIT_SPACE,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &user_state.next_session_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + UserStats::INIT_SPACE,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_config", mint.key().as_ref()],
+        bump = mint_config.bump,
+        has_one = mint,
+        has_one = token_program
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+        token::token_program = token_program
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", mint.key().as_ref()],
+        bump = mint_config.vault_bump
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_focus_pool", mint.key().as_ref()],
+        bump = mint_config.focus_pool_bump
+    )]
+    pub token_focus_pool: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    // Pays the rent, so a relayer can sponsor onboarding; may be the user
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseEpoch<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"epoch".as_ref(), &global_state.current_epoch.to_le_bytes()],
+        bump = epoch.bump
+    )]
+    pub epoch: Account<'info, RewardEpoch>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + RewardEpoch::INIT_SPACE,
+        seeds = [b"epoch".as_ref(), &(global_state.current_epoch + 1).to_le_bytes()],
+        bump
+    )]
+    pub next_epoch: Account<'info, RewardEpoch>,
+
+    // anyone can crank the epoch forward
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimEpochRewards<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"epoch".as_ref(), &epoch_share.epoch_id.to_le_bytes()],
+        bump = epoch.bump
+    )]
+    pub epoch: Account<'info, RewardEpoch>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [
+            b"epoch_share".as_ref(),
+            &epoch_share.epoch_id.to_le_bytes(),
+            user.key().as_ref()
+        ],
+        bump,
+        has_one = user
+    )]
+    pub epoch_share: Account<'info, EpochShare>,
+
+    /// CHECK: Failure pool vault PDA
+    #[account(
+        mut,
+        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
+        bump = global_state.failure_pool_bump
+    )]
+    pub failure_pool_vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FailFocusSessionToken<'info> {
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user,
+        constraint = focus_session.mint == Some(mint.key()) @ ErrorCode::AssetMismatch
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Whoever paid the session's rent at start, refunded on close
+    #[account(mut, address = focus_session.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_config", mint.key().as_ref()],
+        bump = mint_config.bump,
+        has_one = mint,
+        has_one = token_program
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+        token::token_program = token_program
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", mint.key().as_ref()],
+        bump = mint_config.vault_bump
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_failure_pool", mint.key().as_ref()],
+        bump = mint_config.failure_pool_bump
+    )]
+    pub token_failure_pool: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireFocusSessionToken<'info> {
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user,
+        constraint = focus_session.mint == Some(mint.key()) @ ErrorCode::AssetMismatch
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Whoever paid the session's rent at start, refunded on close
+    #[account(mut, address = focus_session.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_config", mint.key().as_ref()],
+        bump = mint_config.bump,
+        has_one = mint,
+        has_one = token_program
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", mint.key().as_ref()],
+        bump = mint_config.vault_bump
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_failure_pool", mint.key().as_ref()],
+        bump = mint_config.failure_pool_bump
+    )]
+    pub token_failure_pool: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = keeper,
+        token::token_program = token_program
+    )]
+    pub keeper_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Session owner, not required to sign for permissionless expiry;
+    /// seed for the focus_session PDA
+    pub user: UncheckedAccount<'info>,
+
+    // anyone can crank the expiry and collect the keeper bounty
+    pub keeper: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewardsToken<'info> {
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user,
+        constraint = focus_session.mint == Some(mint.key()) @ ErrorCode::AssetMismatch
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Whoever paid the session's rent at start, refunded on close
+    #[account(mut, address = focus_session.rent_payer @ ErrorCode::InvalidRentPayer)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", user.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_config", mint.key().as_ref()],
+        bump = mint_config.bump,
+        has_one = mint,
+        has_one = token_program
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+        token::token_program = token_program
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", mint.key().as_ref()],
+        bump = mint_config.vault_bump
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_failure_pool", mint.key().as_ref()],
+        bump = mint_config.failure_pool_bump
+    )]
+    pub token_failure_pool: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct AllowMint<'info> {
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + MintConfig::INIT_SPACE,
+        seeds = [b"mint_config", mint.key().as_ref()],
+        bump
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"token_vault", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = global_state,
+        token::token_program = token_program
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"token_focus_pool", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = global_state,
+        token::token_program = token_program
+    )]
+    pub token_focus_pool: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"token_failure_pool", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = global_state,
+        token::token_program = token_program
+    )]
+    pub token_failure_pool: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMintConfig<'info> {
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_config", mint_config.mint.as_ref()],
+        bump = mint_config.bump
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFocusPoolToken<'info> {
+    #[account(
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [b"mint_config", mint.key().as_ref()],
+        bump = mint_config.bump,
+        has_one = mint,
+        has_one = token_program
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
         mut,
+        seeds = [b"token_focus_pool", mint.key().as_ref()],
+        bump = mint_config.focus_pool_bump
+    )]
+    pub token_focus_pool: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFailurePoolToken<'info> {
+    #[account(
         seeds = [b"global_state"],
         bump
     )]
     pub global_state: Account<'info, GlobalState>,
 
-    /// CHECK: Vault PDA
-    #[account(
-        mut,
-        seeds = [b"vault", global_state.key().as_ref()],
-        bump = global_state.vault_bump
-    )]
-    pub vault: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"mint_config", mint.key().as_ref()],
+        bump = mint_config.bump,
+        has_one = mint,
+        has_one = token_program
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"token_failure_pool", mint.key().as_ref()],
+        bump = mint_config.failure_pool_bump
+    )]
+    pub token_failure_pool: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTask<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Session owner, only used as seed for focus_session PDA
+    pub user: UncheckedAccount<'info>,
+
+    // The owner or their session key
+    pub signer: Signer<'info>,
+
+    /// Accountability partner co-signing a completion (required to complete tasks directly
+    /// when the session has a partner)
+    pub partner: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ReviewTask<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"focus_session",
+            user.key().as_ref(),
+            &focus_session.session_id.to_le_bytes()
+        ],
+        bump,
+        has_one = user,
+        constraint = focus_session.partner == Some(partner.key()) @ ErrorCode::InvalidPartner
+    )]
+    pub focus_session: Account<'info, FocusSession>,
+
+    /// CHECK: Session owner, only used as seed for focus_session PDA
+    pub user: UncheckedAccount<'info>,
+
+    pub partner: Signer<'info>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct GlobalState {
+    pub authority: Pubkey,
+    pub pending_authority: Option<Pubkey>, // Proposed authority awaiting accept_authority
+    pub focus_pool: u64,
+    pub failure_pool: u64,
+    pub total_sessions: u64,
+    pub vault_bump: u8,
+    pub focus_pool_bump: u8,
+    pub failure_pool_bump: u8,
+    pub config: Config,                  // Admin-tunable protocol parameters
+    pub refund_curve: RefundCurve,       // Early-exit refund schedule for fail_focus_session
+    pub current_epoch: u64, // Reward epoch that completed sessions currently earn shares in
+    pub failure_pool_inflow: u64, // Lamports ever credited to the failure pool
+    pub failure_pool_reserved: u64, // Failure pool lamports owed to closed epochs' participants
+    pub circuit_breaker: CircuitBreaker, // Emergency pause flags
+    pub total_active_stake: u64, // Vault lamports staked in active SOL sessions
+    pub total_pending_balance: u64, // Vault lamports owed to completed, unclaimed SOL sessions
+    #[max_len(5)]
+    pub streak_tiers: Vec<StreakTier>, // Claim bonus by completion streak, lowest tier first
+    pub attestor: Option<Pubkey>, // Key that signs focus reports; None leaves completion to the clock
+}
+
+impl GlobalState {
+    pub fn credit_failure_pool(&mut self, amount: u64) -> Result<()> {
+        self.failure_pool = self
+            .failure_pool
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathError)?;
+        self.failure_pool_inflow = self
+            .failure_pool_inflow
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathError)?;
+        Ok(())
+    }
+
+    /// Bonus of the highest streak tier `streak` reaches, 0 below the first tier.
+    pub fn streak_bonus_bps(&self, streak: u64) -> u16 {
+        self.streak_tiers
+            .iter()
+            .filter(|tier| tier.min_streak <= streak)
+            .map(|tier| tier.bonus_bps)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Vault lamports owed to SOL sessions, active or awaiting a claim.
+    pub fn total_owed(&self) -> Result<u64> {
+        self.total_active_stake
+            .checked_add(self.total_pending_balance)
+            .ok_or(error!(ErrorCode::MathError))
+    }
+
+    pub fn lock_stake(&mut self, amount: u64) -> Result<()> {
+        self.total_active_stake = self
+            .total_active_stake
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathError)?;
+        Ok(())
+    }
+
+    pub fn release_stake(&mut self, amount: u64) -> Result<()> {
+        self.total_active_stake = self
+            .total_active_stake
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathError)?;
+        Ok(())
+    }
+
+    pub fn credit_pending(&mut self, amount: u64) -> Result<()> {
+        self.total_pending_balance = self
+            .total_pending_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathError)?;
+        Ok(())
+    }
+
+    pub fn release_pending(&mut self, amount: u64) -> Result<()> {
+        self.total_pending_balance = self
+            .total_pending_balance
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathError)?;
+        Ok(())
+    }
+}
+
+/// Emergency pause flags the authority sets through set_circuit_breaker.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, PartialEq, Eq, InitSpace)]
+pub struct CircuitBreaker {
+    pub new_sessions_paused: bool, // Blocks new sessions and enables emergency_withdraw
+    pub settlements_paused: bool,  // Blocks claims
+    pub withdrawals_paused: bool,  // Blocks authority pool withdrawals
+}
+
+/// Protocol parameters the authority can tune through update_config.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct Config {
+    pub version: u8,                     // Layout version, set by the program
+    pub focus_pool_fee_bps: u16,         // Share of each stake sent to the focus pool
+    pub min_stake: u64,                  // Minimum stake in lamports
+    pub max_duration_minutes: u64,       // Longest allowed session
+    pub max_tasks: u8,                   // Most tasks per session
+    pub grace_minutes: u64,              // Early-completion / late-expiry grace window
+    pub max_pause_minutes: u64,          // Pause budget granted to each new session
+    pub epoch_duration_seconds: i64,     // Minimum length of a reward epoch
+    pub keeper_bounty_bps: u16,          // Share of an expired stake paid to the caller
+    pub heartbeat_interval_seconds: u32, // Expected time between heartbeats, 0 disables them
+    pub min_heartbeat_coverage_bps: u16, // Share of focused intervals that need a heartbeat
+    pub max_missed_heartbeats: u16, // Consecutive misses that make a session expirable, 0 never
+    pub challenge_rate_bps: u16, // Chance that a heartbeat schedules a spot-check, 0 disables them
+    pub challenge_window_seconds: u32, // Time a spot-check stays open for an answer
+    pub min_attested_focus_bps: u16, // Share of the duration a focus report must cover
+    pub distraction_penalty_bps: u16, // Refund cut per distraction in a focus report
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
+            focus_pool_fee_bps: 100,
+            min_stake: 10_000_000,
+            max_duration_minutes: 480,
+            max_tasks: MAX_TASKS,
+            grace_minutes: 5,
+            max_pause_minutes: 30,
+            epoch_duration_seconds: 7 * 86_400,
+            keeper_bounty_bps: 100,
+            heartbeat_interval_seconds: 0,
+            min_heartbeat_coverage_bps: 8_000,
+            max_missed_heartbeats: 5,
+            challenge_rate_bps: 0,
+            challenge_window_seconds: 300,
+            min_attested_focus_bps: 8_000,
+            distraction_penalty_bps: 100,
+        }
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.focus_pool_fee_bps <= MAX_FOCUS_POOL_FEE_BPS,
+            ErrorCode::InvalidConfig
+        );
+        require!(self.min_stake >= MIN_STAKE_FLOOR, ErrorCode::InvalidConfig);
+        require!(
+            self.max_duration_minutes > 0
+                && self.max_duration_minutes <= MAX_DURATION_LIMIT_MINUTES,
+            ErrorCode::InvalidConfig
+        );
+        require!(
+            self.max_tasks > 0 && self.max_tasks <= MAX_TASKS,
+            ErrorCode::InvalidConfig
+        );
+        require!(
+            self.grace_minutes <= MAX_GRACE_MINUTES,
+            ErrorCode::InvalidConfig
+        );
+        require!(
+            self.max_pause_minutes <= MAX_PAUSE_LIMIT_MINUTES,
+            ErrorCode::InvalidConfig
+        );
+        require!(
+            self.epoch_duration_seconds >= MIN_EPOCH_DURATION_SECONDS
+                && self.epoch_duration_seconds <= MAX_EPOCH_DURATION_SECONDS,
+            ErrorCode::InvalidConfig
+        );
+        require!(
+            self.keeper_bounty_bps <= MAX_KEEPER_BOUNTY_BPS,
+            ErrorCode::InvalidConfig
+        );
+        require!(
+            self.heartbeat_interval_seconds == 0
+                || (MIN_HEARTBEAT_INTERVAL_SECONDS..=MAX_HEARTBEAT_INTERVAL_SECONDS)
+                    .contains(&self.heartbeat_interval_seconds),
+            ErrorCode::InvalidConfig
+        );
+        require!(
+            self.min_heartbeat_coverage_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidConfig
+        );
+        // spot-checks are drawn by heartbeats, so they need heartbeats enabled
+        require!(
+            self.challenge_rate_bps as u64 <= BPS_DENOMINATOR
+                && (self.challenge_rate_bps == 0 || self.heartbeat_interval_seconds > 0)
+                && (MIN_CHALLENGE_WINDOW_SECONDS..=MAX_CHALLENGE_WINDOW_SECONDS)
+                    .contains(&self.challenge_window_seconds),
+            ErrorCode::InvalidConfig
+        );
+        require!(
+            self.min_attested_focus_bps as u64 <= BPS_DENOMINATOR
+                && self.distraction_penalty_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidConfig
+        );
+        Ok(())
+    }
 
-    /// CHECK: Focus pool vault PDA
-    #[account(
-        mut,
-        seeds = [b"focus_pool_vault", global_state.key().as_ref()],
-        bump = global_state.focus_pool_bump
-    )]
-    pub focus_pool_vault: UncheckedAccount<'info>,
+    pub fn focus_pool_fee(&self, stake_amount: u64) -> Result<u64> {
+        let fee = stake_amount as u128 * self.focus_pool_fee_bps as u128 / BPS_DENOMINATOR as u128;
+        u64::try_from(fee).map_err(|_| error!(ErrorCode::MathError))
+    }
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    pub fn keeper_bounty(&self, stake_amount: u64) -> Result<u64> {
+        let bounty =
+            stake_amount as u128 * self.keeper_bounty_bps as u128 / BPS_DENOMINATOR as u128;
+        u64::try_from(bounty).map_err(|_| error!(ErrorCode::MathError))
+    }
 }
 
-#[derive(Accounts)]
-pub struct CompleteFocusSession<'info> {
-    #[account(
-        mut,
-        seeds = [b"user_state", user.key().as_ref()],
-        bump,
-        has_one = user
-    )]
-    pub user_state: Account<'info, UserState>,
+/// `GlobalState` as first deployed, before `Config` and the vault totals;
+/// migrate_config upgrades it in place.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct GlobalStateV0 {
+    pub authority: Pubkey,
+    pub focus_pool: u64,
+    pub failure_pool: u64,
+    pub total_sessions: u64,
+    pub vault_bump: u8,
+    pub focus_pool_bump: u8,
+    pub failure_pool_bump: u8,
+}
 
-    #[account(
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
+impl GlobalStateV0 {
+    /// Decodes a global state account, failing unless it is in the first layout.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() == 8 + Self::INIT_SPACE && data.starts_with(GlobalState::DISCRIMINATOR),
+            ErrorCode::UnknownStateLayout
+        );
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
 
-    /// CHECK: Vault PDA
-    #[account(
-        mut,
-        seeds = [b"vault", global_state.key().as_ref()],
-        bump = global_state.vault_bump
-    )]
-    pub vault: UncheckedAccount<'info>,
+    /// Current layout with the same authority, pools and counters, default
+    /// settings and `vault_owed` lamports of active stake.
+    pub fn migrate(self, vault_owed: u64) -> GlobalState {
+        GlobalState {
+            authority: self.authority,
+            pending_authority: None,
+            focus_pool: self.focus_pool,
+            failure_pool: self.failure_pool,
+            total_sessions: self.total_sessions,
+            vault_bump: self.vault_bump,
+            focus_pool_bump: self.focus_pool_bump,
+            failure_pool_bump: self.failure_pool_bump,
+            config: Config::default(),
+            refund_curve: RefundCurve::None,
+            current_epoch: 0,
+            failure_pool_inflow: 0,
+            failure_pool_reserved: 0,
+            circuit_breaker: CircuitBreaker::default(),
+            total_active_stake: vault_owed,
+            total_pending_balance: 0,
+            streak_tiers: Vec::new(),
+            attestor: None,
+        }
+    }
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+/// `UserState` as first deployed, holding the user's only session;
+/// migrate_user_state upgrades it in place.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct UserStateV0 {
+    pub user: Pubkey,
+    pub is_active: bool,
+    pub stake_amount: u64,
+    pub start_time: i64,
+    pub duration_minutes: u64,
+    pub pending_balance: u64,
+    #[max_len(20)]
+    pub tasks: Vec<Task>,
 }
 
-#[derive(Accounts)]
-pub struct CompleteFocusSessionClose<'info> {
-    #[account(
-        mut,
-        close = user,
-        seeds = [b"user_state", user.key().as_ref()],
-        bump,
-        has_one = user
-    )]
-    pub user_state: Account<'info, UserState>,
+impl UserStateV0 {
+    /// Decodes a user state account, failing unless it is in the first layout.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() == 8 + Self::INIT_SPACE && data.starts_with(UserState::DISCRIMINATOR),
+            ErrorCode::UnknownStateLayout
+        );
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
 
-    #[account(
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
+    /// The session as focus_session 0, with none of the checks added since:
+    /// no pauses, heartbeats, spot-checks or streak bonus.
+    pub fn migrate(&self, rent_payer: Pubkey) -> FocusSession {
+        FocusSession {
+            user: self.user,
+            session_id: 0,
+            mint: None,
+            is_active: self.is_active,
+            stake_amount: self.stake_amount,
+            start_time: self.start_time,
+            duration_minutes: self.duration_minutes,
+            pending_balance: self.pending_balance,
+            tasks: self.tasks.clone(),
+            partner: None,
+            pending_approvals: 0,
+            max_pause_seconds: 0,
+            paused_at: 0,
+            pauses: Vec::new(),
+            streak_bonus_bps: 0,
+            session_key: None,
+            rent_payer,
+            heartbeat_interval_seconds: 0,
+            min_heartbeat_coverage_bps: 0,
+            max_missed_heartbeats: 0,
+            last_heartbeat: 0,
+            last_heartbeat_slot: 0,
+            heartbeats: [0; 256],
+            challenge_rate_bps: 0,
+            challenge_window_seconds: 0,
+            attested_focus_bps: BPS_DENOMINATOR as u16,
+            challenge: None,
+        }
+    }
+}
 
-    /// CHECK: Vault PDA
-    #[account(
-        mut,
-        seeds = [b"vault", global_state.key().as_ref()],
-        bump = global_state.vault_bump
-    )]
-    pub vault: UncheckedAccount<'info>,
+/// Share of the stake returned when a user voluntarily fails a session early.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum RefundCurve {
+    /// Whole stake is forfeited
+    None,
+    /// Refund grows linearly with elapsed time, reaching `max_refund_bps` at the full duration
+    Linear { max_refund_bps: u16 },
+    /// Refund of the highest step whose elapsed threshold has been reached
+    Stepped {
+        #[max_len(5)]
+        steps: Vec<RefundStep>,
+    },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct StreakTier {
+    pub min_streak: u64, // Consecutive completions needed, the session itself included
+    pub bonus_bps: u16,  // Share of the claim refund added from the focus pool
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct RefundStep {
+    pub min_elapsed_bps: u16, // Share of the duration that must have elapsed
+    pub refund_bps: u16,      // Share of the stake refunded once reached
+}
+
+impl RefundCurve {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            RefundCurve::None => {}
+            RefundCurve::Linear { max_refund_bps } => {
+                require!(
+                    *max_refund_bps as u64 <= BPS_DENOMINATOR,
+                    ErrorCode::InvalidRefundCurve
+                );
+            }
+            RefundCurve::Stepped { steps } => {
+                require!(
+                    !steps.is_empty() && steps.len() <= MAX_REFUND_STEPS,
+                    ErrorCode::InvalidRefundCurve
+                );
+                for step in steps {
+                    require!(
+                        step.min_elapsed_bps as u64 <= BPS_DENOMINATOR
+                            && step.refund_bps as u64 <= BPS_DENOMINATOR,
+                        ErrorCode::InvalidRefundCurve
+                    );
+                }
+                // thresholds strictly increasing, refunds never decreasing
+                for pair in steps.windows(2) {
+                    require!(
+                        pair[0].min_elapsed_bps < pair[1].min_elapsed_bps
+                            && pair[0].refund_bps <= pair[1].refund_bps,
+                        ErrorCode::InvalidRefundCurve
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Lamports refunded out of `stake_amount` after `elapsed_seconds` of a
+    /// `duration_seconds` session.
+    pub fn refund_amount(
+        &self,
+        stake_amount: u64,
+        elapsed_seconds: i64,
+        duration_seconds: u64,
+    ) -> Result<u64> {
+        if duration_seconds == 0 {
+            return Ok(0);
+        }
+        let elapsed_bps = ((elapsed_seconds.max(0) as u128 * BPS_DENOMINATOR as u128)
+            / duration_seconds as u128)
+            .min(BPS_DENOMINATOR as u128);
+
+        let refund_bps: u128 = match self {
+            RefundCurve::None => 0,
+            RefundCurve::Linear { max_refund_bps } => {
+                elapsed_bps * *max_refund_bps as u128 / BPS_DENOMINATOR as u128
+            }
+            RefundCurve::Stepped { steps } => steps
+                .iter()
+                .filter(|step| step.min_elapsed_bps as u128 <= elapsed_bps)
+                .map(|step| step.refund_bps as u128)
+                .max()
+                .unwrap_or(0),
+        };
+
+        let refund = stake_amount as u128 * refund_bps / BPS_DENOMINATOR as u128;
+        u64::try_from(refund).map_err(|_| error!(ErrorCode::MathError))
+    }
+}
+
+// Allow-listed SPL / Token-2022 mint with its pool token accounts and pool counters
+#[account]
+#[derive(InitSpace)]
+pub struct MintConfig {
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub enabled: bool,  // New sessions allowed
+    pub min_stake: u64, // Minimum stake in base units of the mint
+    pub focus_pool: u64,
+    pub failure_pool: u64,
+    pub bump: u8,
+    pub vault_bump: u8,
+    pub focus_pool_bump: u8,
+    pub failure_pool_bump: u8,
+}
+
+// Failure-pool redistribution period; completed SOL sessions earn stake x minutes shares
+#[account]
+#[derive(InitSpace)]
+pub struct RewardEpoch {
+    pub epoch_id: u64,
+    pub start_time: i64,
+    pub inflow_start: u64, // GlobalState::failure_pool_inflow when the epoch opened
+    pub total_shares: u128,
+    pub rewards: u64, // Lamports reserved for participants at close
+    pub claimed_rewards: u64,
+    pub closed: bool,
+    pub bump: u8,
+}
+
+impl RewardEpoch {
+    /// Pro-rata part of the epoch rewards for `shares`.
+    pub fn reward_for(&self, shares: u128) -> Result<u64> {
+        if self.total_shares == 0 {
+            return Ok(0);
+        }
+        let reward = self.rewards as u128 * shares / self.total_shares;
+        u64::try_from(reward).map_err(|_| error!(ErrorCode::MathError))
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct EpochShare {
+    pub epoch_id: u64,
+    pub user: Pubkey,
+    pub shares: u128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct Task {
+    #[max_len(100)]
+    pub description: String,
+    pub completed: bool,
+}
+
+// What the desktop agent saw of a session, signed by the attestor
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct FocusReport {
+    pub user: Pubkey,
+    pub session_start: i64,
+    pub focused_minutes: u32,
+    pub distractions: u32,
+}
+
+impl FocusReport {
+    /// Bytes the attestor signs: the domain followed by the serialized report.
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = FOCUS_REPORT_DOMAIN.to_vec();
+        self.serialize(&mut message).unwrap();
+        message
+    }
+}
+
+// Delegate that can check in for the owner without their wallet
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub struct SessionKey {
+    pub key: Pubkey,
+    pub expires_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UserState {
+    pub user: Pubkey,
+    pub next_session_id: u64, // Id of the user's next focus_session PDA
+}
+
+// Unpredictable window the user has to answer in; missing it forfeits the session
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct Challenge {
+    pub opens_at: i64,
+    pub closes_at: i64,
+}
+
+// Lifetime track record, kept across sessions. Amounts are lamports from SOL
+// sessions only; token sessions count towards the session totals and streaks.
+#[account]
+#[derive(InitSpace)]
+pub struct UserStats {
+    pub user: Pubkey,
+    pub sessions_started: u64,
+    pub sessions_completed: u64,
+    pub sessions_failed: u64,
+    pub sessions_expired: u64,
+    pub minutes_focused: u64, // Focused time of completed and failed sessions
+    pub total_staked: u64,    // Including the focus pool fee
+    pub total_refunded: u64,
+    pub total_forfeited: u64, // Lost to the failure pool, keeper bounties included
+    pub total_streak_bonus: u64, // Paid from the focus pool on top of claim refunds
+    pub current_streak: u64,  // Full-length completions since the last failed or expired session
+    pub best_streak: u64,
+    pub last_session_at: i64,        // Start time of the most recent session
+    pub last_streak_completion: i64, // When the streak last grew; sessions started before don't extend it
+}
+
+impl UserStats {
+    pub fn record_start(&mut self, focus_session: &FocusSession, staked: u64) -> Result<()> {
+        self.user = focus_session.user;
+        self.sessions_started = checked_inc(self.sessions_started, 1)?;
+        if focus_session.mint.is_none() {
+            self.total_staked = checked_inc(self.total_staked, staked)?;
+        }
+        self.last_session_at = focus_session.start_time;
+        Ok(())
+    }
+
+    /// Counts a completion and returns whether it extended the streak: a
+    /// `full_length` session started after the last one that did, so sessions
+    /// run side by side count once.
+    pub fn record_completed(
+        &mut self,
+        focus_session: &FocusSession,
+        full_length: bool,
+        now: i64,
+    ) -> Result<bool> {
+        self.sessions_completed = checked_inc(self.sessions_completed, 1)?;
+        // time spent past the duration doesn't count
+        let minutes = focus_session
+            .focused_minutes(now)
+            .min(focus_session.duration_minutes);
+        self.minutes_focused = checked_inc(self.minutes_focused, minutes)?;
+        let extends_streak = full_length && focus_session.start_time >= self.last_streak_completion;
+        if extends_streak {
+            self.current_streak = checked_inc(self.current_streak, 1)?;
+            self.best_streak = self.best_streak.max(self.current_streak);
+            self.last_streak_completion = now;
+        }
+        Ok(extends_streak)
+    }
+
+    pub fn record_failed(
+        &mut self,
+        focus_session: &FocusSession,
+        now: i64,
+        refunded: u64,
+        forfeited: u64,
+    ) -> Result<()> {
+        self.sessions_failed = checked_inc(self.sessions_failed, 1)?;
+        self.minutes_focused =
+            checked_inc(self.minutes_focused, focus_session.focused_minutes(now))?;
+        self.current_streak = 0;
+        self.record_payout(focus_session, refunded, forfeited)
+    }
+
+    pub fn record_expired(&mut self, focus_session: &FocusSession) -> Result<()> {
+        self.sessions_expired = checked_inc(self.sessions_expired, 1)?;
+        self.current_streak = 0;
+        self.record_payout(focus_session, 0, focus_session.stake_amount)
+    }
+
+    /// Adds what a settlement returned to the user and what it forfeited.
+    pub fn record_payout(
+        &mut self,
+        focus_session: &FocusSession,
+        refunded: u64,
+        forfeited: u64,
+    ) -> Result<()> {
+        if focus_session.mint.is_none() {
+            self.total_refunded = checked_inc(self.total_refunded, refunded)?;
+            self.total_forfeited = checked_inc(self.total_forfeited, forfeited)?;
+        }
+        Ok(())
+    }
+}
+
+fn checked_inc(value: u64, amount: u64) -> Result<u64> {
+    value
+        .checked_add(amount)
+        .ok_or(error!(ErrorCode::MathError))
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct FocusSession {
+    pub user: Pubkey,
+    pub session_id: u64,
+    pub mint: Option<Pubkey>, // Staked SPL mint, None for native SOL
+    pub is_active: bool,
+    pub stake_amount: u64,
+    pub start_time: i64,
+    pub duration_minutes: u64,
+    pub pending_balance: u64, // Amount available to claim
+    #[max_len(20)]
+    pub tasks: Vec<Task>, // On-chain task list
+    pub partner: Option<Pubkey>, // Accountability partner who must sign off task completion
+    pub pending_approvals: u32, // Bitmask of tasks awaiting partner approval
+    pub max_pause_seconds: u64, // Pause budget snapshotted at session start
+    pub paused_at: i64,       // Start of the current pause, 0 when running
+    #[max_len(10)]
+    pub pauses: Vec<PauseInterval>, // Completed pauses
+    pub streak_bonus_bps: u16, // Streak tier reached at completion (SOL sessions only)
+    pub session_key: Option<SessionKey>, // Can update tasks and complete, never fail or claim
+    pub rent_payer: Pubkey,   // Paid the rent at start and gets it back on close
+    pub heartbeat_interval_seconds: u32, // Heartbeat policy snapshotted at session start
+    pub min_heartbeat_coverage_bps: u16,
+    pub max_missed_heartbeats: u16,
+    pub last_heartbeat: i64,      // 0 until the first heartbeat
+    pub last_heartbeat_slot: u64, // Latest slot (unix time / interval) with a heartbeat
+    pub heartbeats: [u8; 256],    // Ring of the last HEARTBEAT_SLOTS slots, one bit each
+    pub challenge_rate_bps: u16,  // Spot-check policy snapshotted at session start
+    pub challenge_window_seconds: u32,
+    pub attested_focus_bps: u16, // Share of the refund kept after the focus report
+    pub challenge: Option<Challenge>, // Spot-check awaiting an answer, kept once missed
+}
+
+impl FocusSession {
+    // The owner, or their session key until it expires
+    pub fn require_signer(&self, signer: Pubkey, now: i64) -> Result<()> {
+        if signer == self.user {
+            return Ok(());
+        }
+        match &self.session_key {
+            Some(session_key) if session_key.key == signer => {
+                require!(now < session_key.expires_at, ErrorCode::SessionKeyExpired);
+                Ok(())
+            }
+            _ => err!(ErrorCode::UnauthorizedSigner),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at != 0
+    }
+
+    /// Total time spent paused, including a pause still in progress.
+    pub fn paused_seconds(&self, now: i64) -> i64 {
+        let completed: i64 = self.pauses.iter().map(|p| p.end - p.start).sum();
+        let ongoing = if self.is_paused() {
+            now - self.paused_at
+        } else {
+            0
+        };
+        completed + ongoing
+    }
+
+    /// Whether heartbeats stopped for longer than the session allows; a lapsed
+    /// session can no longer be completed and is expirable right away. Time spent
+    /// paused since the last heartbeat doesn't count, but resuming isn't a heartbeat.
+    pub fn heartbeat_lapsed(&self, now: i64) -> bool {
+        if self.heartbeat_interval_seconds == 0 || self.max_missed_heartbeats == 0 {
+            return false;
+        }
+        let last_seen = self.last_heartbeat.max(self.start_time);
+        let ongoing = self.is_paused().then_some((self.paused_at, now));
+        let paused: i64 = self
+            .pauses
+            .iter()
+            .map(|pause| (pause.start, pause.end))
+            .chain(ongoing)
+            .map(|(start, end)| (end - start.max(last_seen)).max(0))
+            .sum();
+        let max_gap =
+            (self.max_missed_heartbeats as i64 + 1) * self.heartbeat_interval_seconds as i64;
+        now - last_seen - paused > max_gap
+    }
+
+    fn heartbeat_bit(slot: u64) -> (usize, u8) {
+        let index = slot % HEARTBEAT_SLOTS;
+        ((index / 8) as usize, 1 << (index % 8))
+    }
+
+    /// Marks the slot `now` falls in, forgetting slots that have left the ring.
+    pub fn record_heartbeat(&mut self, now: i64) {
+        let slot = now as u64 / self.heartbeat_interval_seconds as u64;
+        if slot >= self.last_heartbeat_slot + HEARTBEAT_SLOTS {
+            self.heartbeats = [0; 256];
+        } else {
+            // skipped slots still hold bits from a full ring ago
+            for missed in self.last_heartbeat_slot + 1..slot {
+                let (byte, bit) = Self::heartbeat_bit(missed);
+                self.heartbeats[byte] &= !bit;
+            }
+        }
+        let (byte, bit) = Self::heartbeat_bit(slot);
+        self.heartbeats[byte] |= bit;
+        self.last_heartbeat_slot = self.last_heartbeat_slot.max(slot);
+        self.last_heartbeat = now;
+    }
+
+    /// Heartbeats in slots `from..=to`.
+    pub fn heartbeats_between(&self, from: u64, to: u64) -> u64 {
+        let oldest = (self.last_heartbeat_slot + 1).saturating_sub(HEARTBEAT_SLOTS);
+        (from.max(oldest)..=to.min(self.last_heartbeat_slot))
+            .filter(|&slot| {
+                let (byte, bit) = Self::heartbeat_bit(slot);
+                self.heartbeats[byte] & bit != 0
+            })
+            .count() as u64
+    }
+
+    /// Fails unless heartbeats cover enough of the session's focused intervals.
+    pub fn require_heartbeat_coverage(&self, now: i64) -> Result<()> {
+        if self.heartbeat_interval_seconds == 0 {
+            return Ok(());
+        }
+        let interval = self.heartbeat_interval_seconds as u64;
+        let focused = (self.focused_seconds(now).max(0) as u64).min(self.duration_minutes * 60);
+        let expected = focused / interval;
+        let covered = self
+            .heartbeats_between(self.start_time as u64 / interval, now as u64 / interval)
+            .min(expected);
+        require!(
+            covered as u128 * BPS_DENOMINATOR as u128
+                >= expected as u128 * self.min_heartbeat_coverage_bps as u128,
+            ErrorCode::InsufficientHeartbeats
+        );
+        Ok(())
+    }
+
+    /// Whether the session's spot-check closed without an answer.
+    pub fn missed_challenge(&self, now: i64) -> bool {
+        matches!(self.challenge, Some(challenge) if now > challenge.closes_at)
+    }
+
+    /// Schedules a spot-check with the session's challenge rate, seeded by the
+    /// latest slot hash so nobody can tell ahead of the heartbeat when it lands.
+    pub fn draw_challenge(&mut self, slot_hash: &[u8], now: i64) {
+        if self.challenge_rate_bps == 0 || self.challenge.is_some() {
+            return;
+        }
+        let seed = hashv(&[
+            slot_hash,
+            self.user.as_ref(),
+            &self.session_id.to_le_bytes(),
+            &now.to_le_bytes(),
+        ])
+        .to_bytes();
+        let roll = u16::from_le_bytes([seed[0], seed[1]]) as u64 % BPS_DENOMINATOR;
+        if roll >= self.challenge_rate_bps as u64 {
+            return;
+        }
+        // the whole window has to fit in what's left of the session
+        let remaining =
+            (self.duration_minutes as i64 * 60).saturating_sub(self.focused_seconds(now));
+        let room = remaining - self.challenge_window_seconds as i64;
+        if room <= 0 {
+            return;
+        }
+        let delay = u64::from_le_bytes(seed[8..16].try_into().unwrap()) % room as u64;
+        let opens_at = now + delay as i64;
+        self.challenge = Some(Challenge {
+            opens_at,
+            closes_at: opens_at + self.challenge_window_seconds as i64,
+        });
+    }
+
+    /// Fails if the session's spot-check was missed or is still open.
+    pub fn require_challenges_answered(&self, now: i64) -> Result<()> {
+        require!(!self.missed_challenge(now), ErrorCode::ChallengeMissed);
+        require!(self.challenge.is_none(), ErrorCode::ChallengePending);
+        Ok(())
+    }
+
+    /// Share of the refund a focus report keeps: its focused share of the duration,
+    /// less the distraction penalty. Fails below the configured minimum.
+    pub fn attested_focus_share(
+        &self,
+        report: &FocusReport,
+        config: &Config,
+        now: i64,
+    ) -> Result<u16> {
+        require_keys_eq!(report.user, self.user, ErrorCode::InvalidFocusReport);
+        require!(
+            report.session_start == self.start_time
+                && report.focused_minutes as i64 * 60 <= now - self.start_time,
+            ErrorCode::InvalidFocusReport
+        );
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        let focus_bps = (report.focused_minutes as u64 * BPS_DENOMINATOR / self.duration_minutes)
+            .min(BPS_DENOMINATOR);
+        require!(
+            focus_bps >= config.min_attested_focus_bps as u64,
+            ErrorCode::InsufficientAttestedFocus
+        );
+        let penalty_bps = report.distractions as u64 * config.distraction_penalty_bps as u64;
+        Ok(focus_bps.saturating_sub(penalty_bps) as u16)
+    }
 
-#[derive(Accounts)]
-pub struct FailFocusSession<'info> {
-    #[account(
-        mut,
-        close = user,
-        seeds = [b"user_state", user.key().as_ref()],
-        bump,
-        has_one = user
-    )]
-    pub user_state: Account<'info, UserState>,
+    /// Fails unless enough focused time has passed to complete the session.
+    pub fn require_completable(&self, grace_minutes: u64, now: i64) -> Result<()> {
+        require!(!self.heartbeat_lapsed(now), ErrorCode::HeartbeatLapsed);
+        let elapsed_minutes = self.focused_seconds(now) / 60;
+        // allow a small grace window for completion confirmation
+        let required_minutes = (self.duration_minutes as i64).saturating_sub(grace_minutes as i64);
+        require!(
+            elapsed_minutes >= required_minutes,
+            ErrorCode::SessionNotComplete
+        );
+        Ok(())
+    }
 
-    #[account(
-        mut,
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
+    /// Whether completing now is full-length enough to count towards the streak:
+    /// the full duration has passed, and it is longer than the early-completion
+    /// grace window, so a run of near-empty sessions can't farm streak tiers.
+    pub fn extends_streak(&self, grace_minutes: u64, now: i64) -> bool {
+        self.duration_minutes > grace_minutes
+            && self.focused_seconds(now) >= self.duration_minutes as i64 * 60
+    }
 
-    /// CHECK: Vault PDA
-    #[account(
-        mut,
-        seeds = [b"vault", global_state.key().as_ref()],
-        bump = global_state.vault_bump
-    )]
-    pub vault: UncheckedAccount<'info>,
+    /// Fails unless the session has overrun its duration plus the grace window,
+    /// its heartbeats have lapsed or it missed a spot-check.
+    pub fn require_expirable(&self, grace_minutes: u64, now: i64) -> Result<()> {
+        if self.heartbeat_lapsed(now) || self.missed_challenge(now) {
+            return Ok(());
+        }
+        // a session left paused past its pause budget starts counting down again
+        let elapsed_minutes = self.focused_seconds(now) / 60;
+        let required_minutes = self.duration_minutes as i64 + grace_minutes as i64;
+        require!(
+            elapsed_minutes >= required_minutes,
+            ErrorCode::SessionNotComplete
+        );
+        Ok(())
+    }
 
-    /// CHECK: Failure pool vault PDA
-    #[account(
-        mut,
-        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
-        bump = global_state.failure_pool_bump
-    )]
-    pub failure_pool_vault: UncheckedAccount<'info>,
+    /// Part of the active stake returned on a voluntary early exit; nothing once
+    /// heartbeats have lapsed or a spot-check was missed, since a keeper could
+    /// expire the session instead.
+    pub fn early_exit_refund(&self, refund_curve: &RefundCurve, now: i64) -> Result<u64> {
+        if self.heartbeat_lapsed(now) || self.missed_challenge(now) {
+            return Ok(0);
+        }
+        refund_curve.refund_amount(
+            self.stake_amount,
+            self.focused_seconds(now),
+            self.duration_minutes * 60,
+        )
+    }
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    pub fn completed_tasks(&self) -> u8 {
+        self.tasks.iter().filter(|t| t.completed).count() as u8
+    }
 
-#[derive(Accounts)]
-pub struct ExpireFocusSession<'info> {
-    #[account(
-        mut,
-        close = receiver,
-        seeds = [b"user_state", user.key().as_ref()],
-        bump,
-        has_one = user
-    )]
-    pub user_state: Account<'info, UserState>,
+    /// Splits pending_balance into (refund, penalty) by the share of completed tasks
+    /// and the attested focus.
+    pub fn claim_split(&self) -> Result<(u64, u64)> {
+        let total_tasks = self.tasks.len() as u64;
+        let completed_tasks = self.completed_tasks() as u64;
 
-    #[account(
-        mut,
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
+        require!(total_tasks > 0, ErrorCode::NoTasksProvided);
+        require!(self.pending_balance > 0, ErrorCode::NoPendingBalance);
 
-    /// CHECK: Vault PDA
-    #[account(
-        mut,
-        seeds = [b"vault", global_state.key().as_ref()],
-        bump = global_state.vault_bump
-    )]
-    pub vault: UncheckedAccount<'info>,
+        // If all tasks completed, get full stake back
+        // If some tasks incomplete, lose a percentage
+        let refund_percentage = (completed_tasks * 100) / total_tasks;
+        let refund_amount = (self.pending_balance as u128 * refund_percentage as u128) / 100;
+        let refund_amount =
+            refund_amount * self.attested_focus_bps as u128 / BPS_DENOMINATOR as u128;
+        let refund_amount =
+            u64::try_from(refund_amount).map_err(|_| error!(ErrorCode::MathError))?;
+        let penalty_amount = self.pending_balance.saturating_sub(refund_amount);
 
-    /// CHECK: Failure pool vault PDA
-    #[account(
-        mut,
-        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
-        bump = global_state.failure_pool_bump
-    )]
-    pub failure_pool_vault: UncheckedAccount<'info>,
+        Ok((refund_amount, penalty_amount))
+    }
 
-    /// CHECK: Receiver of closed account rent (user)
-    #[account(mut)]
-    pub receiver: SystemAccount<'info>,
+    /// Streak bonus on top of a claim `refund_amount`, before the focus pool cap.
+    pub fn streak_bonus(&self, refund_amount: u64) -> Result<u64> {
+        let bonus = refund_amount as u128 * self.streak_bonus_bps as u128 / BPS_DENOMINATOR as u128;
+        u64::try_from(bonus).map_err(|_| error!(ErrorCode::MathError))
+    }
 
-    /// CHECK: Associated user for PDA
-    /// not required to sign for permissionless expiry
-    /// only used as seed for user_state PDA
-    pub user: UncheckedAccount<'info>,
+    /// Stake still held in the vault for this session: active stake plus unclaimed balance.
+    pub fn principal(&self) -> Result<u64> {
+        self.stake_amount
+            .checked_add(self.pending_balance)
+            .ok_or(error!(ErrorCode::MathError))
+    }
 
-    pub system_program: Program<'info, System>,
+    /// Wall-clock time since start, minus paused time up to the pause budget.
+    pub fn focused_seconds(&self, now: i64) -> i64 {
+        let paused = self.paused_seconds(now).min(self.max_pause_seconds as i64);
+        (now - self.start_time).saturating_sub(paused)
+    }
+
+    pub fn focused_minutes(&self, now: i64) -> u64 {
+        (self.focused_seconds(now) / 60).max(0) as u64
+    }
 }
 
-#[derive(Accounts)]
-pub struct WithdrawFocusPool<'info> {
-    #[account(
-        mut,
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct PauseInterval {
+    pub start: i64,
+    pub end: i64,
+}
 
-    /// CHECK: Focus pool vault
-    #[account(
-        mut,
-        seeds = [b"focus_pool_vault", global_state.key().as_ref()],
-        bump = global_state.focus_pool_bump
-    )]
-    pub focus_pool_vault: UncheckedAccount<'info>,
+#[event]
+pub struct Initialized {
+    pub authority: Pubkey,
+    pub config: Config,
+    pub current_epoch: u64,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub recipient: SystemAccount<'info>,
+#[event]
+pub struct SessionStarted {
+    pub user: Pubkey,
+    pub session_id: u64,
+    pub mint: Option<Pubkey>,
+    pub stake_amount: u64,   // Locked in the vault, after the focus pool fee
+    pub focus_pool_fee: u64, // Sent to the focus pool
+    pub duration_minutes: u64,
+    pub task_count: u8,
+    pub partner: Option<Pubkey>,
+    pub start_time: i64,
+    pub focus_pool: u64, // Pool total after the fee
+}
 
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+impl SessionStarted {
+    fn new(focus_session: &FocusSession, focus_pool_fee: u64, focus_pool: u64) -> Self {
+        SessionStarted {
+            user: focus_session.user,
+            session_id: focus_session.session_id,
+            mint: focus_session.mint,
+            stake_amount: focus_session.stake_amount,
+            focus_pool_fee,
+            duration_minutes: focus_session.duration_minutes,
+            task_count: focus_session.tasks.len() as u8,
+            partner: focus_session.partner,
+            start_time: focus_session.start_time,
+            focus_pool,
+        }
+    }
 }
 
-#[derive(Accounts)]
-pub struct WithdrawFailurePool<'info> {
-    #[account(
-        mut,
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
+#[event]
+pub struct SessionCompleted {
+    pub user: Pubkey,
+    pub session_id: u64,
+    pub mint: Option<Pubkey>,
+    pub stake_amount: u64,
+    pub returned_amount: u64, // Paid out immediately (legacy completion only)
+    pub pending_balance: u64, // Claimable via claim_rewards
+    pub duration_minutes: u64,
+    pub focused_seconds: i64,
+    pub task_count: u8,
+    pub timestamp: i64,
+}
 
-    /// CHECK: Failure pool vault
-    #[account(
-        mut,
-        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
-        bump = global_state.failure_pool_bump
-    )]
-    pub failure_pool_vault: UncheckedAccount<'info>,
+#[event]
+pub struct SessionFailed {
+    pub user: Pubkey,
+    pub session_id: u64,
+    pub mint: Option<Pubkey>,
+    pub stake_amount: u64,
+    pub refund_amount: u64,    // Early-exit refund paid to the user
+    pub forfeited_amount: u64, // Moved to the failure pool
+    pub duration_minutes: u64,
+    pub focused_seconds: i64,
+    pub task_count: u8,
+    pub failure_pool: u64, // Pool total after the forfeit
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub recipient: SystemAccount<'info>,
+impl SessionFailed {
+    fn new(
+        focus_session: &FocusSession,
+        refund_amount: u64,
+        forfeited_amount: u64,
+        failure_pool: u64,
+        now: i64,
+    ) -> Self {
+        SessionFailed {
+            user: focus_session.user,
+            session_id: focus_session.session_id,
+            mint: focus_session.mint,
+            stake_amount: focus_session.stake_amount,
+            refund_amount,
+            forfeited_amount,
+            duration_minutes: focus_session.duration_minutes,
+            focused_seconds: focus_session.focused_seconds(now),
+            task_count: focus_session.tasks.len() as u8,
+            failure_pool,
+            timestamp: now,
+        }
+    }
+}
 
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct SessionExpired {
+    pub user: Pubkey,
+    pub session_id: u64,
+    pub mint: Option<Pubkey>,
+    pub forfeited_amount: u64, // Moved to the failure pool
+    pub keeper: Pubkey,
+    pub keeper_bounty: u64, // Paid to the keeper out of the stake
+    pub duration_minutes: u64,
+    pub start_time: i64,
+    pub task_count: u8,
+    pub failure_pool: u64, // Pool total after the forfeit
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct ClaimRewards<'info> {
-    #[account(
-        mut,
-        close = user,
-        seeds = [b"user_state", user.key().as_ref()],
-        bump,
-        has_one = user
-    )]
-    pub user_state: Account<'info, UserState>,
+impl SessionExpired {
+    fn new(
+        focus_session: &FocusSession,
+        forfeited_amount: u64,
+        keeper: Pubkey,
+        keeper_bounty: u64,
+        failure_pool: u64,
+        now: i64,
+    ) -> Self {
+        SessionExpired {
+            user: focus_session.user,
+            session_id: focus_session.session_id,
+            mint: focus_session.mint,
+            forfeited_amount,
+            keeper,
+            keeper_bounty,
+            duration_minutes: focus_session.duration_minutes,
+            start_time: focus_session.start_time,
+            task_count: focus_session.tasks.len() as u8,
+            failure_pool,
+            timestamp: now,
+        }
+    }
+}
 
-    #[account(
-        mut,
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
+#[event]
+pub struct TaskUpdated {
+    pub user: Pubkey,
+    pub session_id: u64,
+    pub task_index: u8,
+    pub completed: bool,             // Recorded state of the task
+    pub pending_approval: bool,      // Waiting on the accountability partner
+    pub approved_by: Option<Pubkey>, // Partner who signed off, if any
+    pub completed_tasks: u8,
+    pub task_count: u8,
+    pub timestamp: i64,
+}
 
-    /// CHECK: Vault PDA
-    #[account(
-        mut,
-        seeds = [b"vault", global_state.key().as_ref()],
-        bump = global_state.vault_bump
-    )]
-    pub vault: UncheckedAccount<'info>,
+impl TaskUpdated {
+    fn new(
+        focus_session: &FocusSession,
+        task_index: u8,
+        approved_by: Option<Pubkey>,
+        now: i64,
+    ) -> Self {
+        TaskUpdated {
+            user: focus_session.user,
+            session_id: focus_session.session_id,
+            task_index,
+            completed: focus_session.tasks[task_index as usize].completed,
+            pending_approval: focus_session.pending_approvals & (1u32 << task_index) != 0,
+            approved_by,
+            completed_tasks: focus_session.completed_tasks(),
+            task_count: focus_session.tasks.len() as u8,
+            timestamp: now,
+        }
+    }
+}
 
-    /// CHECK: Failure pool vault PDA
-    #[account(
-        mut,
-        seeds = [b"failure_pool_vault", global_state.key().as_ref()],
-        bump = global_state.failure_pool_bump
-    )]
-    pub failure_pool_vault: UncheckedAccount<'info>,
+#[event]
+pub struct RewardsClaimed {
+    pub user: Pubkey,
+    pub session_id: u64,
+    pub mint: Option<Pubkey>,
+    pub refund_amount: u64,
+    pub penalty_amount: u64, // Moved to the failure pool for incomplete tasks
+    pub streak_bonus: u64,   // Paid from the focus pool on top of the refund
+    pub completed_tasks: u8,
+    pub task_count: u8,
+    pub failure_pool: u64, // Pool total after the penalty
+    pub focus_pool: u64,   // Pool total after the streak bonus
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+impl RewardsClaimed {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        focus_session: &FocusSession,
+        refund_amount: u64,
+        penalty_amount: u64,
+        streak_bonus: u64,
+        failure_pool: u64,
+        focus_pool: u64,
+        now: i64,
+    ) -> Self {
+        RewardsClaimed {
+            user: focus_session.user,
+            session_id: focus_session.session_id,
+            mint: focus_session.mint,
+            refund_amount,
+            penalty_amount,
+            streak_bonus,
+            completed_tasks: focus_session.completed_tasks(),
+            task_count: focus_session.tasks.len() as u8,
+            failure_pool,
+            focus_pool,
+            timestamp: now,
+        }
+    }
 }
 
-#[derive(Accounts)]
-pub struct UpdateTask<'info> {
-    #[account(
-        mut,
-        seeds = [b"user_state", user.key().as_ref()],
-        bump,
-        has_one = user
-    )]
-    pub user_state: Account<'info, UserState>,
+#[event]
+pub struct EmergencyWithdrawn {
+    pub user: Pubkey,
+    pub session_id: u64,
+    pub mint: Option<Pubkey>,
+    pub amount: u64, // Principal returned: active stake plus unclaimed balance
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+impl EmergencyWithdrawn {
+    fn new(focus_session: &FocusSession, amount: u64, now: i64) -> Self {
+        EmergencyWithdrawn {
+            user: focus_session.user,
+            session_id: focus_session.session_id,
+            mint: focus_session.mint,
+            amount,
+            timestamp: now,
+        }
+    }
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct GlobalState {
-    pub authority: Pubkey,
-    pub focus_pool: u64,
-    pub failure_pool: u64,
-    pub total_sessions: u64,
-    pub vault_bump: u8,
-    pub focus_pool_bump: u8,
-    pub failure_pool_bump: u8,
+#[event]
+pub struct EpochRewardsClaimed {
+    pub user: Pubkey,
+    pub epoch_id: u64,
+    pub shares: u128,
+    pub reward: u64,
+    pub failure_pool: u64, // Pool total after the payout
+    pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
-pub struct Task {
-    #[max_len(100)]
-    pub description: String,
-    pub completed: bool,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PoolKind {
+    Focus,
+    Failure,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct UserState {
-    pub user: Pubkey,
-    pub is_active: bool,
-    pub stake_amount: u64,
-    pub start_time: i64,
-    pub duration_minutes: u64,
-    pub pending_balance: u64, // Amount available to claim
-    #[max_len(20)]
-    pub tasks: Vec<Task>, // On-chain task list
+#[event]
+pub struct PoolWithdrawn {
+    pub pool: PoolKind,
+    pub mint: Option<Pubkey>, // None for the SOL pools
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub pool_balance: u64, // Pool total after the withdrawal
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Reconciled {
+    pub vault_balance: u64, // Vault lamports above rent
+    pub vault_owed: u64,    // total_active_stake + total_pending_balance
+    pub focus_pool_balance: u64,
+    pub focus_pool: u64,
+    pub failure_pool_balance: u64,
+    pub failure_pool: u64,
+    pub shortfall: u64, // Lamports missing across the three vaults; 0 when solvent
+    pub withdrawals_paused: bool,
+    pub timestamp: i64,
 }
 
 #[error_code]
 pub enum ErrorCode {
-    #[msg("Stake amount too low. Minimum 0.01 SOL")]
+    #[msg("Stake amount below the configured minimum")]
     StakeTooLow,
-    #[msg("Invalid duration. Must be between 1-480 minutes")]
+    #[msg("Invalid duration. Must be between 1 minute and the configured maximum")]
     InvalidDuration,
-    #[msg("User already has an active session")]
-    SessionAlreadyActive,
     #[msg("No active session found")]
     NoActiveSession,
     #[msg("Session duration not yet complete")]
@@ -771,7 +4591,7 @@ pub enum ErrorCode {
     MathError,
     #[msg("No tasks provided")]
     NoTasksProvided,
-    #[msg("Too many tasks. Maximum 20 tasks")]
+    #[msg("Too many tasks for the configured maximum")]
     TooManyTasks,
     #[msg("Session is still active")]
     SessionStillActive,
@@ -779,5 +4599,74 @@ pub enum ErrorCode {
     InvalidTaskIndex,
     #[msg("No pending balance to claim")]
     NoPendingBalance,
+    #[msg("Invalid accountability partner")]
+    InvalidPartner,
+    #[msg("Session is paused")]
+    SessionPaused,
+    #[msg("Session is not paused")]
+    SessionNotPaused,
+    #[msg("Too many pauses for this session")]
+    TooManyPauses,
+    #[msg("Pause budget exhausted for this session")]
+    PauseBudgetExhausted,
+    #[msg("Invalid protocol config")]
+    InvalidConfig,
+    #[msg("Invalid refund curve")]
+    InvalidRefundCurve,
+    #[msg("Mint is not allowed for new sessions")]
+    MintNotAllowed,
+    #[msg("Mint has extensions the vaults cannot support")]
+    UnsupportedMint,
+    #[msg("Session was staked with a different asset")]
+    AssetMismatch,
+    #[msg("Reward epoch has not run its full duration")]
+    EpochNotOver,
+    #[msg("Reward epoch is not closed yet")]
+    EpochNotClosed,
+    #[msg("Amount exceeds the unreserved failure pool")]
+    PoolFundsReserved,
+    #[msg("Signer is not the pending authority")]
+    NotPendingAuthority,
+    #[msg("New sessions are paused")]
+    NewSessionsPaused,
+    #[msg("Settlements are paused")]
+    SettlementsPaused,
+    #[msg("Pool withdrawals are paused")]
+    WithdrawalsPaused,
+    #[msg("Emergency withdraw is only available while new sessions are paused")]
+    EmergencyWithdrawDisabled,
+    #[msg("Batch accounts must be (user, focus_session, user_stats, rent_payer) groups")]
+    InvalidBatchAccounts,
+    #[msg("Invalid streak tiers")]
+    InvalidStreakTiers,
+    #[msg("Session key must differ from the owner and expire within a week")]
+    InvalidSessionKey,
+    #[msg("Session key has expired")]
+    SessionKeyExpired,
+    #[msg("Signer is neither the session owner nor its session key")]
+    UnauthorizedSigner,
+    #[msg("Rent payer does not match the one recorded at session start")]
+    InvalidRentPayer,
+    #[msg("Heartbeats are not enabled for this session")]
+    HeartbeatsDisabled,
+    #[msg("Heartbeats stopped for too long; the session can only be failed or expired")]
+    HeartbeatLapsed,
+    #[msg("Not enough of the session is covered by heartbeats")]
+    InsufficientHeartbeats,
+    #[msg("A spot-check was missed; the session can only be failed or expired")]
+    ChallengeMissed,
+    #[msg("A spot-check is still waiting for an answer")]
+    ChallengePending,
+    #[msg("No spot-check is open for an answer")]
+    NoOpenChallenge,
+    #[msg("SlotHashes sysvar has no entries")]
+    SlotHashesUnavailable,
+    #[msg("Completion needs the attestor's focus report in the preceding Ed25519 instruction")]
+    FocusReportRequired,
+    #[msg("Focus report isn't signed by the attestor or doesn't match the session")]
+    InvalidFocusReport,
+    #[msg("Focus report covers too little of the session")]
+    InsufficientAttestedFocus,
+    #[msg("Account is not in a layout that can be migrated")]
+    UnknownStateLayout,
 }
-
@@ -0,0 +1,319 @@
+//! Scenario tests on a real bank through `solana-program-test`: the runtime
+//! checks Ed25519 precompile signatures, serves the sysvars and runs the
+//! system and token programs the deepwork program invokes.
+//!
+//! `cargo test` runs the native build as a builtin, its CPIs going through
+//! the patched `solana-invoke` like in `common`. `cargo test-sbf` runs the
+//! same tests against the SBF build in `target/deploy`, which compiles the
+//! upstream `solana-invoke` and needs no patch.
+
+use anchor_lang::prelude::AccountInfo;
+use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::system_instruction;
+use anchor_lang::AccountDeserialize;
+use anchor_program as deepwork;
+use deepwork::ErrorCode;
+use deepwork_client::{
+    instruction as ix, pda, Config, FocusReport, FocusSession, GlobalState, Task,
+};
+use solana_precompile_error::PrecompileError;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+const STAKE: u64 = LAMPORTS_PER_SOL;
+// Locked in the vault after the default 1% focus pool fee
+const LOCKED: u64 = STAKE - STAKE / 100;
+
+struct Bank {
+    context: ProgramTestContext,
+}
+
+impl Bank {
+    async fn new() -> Self {
+        let program_test = ProgramTest::new("anchor_program", deepwork::ID, processor!(process));
+        Bank {
+            context: program_test.start_with_context().await,
+        }
+    }
+
+    /// Sends `instructions` in one transaction with the context's payer paying
+    /// the fee, so `signers` only see the program's own transfers.
+    async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransactionError> {
+        let blockhash = self
+            .context
+            .get_new_latest_blockhash()
+            .await
+            .expect("blockhash");
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        match self
+            .context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+        {
+            Ok(()) => Ok(()),
+            Err(BanksClientError::TransactionError(err)) => Err(err),
+            Err(BanksClientError::SimulationError { err, .. }) => Err(err),
+            Err(err) => panic!("transaction not processed: {err}"),
+        }
+    }
+
+    async fn funded_user(&mut self) -> Keypair {
+        let user = Keypair::new();
+        let payer = self.context.payer.pubkey();
+        self.send(
+            &[system_instruction::transfer(
+                &payer,
+                &user.pubkey(),
+                10 * LAMPORTS_PER_SOL,
+            )],
+            &[],
+        )
+        .await
+        .unwrap();
+        user
+    }
+
+    async fn balance(&mut self, key: &Pubkey) -> u64 {
+        self.context.banks_client.get_balance(*key).await.unwrap()
+    }
+
+    async fn exists(&mut self, key: &Pubkey) -> bool {
+        self.context
+            .banks_client
+            .get_account(*key)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    async fn get<T: AccountDeserialize>(&mut self, key: &Pubkey) -> T {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*key)
+            .await
+            .unwrap()
+            .expect("account exists");
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn global_state(&mut self) -> GlobalState {
+        self.get(&pda::global_state()).await
+    }
+
+    async fn focus_session(&mut self, user: &Pubkey, session_id: u64) -> FocusSession {
+        self.get(&pda::focus_session(user, session_id)).await
+    }
+
+    async fn clock(&mut self) -> Clock {
+        self.context.banks_client.get_sysvar().await.unwrap()
+    }
+
+    /// Moves the bank's clock `seconds` ahead.
+    async fn warp(&mut self, seconds: i64) {
+        let mut clock = self.clock().await;
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    async fn initialize(&mut self) -> Keypair {
+        let authority = self.funded_user().await;
+        self.send(&[ix::initialize(&authority.pubkey())], &[&authority])
+            .await
+            .unwrap();
+        authority
+    }
+
+    async fn start_session(&mut self, user: &Keypair, duration_minutes: u64) -> u64 {
+        let session_id = 0;
+        self.send(
+            &[ix::start_focus_session(
+                &user.pubkey(),
+                &user.pubkey(),
+                session_id,
+                STAKE,
+                duration_minutes,
+                vec![Task {
+                    description: "a".to_string(),
+                    completed: false,
+                }],
+                None,
+                None,
+            )],
+            &[user],
+        )
+        .await
+        .unwrap();
+        session_id
+    }
+}
+
+/// The native build's entrypoint as a builtin.
+fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    // the infos outlive the call, which is all Anchor's `'info` asks for
+    let accounts: &[AccountInfo<'static>] = unsafe { std::mem::transmute(accounts) };
+    deepwork::entry(program_id, accounts, data)
+}
+
+fn program_error(code: ErrorCode, index: u8) -> TransactionError {
+    TransactionError::InstructionError(index, InstructionError::Custom(code.into()))
+}
+
+#[tokio::test]
+async fn session_settles_on_the_sbf_runtime() {
+    let mut bank = Bank::new().await;
+    bank.initialize().await;
+    let user = bank.funded_user().await;
+    let key = user.pubkey();
+
+    let before = bank.balance(&key).await;
+    let session_id = bank.start_session(&user, 30).await;
+    let session_rent = bank.balance(&pda::focus_session(&key, session_id)).await;
+    // the user also paid the rent of their user state and stats
+    let state_rent =
+        bank.balance(&pda::user_state(&key)).await + bank.balance(&pda::user_stats(&key)).await;
+    assert_eq!(
+        bank.balance(&key).await,
+        before - STAKE - session_rent - state_rent
+    );
+    assert_eq!(bank.global_state().await.total_active_stake, LOCKED);
+
+    // too early, then on time
+    let current_epoch = bank.global_state().await.current_epoch;
+    let complete = ix::complete_focus_session(&key, &key, session_id, current_epoch);
+    assert_eq!(
+        bank.send(std::slice::from_ref(&complete), &[&user]).await,
+        Err(program_error(ErrorCode::SessionNotComplete, 0))
+    );
+    bank.warp(30 * 60).await;
+    bank.send(&[complete], &[&user]).await.unwrap();
+    assert_eq!(
+        bank.focus_session(&key, session_id).await.pending_balance,
+        LOCKED
+    );
+
+    bank.send(
+        &[
+            ix::update_task(&key, &key, session_id, None, 0, true),
+            ix::claim_rewards(&key, &key, session_id),
+        ],
+        &[&user],
+    )
+    .await
+    .unwrap();
+    // the session's rent comes back with the refund; completing it opened the
+    // user's share of the reward epoch
+    assert!(!bank.exists(&pda::focus_session(&key, session_id)).await);
+    let share_rent = bank.balance(&pda::epoch_share(current_epoch, &key)).await;
+    assert_eq!(
+        bank.balance(&key).await,
+        before - STAKE / 100 - state_rent - share_rent
+    );
+    let global_state = bank.global_state().await;
+    assert_eq!(global_state.focus_pool, STAKE / 100);
+    assert_eq!(global_state.total_active_stake, 0);
+    assert_eq!(global_state.total_pending_balance, 0);
+    let vault_rent = bank.balance(&pda::vault()).await;
+    assert_eq!(
+        bank.balance(&pda::focus_pool_vault()).await - vault_rent,
+        STAKE / 100
+    );
+}
+
+#[tokio::test]
+async fn focus_reports_go_through_the_ed25519_precompile() {
+    let mut bank = Bank::new().await;
+    let authority = bank.initialize().await;
+    let attestor = Keypair::new();
+    bank.send(
+        &[ix::set_attestor(
+            &authority.pubkey(),
+            Some(attestor.pubkey()),
+        )],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let user = bank.funded_user().await;
+    let key = user.pubkey();
+    let session_id = bank.start_session(&user, 30).await;
+    bank.warp(30 * 60).await;
+
+    let report = FocusReport {
+        user: key,
+        session_start: bank.focus_session(&key, session_id).await.start_time,
+        focused_minutes: 30,
+        distractions: 0,
+    };
+    let current_epoch = bank.global_state().await.current_epoch;
+    let complete = ix::complete_focus_session(&key, &key, session_id, current_epoch);
+    let signed_by =
+        |signer: &Keypair| -> [u8; 64] { signer.sign_message(&report.message()).into() };
+
+    // the bank rejects a signature that isn't the attestor's before the program runs
+    let forged = ix::verify_focus_report(&attestor.pubkey(), &report, &signed_by(&user));
+    assert_eq!(
+        bank.send(&[forged, complete.clone()], &[&user]).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(PrecompileError::InvalidSignature as u32)
+        ))
+    );
+
+    let verified = ix::verify_focus_report(&attestor.pubkey(), &report, &signed_by(&attestor));
+    bank.send(&[verified, complete], &[&user]).await.unwrap();
+    assert_eq!(
+        bank.focus_session(&key, session_id)
+            .await
+            .attested_focus_bps,
+        10_000
+    );
+}
+
+#[tokio::test]
+async fn heartbeats_draw_from_the_banks_slot_hashes() {
+    let mut bank = Bank::new().await;
+    let authority = bank.initialize().await;
+    let config = Config {
+        heartbeat_interval_seconds: 60,
+        challenge_rate_bps: 10_000,
+        ..Config::default()
+    };
+    bank.send(
+        &[ix::update_config(&authority.pubkey(), config)],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    let user = bank.funded_user().await;
+    let key = user.pubkey();
+    let session_id = bank.start_session(&user, 30).await;
+
+    let slot = bank.clock().await.slot;
+    bank.context.warp_to_slot(slot + 150).unwrap();
+    bank.warp(60).await;
+    bank.send(&[ix::heartbeat(&key, &key, session_id)], &[&user])
+        .await
+        .unwrap();
+    let session = bank.focus_session(&key, session_id).await;
+    assert_eq!(session.last_heartbeat, bank.clock().await.unix_timestamp);
+    // every heartbeat schedules a spot-check at this rate
+    assert!(session.challenge.is_some());
+}
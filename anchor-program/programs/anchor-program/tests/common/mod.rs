@@ -0,0 +1,964 @@
+//! In-process runtime for driving the deepwork program natively from tests.
+//!
+//! Instructions run through the program's Anchor `entry` against an in-memory
+//! account store, with the `Clock` sysvar under the test's control so that
+//! time-dependent paths can be exercised by warping the clock.
+//!
+//! Anchor routes CPIs through `solana-invoke`, which the workspace patches to
+//! hand them to the syscall stubs off-chain. The stubs serve them with an
+//! emulated system program and the real SPL Token and Token-2022 processors,
+//! so every instruction, including the ones that create accounts or move
+//! tokens, runs for real. Only the accounts a validator would already hold
+//! (funded wallets, mints, token accounts) are written directly.
+//!
+//! After every instruction the runtime checks the rules the real validator
+//! enforces: total lamports are conserved, read-only accounts are untouched,
+//! only the owning program debits or rewrites an account, and accounts stay
+//! rent-exempt.
+//!
+//! Anchor compiles `emit!` down to a no-op off-chain, so events can't be
+//! observed here; tests assert on the resulting account state instead.
+//!
+//! Like the runtime, the harness verifies Ed25519 precompile signatures before
+//! any program runs and fails the transaction on a bad one; the precompile
+//! instructions then only show up in the `Instructions` sysvar for the
+//! program to introspect.
+
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::slice;
+use std::sync::Once;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::rent::Rent;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as instructions_sysvar, construct_instructions_data, BorrowedAccountMeta,
+    BorrowedInstruction,
+};
+use anchor_lang::solana_program::sysvar::slot_hashes;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut},
+    state::{Account as TokenAccountState, AccountState as TokenState, Mint as MintState},
+};
+use deepwork_client::{
+    instruction as ix, pda, FocusSession, GlobalState, SessionKey, Task, UserState, UserStats,
+};
+#[allow(deprecated)]
+use solana_feature_set::FeatureSet;
+pub use solana_precompile_error::PrecompileError;
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+
+pub use anchor_program as deepwork;
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+pub const START_TIME: i64 = 1_700_000_000;
+
+const BPF_LOADER_UPGRADEABLE: Pubkey = pubkey!("BPFLoaderUpgradeab1e11111111111111111111111");
+const NATIVE_LOADER: Pubkey = pubkey!("NativeLoader1111111111111111111111111111111");
+const SYSVAR: Pubkey = pubkey!("Sysvar1111111111111111111111111111111111111");
+const ED25519_PROGRAM: Pubkey = pubkey!("Ed25519SigVerify111111111111111111111111111");
+const SUCCESS: u64 = 0;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccountState {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+// Syscall stubs are process-wide; the state they serve is per test thread
+thread_local! {
+    static CLOCK: RefCell<Clock> = RefCell::new(Clock::default());
+    // accounts a CPI callee legitimately debited or rewrote during the instruction
+    static CPI_WRITES: RefCell<HashSet<Pubkey>> = RefCell::new(HashSet::new());
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        CLOCK.with(|clock| unsafe { *(var_addr as *mut Clock) = clock.borrow().clone() });
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        invoke(instruction, account_infos, signers_seeds)
+    }
+}
+
+/// Runs a CPI from the deepwork program on the caller's `AccountInfo`s, so the
+/// callee writes straight into the outer instruction's buffers.
+fn invoke(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let pda_signers = signers_seeds
+        .iter()
+        .map(|seeds| Pubkey::create_program_address(seeds, &deepwork::ID))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+
+    let mut infos = Vec::with_capacity(instruction.accounts.len());
+    for meta in &instruction.accounts {
+        let info = account_infos
+            .iter()
+            .find(|info| *info.key == meta.pubkey)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let is_signer = info.is_signer || pda_signers.contains(&meta.pubkey);
+        if meta.is_signer && !is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        assert!(
+            info.is_writable || !meta.is_writable,
+            "CPI escalated {} to writable",
+            meta.pubkey
+        );
+        let mut info = info.clone();
+        info.is_signer = is_signer;
+        info.is_writable = meta.is_writable;
+        infos.push(info);
+    }
+
+    let before: Vec<AccountState> = infos.iter().map(AccountState::from).collect();
+    let program_id = instruction.program_id;
+    if program_id == system_program::ID {
+        system_program_processor(&infos, &instruction.data)?;
+    } else if program_id == spl_token::ID {
+        spl_token::processor::Processor::process(&program_id, &infos, &instruction.data)?;
+    } else if program_id == spl_token_2022::ID {
+        spl_token_2022::processor::Processor::process(&program_id, &infos, &instruction.data)?;
+    } else {
+        panic!("no native processor for {program_id}");
+    }
+
+    // the callee may only debit, rewrite or reassign accounts it owns
+    let after: Vec<AccountState> = infos.iter().map(AccountState::from).collect();
+    let mut seen = HashSet::new();
+    let (mut total_before, mut total_after) = (0u128, 0u128);
+    for ((info, old), new) in infos.iter().zip(&before).zip(&after) {
+        if !seen.insert(*info.key) {
+            continue;
+        }
+        total_before += old.lamports as u128;
+        total_after += new.lamports as u128;
+        if new.lamports < old.lamports || new.data != old.data || new.owner != old.owner {
+            assert_eq!(
+                old.owner, program_id,
+                "{program_id} debited or rewrote {} without owning it",
+                info.key
+            );
+            CPI_WRITES.with(|writes| writes.borrow_mut().insert(*info.key));
+        }
+    }
+    assert_eq!(total_before, total_after, "CPI did not conserve lamports");
+    Ok(())
+}
+
+// SystemError codes, as the system program reports them
+const ACCOUNT_ALREADY_IN_USE: u32 = 0;
+const RESULT_WITH_NEGATIVE_LAMPORTS: u32 = 1;
+
+/// The system instructions Anchor issues: create_account for `init`, and
+/// transfer, allocate and assign for `init` on an account that already holds
+/// lamports.
+fn system_program_processor(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    let pubkey_at = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).unwrap();
+    match u32::from_le_bytes(data[..4].try_into().unwrap()) {
+        // CreateAccount { lamports, space, owner }
+        0 => {
+            let (from, to) = (&accounts[0], &accounts[1]);
+            if to.lamports() > 0 {
+                return Err(ProgramError::Custom(ACCOUNT_ALREADY_IN_USE));
+            }
+            allocate(to, u64_at(12) as usize)?;
+            assign(to, &pubkey_at(20))?;
+            transfer(from, to, u64_at(4))
+        }
+        // Assign { owner }
+        1 => assign(&accounts[0], &pubkey_at(4)),
+        // Transfer { lamports }
+        2 => transfer(&accounts[0], &accounts[1], u64_at(4)),
+        // Allocate { space }
+        8 => allocate(&accounts[0], u64_at(4) as usize),
+        tag => panic!("system instruction {tag} isn't emulated"),
+    }
+}
+
+fn transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if !from.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !from.data_is_empty() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if from.lamports() < lamports {
+        return Err(ProgramError::Custom(RESULT_WITH_NEGATIVE_LAMPORTS));
+    }
+    **from.try_borrow_mut_lamports()? -= lamports;
+    **to.try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
+
+fn allocate(account: &AccountInfo, space: usize) -> ProgramResult {
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !account.data_is_empty() || *account.owner != system_program::ID {
+        return Err(ProgramError::Custom(ACCOUNT_ALREADY_IN_USE));
+    }
+    account.resize(space)
+}
+
+fn assign(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account.owner == owner {
+        return Ok(());
+    }
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    account.assign(owner);
+    Ok(())
+}
+
+impl From<&AccountInfo<'_>> for AccountState {
+    fn from(info: &AccountInfo) -> Self {
+        AccountState {
+            lamports: info.lamports(),
+            data: info.data.borrow().to_vec(),
+            owner: *info.owner,
+            executable: info.executable,
+        }
+    }
+}
+
+/// Account serialized the way the loader lays it out, so that
+/// `AccountInfo::assign`, `resize` and `original_data_len` work natively.
+struct AccountBuffer {
+    words: Vec<u64>,
+    lamports: Box<u64>,
+}
+
+// Byte offsets inside AccountBuffer::words
+const ORIGINAL_LEN_OFFSET: usize = 4;
+const KEY_OFFSET: usize = 8;
+const OWNER_OFFSET: usize = 40;
+const DATA_LEN_OFFSET: usize = 72;
+const DATA_OFFSET: usize = 80;
+
+impl AccountBuffer {
+    fn new(key: &Pubkey, account: &AccountState) -> Self {
+        let len = account.data.len();
+        let bytes = DATA_OFFSET + len + MAX_PERMITTED_DATA_INCREASE;
+        let mut buffer = AccountBuffer {
+            words: vec![0; bytes.div_ceil(8)],
+            lamports: Box::new(account.lamports),
+        };
+        let raw = buffer.bytes();
+        raw[ORIGINAL_LEN_OFFSET..KEY_OFFSET].copy_from_slice(&(len as u32).to_le_bytes());
+        raw[KEY_OFFSET..OWNER_OFFSET].copy_from_slice(key.as_ref());
+        raw[OWNER_OFFSET..DATA_LEN_OFFSET].copy_from_slice(account.owner.as_ref());
+        raw[DATA_LEN_OFFSET..DATA_OFFSET].copy_from_slice(&(len as u64).to_le_bytes());
+        raw[DATA_OFFSET..DATA_OFFSET + len].copy_from_slice(&account.data);
+        buffer
+    }
+
+    fn bytes(&mut self) -> &mut [u8] {
+        let len = self.words.len() * 8;
+        unsafe { slice::from_raw_parts_mut(self.words.as_mut_ptr() as *mut u8, len) }
+    }
+
+    /// # Safety
+    /// The returned info points into the buffer and must not outlive it.
+    unsafe fn account_info(
+        &mut self,
+        is_signer: bool,
+        is_writable: bool,
+        executable: bool,
+    ) -> AccountInfo<'static> {
+        let base = self.words.as_mut_ptr() as *mut u8;
+        let data_len = *(base.add(DATA_LEN_OFFSET) as *const u64) as usize;
+        AccountInfo::new(
+            &*(base.add(KEY_OFFSET) as *const Pubkey),
+            is_signer,
+            is_writable,
+            &mut *(&mut *self.lamports as *mut u64),
+            slice::from_raw_parts_mut(base.add(DATA_OFFSET), data_len),
+            &*(base.add(OWNER_OFFSET) as *const Pubkey),
+            executable,
+            0,
+        )
+    }
+}
+
+pub struct Svm {
+    accounts: HashMap<Pubkey, AccountState>,
+    pub rent: Rent,
+}
+
+impl Default for Svm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Svm {
+    pub fn new() -> Self {
+        static INSTALL_STUBS: Once = Once::new();
+        INSTALL_STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(Stubs));
+        });
+        CLOCK.with(|clock| {
+            *clock.borrow_mut() = Clock {
+                slot: 1,
+                unix_timestamp: START_TIME,
+                ..Clock::default()
+            }
+        });
+
+        let mut svm = Svm {
+            accounts: HashMap::new(),
+            rent: Rent::default(),
+        };
+        for (program, loader) in [
+            (deepwork::ID, BPF_LOADER_UPGRADEABLE),
+            (system_program::ID, NATIVE_LOADER),
+            (spl_token::ID, BPF_LOADER_UPGRADEABLE),
+            (spl_token_2022::ID, BPF_LOADER_UPGRADEABLE),
+        ] {
+            svm.set_account(
+                program,
+                AccountState {
+                    lamports: 1,
+                    data: vec![],
+                    owner: loader,
+                    executable: true,
+                },
+            );
+        }
+        svm.set_slot_hash([7; 32]);
+        svm.set_instructions_sysvar(&[], 0);
+        svm
+    }
+
+    pub fn now(&self) -> i64 {
+        CLOCK.with(|clock| clock.borrow().unix_timestamp)
+    }
+
+    /// Moves the clock forward, one slot per 400ms.
+    pub fn warp(&mut self, seconds: i64) {
+        CLOCK.with(|clock| {
+            let mut clock = clock.borrow_mut();
+            clock.unix_timestamp += seconds;
+            clock.slot += (seconds.max(0) as u64 * 5).div_ceil(2);
+        });
+    }
+
+    pub fn warp_minutes(&mut self, minutes: i64) {
+        self.warp(minutes * 60);
+    }
+
+    /// Replaces the `SlotHashes` sysvar with a single entry for the current slot.
+    pub fn set_slot_hash(&mut self, hash: [u8; 32]) {
+        let slot = CLOCK.with(|clock| clock.borrow().slot);
+        let mut data = 1u64.to_le_bytes().to_vec();
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.extend_from_slice(&hash);
+        self.set_account(
+            slot_hashes::ID,
+            AccountState {
+                lamports: self.rent.minimum_balance(data.len()),
+                data,
+                owner: SYSVAR,
+                executable: false,
+            },
+        );
+    }
+
+    /// Replaces the `SlotHashes` sysvar with one holding no entries.
+    pub fn clear_slot_hashes(&mut self) {
+        let data = 0u64.to_le_bytes().to_vec();
+        self.set_account(
+            slot_hashes::ID,
+            AccountState {
+                lamports: self.rent.minimum_balance(data.len()),
+                data,
+                owner: SYSVAR,
+                executable: false,
+            },
+        );
+    }
+
+    pub fn account(&self, key: &Pubkey) -> Option<&AccountState> {
+        self.accounts.get(key)
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: AccountState) {
+        self.accounts.insert(key, account);
+    }
+
+    pub fn exists(&self, key: &Pubkey) -> bool {
+        self.accounts.contains_key(key)
+    }
+
+    pub fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        self.accounts.entry(*key).or_default().lamports += lamports;
+    }
+
+    pub fn balance(&self, key: &Pubkey) -> u64 {
+        self.accounts.get(key).map_or(0, |account| account.lamports)
+    }
+
+    pub fn total_lamports(&self) -> u128 {
+        self.accounts.values().map(|a| a.lamports as u128).sum()
+    }
+
+    pub fn get<T: AccountDeserialize>(&self, key: &Pubkey) -> T {
+        self.try_get(key)
+            .unwrap_or_else(|| panic!("account {key} missing or undecodable"))
+    }
+
+    pub fn try_get<T: AccountDeserialize>(&self, key: &Pubkey) -> Option<T> {
+        let account = self.accounts.get(key)?;
+        T::try_deserialize(&mut account.data.as_slice()).ok()
+    }
+
+    /// Accounts of type `T` currently held by the program.
+    pub fn program_accounts<T: AccountDeserialize + Discriminator>(&self) -> Vec<(Pubkey, T)> {
+        self.accounts
+            .iter()
+            .filter(|(_, account)| {
+                account.owner == deepwork::ID && account.data.starts_with(T::DISCRIMINATOR)
+            })
+            .filter_map(|(key, account)| {
+                T::try_deserialize(&mut account.data.as_slice())
+                    .ok()
+                    .map(|decoded| (*key, decoded))
+            })
+            .collect()
+    }
+
+    pub fn create_mint(&mut self, mint: Pubkey, decimals: u8) {
+        let state = MintState {
+            decimals,
+            is_initialized: true,
+            ..MintState::default()
+        };
+        let mut data = vec![0; MintState::LEN];
+        MintState::pack(state, &mut data).unwrap();
+        self.set_account(
+            mint,
+            AccountState {
+                lamports: self.rent.minimum_balance(data.len()),
+                data,
+                owner: spl_token::ID,
+                executable: false,
+            },
+        );
+    }
+
+    /// Creates a Token-2022 mint with room for `extensions`, which `init` sets up.
+    pub fn create_mint_2022(
+        &mut self,
+        mint: Pubkey,
+        decimals: u8,
+        extensions: &[ExtensionType],
+        init: impl FnOnce(&mut StateWithExtensionsMut<MintState>),
+    ) {
+        let len = ExtensionType::try_calculate_account_len::<MintState>(extensions).unwrap();
+        let mut data = vec![0; len];
+        let mut state =
+            StateWithExtensionsMut::<MintState>::unpack_uninitialized(&mut data).unwrap();
+        init(&mut state);
+        state.base = MintState {
+            decimals,
+            is_initialized: true,
+            ..MintState::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        self.set_account(
+            mint,
+            AccountState {
+                lamports: self.rent.minimum_balance(len),
+                data,
+                owner: spl_token_2022::ID,
+                executable: false,
+            },
+        );
+    }
+
+    pub fn create_token_account(
+        &mut self,
+        key: Pubkey,
+        mint: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+    ) {
+        let state = TokenAccountState {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: TokenState::Initialized,
+            ..TokenAccountState::default()
+        };
+        let mut data = vec![0; TokenAccountState::LEN];
+        TokenAccountState::pack(state, &mut data).unwrap();
+        self.set_account(
+            key,
+            AccountState {
+                lamports: self.rent.minimum_balance(data.len()),
+                data,
+                owner: spl_token::ID,
+                executable: false,
+            },
+        );
+    }
+
+    pub fn token_balance(&self, key: &Pubkey) -> u64 {
+        let account = self.accounts.get(key).expect("token account");
+        TokenAccountState::unpack(&account.data).unwrap().amount
+    }
+
+    /// Runs `instructions` atomically as one transaction signed by `signers`.
+    pub fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[Pubkey],
+    ) -> std::result::Result<(), ProgramError> {
+        verify_precompiles(instructions)?;
+        let snapshot = self.accounts.clone();
+        for (index, instruction) in instructions.iter().enumerate() {
+            if instruction.program_id == ED25519_PROGRAM {
+                continue;
+            }
+            self.set_instructions_sysvar(instructions, index);
+            if let Err(err) = self.process(instruction, signers) {
+                self.accounts = snapshot;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes the transaction's instructions into the `Instructions` sysvar with
+    /// `current` as the executing one.
+    fn set_instructions_sysvar(&mut self, instructions: &[Instruction], current: usize) {
+        let borrowed: Vec<BorrowedInstruction> = instructions
+            .iter()
+            .map(|instruction| BorrowedInstruction {
+                program_id: &instruction.program_id,
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|meta| BorrowedAccountMeta {
+                        pubkey: &meta.pubkey,
+                        is_signer: meta.is_signer,
+                        is_writable: meta.is_writable,
+                    })
+                    .collect(),
+                data: &instruction.data,
+            })
+            .collect();
+        let mut data = construct_instructions_data(&borrowed);
+        let len = data.len();
+        data[len - 2..].copy_from_slice(&(current as u16).to_le_bytes());
+        // a fixed balance keeps the lamport total steady as the data changes size
+        self.set_account(
+            instructions_sysvar::ID,
+            AccountState {
+                lamports: 1,
+                data,
+                owner: SYSVAR,
+                executable: false,
+            },
+        );
+    }
+
+    pub fn send_one(
+        &mut self,
+        instruction: Instruction,
+        signers: &[Pubkey],
+    ) -> std::result::Result<(), ProgramError> {
+        self.send(&[instruction], signers)
+    }
+
+    fn process(
+        &mut self,
+        instruction: &Instruction,
+        signers: &[Pubkey],
+    ) -> std::result::Result<(), ProgramError> {
+        assert_eq!(
+            instruction.program_id,
+            deepwork::ID,
+            "only the deepwork program runs natively"
+        );
+
+        // one buffer per distinct account, with privileges merged across metas
+        let mut keys: Vec<Pubkey> = Vec::new();
+        let mut privileges: Vec<(bool, bool)> = Vec::new();
+        for meta in &instruction.accounts {
+            if meta.is_signer && !signers.contains(&meta.pubkey) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            match keys.iter().position(|key| *key == meta.pubkey) {
+                Some(index) => {
+                    privileges[index].0 |= meta.is_signer;
+                    privileges[index].1 |= meta.is_writable;
+                }
+                None => {
+                    keys.push(meta.pubkey);
+                    privileges.push((meta.is_signer, meta.is_writable));
+                }
+            }
+        }
+
+        let before: Vec<AccountState> = keys
+            .iter()
+            .map(|key| self.accounts.get(key).cloned().unwrap_or_default())
+            .collect();
+        let mut buffers: Vec<AccountBuffer> = keys
+            .iter()
+            .zip(&before)
+            .map(|(key, account)| AccountBuffer::new(key, account))
+            .collect();
+
+        CPI_WRITES.with(|writes| writes.borrow_mut().clear());
+        let (result, after) = {
+            let unique: Vec<AccountInfo<'static>> = buffers
+                .iter_mut()
+                .zip(&privileges)
+                .zip(&before)
+                .map(|((buffer, (is_signer, is_writable)), account)| unsafe {
+                    buffer.account_info(*is_signer, *is_writable, account.executable)
+                })
+                .collect();
+            // duplicated metas share one AccountInfo, as in the real runtime
+            let infos: Vec<AccountInfo<'static>> = instruction
+                .accounts
+                .iter()
+                .map(|meta| {
+                    let index = keys.iter().position(|key| *key == meta.pubkey).unwrap();
+                    unique[index].clone()
+                })
+                .collect();
+            // the infos only borrow `buffers`, which outlive them
+            let infos_ref: &'static [AccountInfo<'static>] =
+                unsafe { std::mem::transmute::<&[AccountInfo], _>(infos.as_slice()) };
+
+            let result = deepwork::entry(&instruction.program_id, infos_ref, &instruction.data);
+            let after: Vec<AccountState> = unique.iter().map(AccountState::from).collect();
+            (result, after)
+        };
+        drop(buffers);
+        result?;
+
+        self.verify(&keys, &privileges, &before, &after)?;
+        for (key, account) in keys.into_iter().zip(after) {
+            if account.lamports == 0 {
+                self.accounts.remove(&key);
+            } else {
+                self.accounts.insert(key, account);
+            }
+        }
+        Ok(())
+    }
+
+    fn verify(
+        &self,
+        keys: &[Pubkey],
+        privileges: &[(bool, bool)],
+        before: &[AccountState],
+        after: &[AccountState],
+    ) -> std::result::Result<(), ProgramError> {
+        let total_before: u128 = before.iter().map(|a| a.lamports as u128).sum();
+        let total_after: u128 = after.iter().map(|a| a.lamports as u128).sum();
+        assert_eq!(
+            total_before, total_after,
+            "instruction did not conserve lamports"
+        );
+
+        for (index, key) in keys.iter().enumerate() {
+            let (old, new) = (&before[index], &after[index]);
+            if old == new {
+                continue;
+            }
+            assert!(privileges[index].1, "read-only account {key} was modified");
+            // CPI callees' writes were checked against the callee when they ran
+            let by_cpi = CPI_WRITES.with(|writes| writes.borrow().contains(key));
+            if !by_cpi
+                && (new.lamports < old.lamports || new.data != old.data || new.owner != old.owner)
+            {
+                assert_eq!(
+                    old.owner,
+                    deepwork::ID,
+                    "account {key} debited or rewritten without being owned by the program"
+                );
+            }
+            if new.lamports > 0 && new.lamports < self.rent.minimum_balance(new.data.len()) {
+                return Err(ProgramError::AccountNotRentExempt);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs `initialize`: global state, epoch 0 and the three empty vaults.
+    pub fn initialize(&mut self, authority: &Pubkey) {
+        self.send_one(ix::initialize(authority), &[*authority])
+            .unwrap();
+    }
+
+    pub fn global_state(&self) -> GlobalState {
+        self.get(&pda::global_state())
+    }
+
+    pub fn focus_session(&self, user: &Pubkey, session_id: u64) -> FocusSession {
+        self.get(&pda::focus_session(user, session_id))
+    }
+
+    /// The id the user's next session will get.
+    pub fn next_session_id(&self, user: &Pubkey) -> u64 {
+        self.try_get::<UserState>(&pda::user_state(user))
+            .map_or(0, |user_state| user_state.next_session_id)
+    }
+
+    /// Runs `start_focus_session`, with the user paying stake and rent.
+    pub fn start_session(
+        &mut self,
+        user: &Pubkey,
+        stake_amount: u64,
+        duration_minutes: u64,
+        tasks: &[&str],
+        partner: Option<Pubkey>,
+    ) -> u64 {
+        self.start_sponsored_session(user, user, stake_amount, duration_minutes, tasks, partner)
+    }
+
+    /// Same as [`Svm::start_session`], with `payer` covering the rent instead of the user.
+    pub fn start_sponsored_session(
+        &mut self,
+        user: &Pubkey,
+        payer: &Pubkey,
+        stake_amount: u64,
+        duration_minutes: u64,
+        tasks: &[&str],
+        partner: Option<Pubkey>,
+    ) -> u64 {
+        let session_id = self.next_session_id(user);
+        let signers = if user == payer {
+            vec![*user]
+        } else {
+            vec![*user, *payer]
+        };
+        self.send_one(
+            ix::start_focus_session(
+                payer,
+                user,
+                session_id,
+                stake_amount,
+                duration_minutes,
+                tasks_from(tasks),
+                partner,
+                None,
+            ),
+            &signers,
+        )
+        .unwrap();
+        session_id
+    }
+
+    /// Same as [`Svm::start_session`], registering `session_key` for the session.
+    pub fn start_session_with_key(
+        &mut self,
+        user: &Pubkey,
+        stake_amount: u64,
+        duration_minutes: u64,
+        tasks: &[&str],
+        session_key: SessionKey,
+    ) -> u64 {
+        let session_id = self.next_session_id(user);
+        self.send_one(
+            ix::start_focus_session(
+                user,
+                user,
+                session_id,
+                stake_amount,
+                duration_minutes,
+                tasks_from(tasks),
+                None,
+                Some(session_key),
+            ),
+            &[*user],
+        )
+        .unwrap();
+        session_id
+    }
+
+    /// Runs `start_focus_session_token` for a mint set up by [`Svm::allow_mint`].
+    pub fn start_token_session(
+        &mut self,
+        user: &Pubkey,
+        user_token_account: &Pubkey,
+        mint: &Pubkey,
+        stake_amount: u64,
+        duration_minutes: u64,
+        tasks: &[&str],
+    ) -> u64 {
+        let session_id = self.next_session_id(user);
+        let token_program = self.account(mint).expect("mint").owner;
+        self.send_one(
+            ix::start_focus_session_token(
+                user,
+                user,
+                session_id,
+                mint,
+                user_token_account,
+                &token_program,
+                stake_amount,
+                duration_minutes,
+                tasks_from(tasks),
+                None,
+                None,
+            ),
+            &[*user],
+        )
+        .unwrap();
+        session_id
+    }
+
+    pub fn user_stats(&self, user: &Pubkey) -> UserStats {
+        self.get(&pda::user_stats(user))
+    }
+
+    /// Runs `close_epoch` for the current epoch, with `payer` funding the next one.
+    pub fn close_epoch(&mut self, payer: &Pubkey) {
+        let current_epoch = self.global_state().current_epoch;
+        self.send_one(ix::close_epoch(payer, current_epoch), &[*payer])
+            .unwrap();
+    }
+
+    /// Runs `allow_mint`: the mint config and its three empty pool accounts.
+    pub fn allow_mint(&mut self, authority: &Pubkey, mint: &Pubkey, min_stake: u64) {
+        let token_program = self.account(mint).expect("mint").owner;
+        self.send_one(
+            ix::allow_mint(authority, mint, &token_program, min_stake),
+            &[*authority],
+        )
+        .unwrap();
+    }
+
+    /// Asserts the SOL escrow invariants: each pool counter matches its vault's
+    /// balance above rent, and the vault holds exactly the open sessions' principal,
+    /// as tracked by the stake and pending balance counters.
+    pub fn assert_solvent(&self) {
+        let rent = self.rent.minimum_balance(0);
+        let global_state = self.global_state();
+        assert_eq!(
+            self.balance(&pda::focus_pool_vault()) - rent,
+            global_state.focus_pool,
+            "focus pool counter out of sync with its vault"
+        );
+        assert_eq!(
+            self.balance(&pda::failure_pool_vault()) - rent,
+            global_state.failure_pool,
+            "failure pool counter out of sync with its vault"
+        );
+        let sessions: Vec<FocusSession> = self
+            .program_accounts::<FocusSession>()
+            .into_iter()
+            .map(|(_, session)| session)
+            .filter(|session| session.mint.is_none())
+            .collect();
+        assert_eq!(
+            self.balance(&pda::vault()) - rent,
+            sessions
+                .iter()
+                .map(|session| session.principal().unwrap())
+                .sum::<u64>(),
+            "vault doesn't match outstanding principal"
+        );
+        assert_eq!(
+            global_state.total_active_stake,
+            sessions
+                .iter()
+                .map(|session| session.stake_amount)
+                .sum::<u64>(),
+            "total_active_stake out of sync with open sessions"
+        );
+        assert_eq!(
+            global_state.total_pending_balance,
+            sessions
+                .iter()
+                .map(|session| session.pending_balance)
+                .sum::<u64>(),
+            "total_pending_balance out of sync with open sessions"
+        );
+    }
+}
+
+/// Checks the transaction's Ed25519 instructions the way the runtime does
+/// before running it, failing with the `PrecompileError` as a custom error.
+#[allow(deprecated)]
+fn verify_precompiles(instructions: &[Instruction]) -> std::result::Result<(), ProgramError> {
+    let datas: Vec<&[u8]> = instructions
+        .iter()
+        .map(|instruction| instruction.data.as_slice())
+        .collect();
+    let feature_set = FeatureSet::all_enabled();
+    for instruction in instructions {
+        if instruction.program_id == ED25519_PROGRAM {
+            solana_ed25519_program::verify(&instruction.data, &datas, &feature_set)
+                .map_err(|err| ProgramError::Custom(err as u32))?;
+        }
+    }
+    Ok(())
+}
+
+/// Error returned for a transaction whose Ed25519 precompile check fails.
+pub fn precompile_error(err: PrecompileError) -> ProgramError {
+    ProgramError::Custom(err as u32)
+}
+
+/// Error returned for one of the program's `ErrorCode`s.
+pub fn program_error(code: deepwork::ErrorCode) -> ProgramError {
+    ProgramError::Custom(code.into())
+}
+
+/// Error returned for one of Anchor's own error codes.
+pub fn anchor_error(code: anchor_lang::error::ErrorCode) -> ProgramError {
+    ProgramError::Custom(code.into())
+}
+
+/// Tasks with the given descriptions, none completed yet.
+pub fn tasks_from(descriptions: &[&str]) -> Vec<Task> {
+    descriptions
+        .iter()
+        .map(|description| Task {
+            description: description.to_string(),
+            completed: false,
+        })
+        .collect()
+}
@@ -0,0 +1,492 @@
+//! Property test for the escrow vaults: random instruction sequences across
+//! many users must never create or lose lamports.
+//!
+//! After every step, successful or not, the harness checks that total
+//! lamports are unchanged, the vault holds exactly the outstanding
+//! `stake_amount + pending_balance` of open SOL sessions, and the
+//! `GlobalState` counters match the vault balances. Each user's `UserStats`
+//! must also account for everything they staked. The runtime in
+//! `common` additionally checks conservation and ownership per instruction.
+//!
+//! Every step is a real instruction, starts included, so the stake and rent
+//! transfers go through the system program CPIs. Starts draw out-of-range
+//! stakes, durations and task counts too, and some have a keeper pay the rent.
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use deepwork_client::{
+    instruction as ix, pda, CircuitBreaker, Config, FocusSession, RefundCurve, RewardEpoch,
+    UserStats,
+};
+use proptest::prelude::*;
+
+const USERS: usize = 6;
+const KEEPERS: usize = 2;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Start {
+        user: usize,
+        stake: u64,
+        duration_minutes: u64,
+        tasks: usize,
+        with_partner: bool,
+        sponsor: Option<usize>,
+    },
+    Complete {
+        user: usize,
+        session: u64,
+    },
+    CompleteV1 {
+        user: usize,
+        session: u64,
+    },
+    Fail {
+        user: usize,
+        session: u64,
+    },
+    Expire {
+        keeper: usize,
+        user: usize,
+        session: u64,
+    },
+    ExpireBatch {
+        keeper: usize,
+        sessions: Vec<(usize, u64)>,
+    },
+    Pause {
+        user: usize,
+        session: u64,
+    },
+    Resume {
+        user: usize,
+        session: u64,
+    },
+    UpdateTask {
+        user: usize,
+        session: u64,
+        index: u8,
+        completed: bool,
+        partner_signs: bool,
+    },
+    ReviewTask {
+        user: usize,
+        session: u64,
+        index: u8,
+        approved: bool,
+    },
+    Claim {
+        user: usize,
+        session: u64,
+    },
+    EmergencyWithdraw {
+        user: usize,
+        session: u64,
+    },
+    CloseEpoch {
+        payer: usize,
+    },
+    ClaimEpochRewards {
+        user: usize,
+        epoch: u64,
+    },
+    WithdrawFocusPool {
+        amount: u64,
+    },
+    WithdrawFailurePool {
+        amount: u64,
+    },
+    SetRefundCurve {
+        max_refund_bps: Option<u16>,
+    },
+    Reconcile,
+    Warp {
+        minutes: i64,
+    },
+}
+
+// Sessions are picked by index into the user's sessions so far, which
+// includes settled (closed) ones
+fn op() -> impl Strategy<Value = Op> {
+    let user = 0..USERS;
+    let session = 0..8u64;
+    prop_oneof![
+        4 => (
+            user.clone(),
+            0..50u64,
+            0..130u64,
+            0..5usize,
+            any::<bool>(),
+            prop::option::of(0..KEEPERS)
+        )
+            .prop_map(|(user, tenths, duration_minutes, tasks, with_partner, sponsor)| Op::Start {
+                user,
+                // below 0.1 SOL is under the default minimum stake
+                stake: tenths * LAMPORTS_PER_SOL / 10,
+                // the maximum duration is lowered to 120 minutes below
+                duration_minutes,
+                tasks,
+                with_partner,
+                sponsor,
+            }),
+        3 => (user.clone(), session.clone()).prop_map(|(user, session)| Op::Complete { user, session }),
+        1 => (user.clone(), session.clone()).prop_map(|(user, session)| Op::CompleteV1 { user, session }),
+        2 => (user.clone(), session.clone()).prop_map(|(user, session)| Op::Fail { user, session }),
+        2 => (0..KEEPERS, user.clone(), session.clone())
+            .prop_map(|(keeper, user, session)| Op::Expire { keeper, user, session }),
+        1 => (
+            0..KEEPERS,
+            prop::collection::vec((user.clone(), session.clone()), 1..5)
+        )
+            .prop_map(|(keeper, sessions)| Op::ExpireBatch { keeper, sessions }),
+        1 => (user.clone(), session.clone()).prop_map(|(user, session)| Op::Pause { user, session }),
+        1 => (user.clone(), session.clone()).prop_map(|(user, session)| Op::Resume { user, session }),
+        2 => (
+            user.clone(),
+            session.clone(),
+            0..4u8,
+            any::<bool>(),
+            any::<bool>()
+        )
+            .prop_map(|(user, session, index, completed, partner_signs)| Op::UpdateTask {
+                user,
+                session,
+                index,
+                completed,
+                partner_signs,
+            }),
+        1 => (user.clone(), session.clone(), 0..4u8, any::<bool>())
+            .prop_map(|(user, session, index, approved)| Op::ReviewTask {
+                user,
+                session,
+                index,
+                approved,
+            }),
+        3 => (user.clone(), session.clone()).prop_map(|(user, session)| Op::Claim { user, session }),
+        1 => (user.clone(), session.clone())
+            .prop_map(|(user, session)| Op::EmergencyWithdraw { user, session }),
+        1 => (0..USERS).prop_map(|payer| Op::CloseEpoch { payer }),
+        1 => (user.clone(), 0..4u64).prop_map(|(user, epoch)| Op::ClaimEpochRewards { user, epoch }),
+        1 => (0..LAMPORTS_PER_SOL).prop_map(|amount| Op::WithdrawFocusPool { amount }),
+        1 => (0..5 * LAMPORTS_PER_SOL).prop_map(|amount| Op::WithdrawFailurePool { amount }),
+        1 => prop::option::of(0..=10_000u16)
+            .prop_map(|max_refund_bps| Op::SetRefundCurve { max_refund_bps }),
+        1 => Just(Op::Reconcile),
+        4 => (0..90i64).prop_map(|minutes| Op::Warp { minutes }),
+    ]
+}
+
+struct World {
+    svm: Svm,
+    authority: Pubkey,
+    partner: Pubkey,
+    users: Vec<Pubkey>,
+    keepers: Vec<Pubkey>,
+    fees_paid: Vec<u64>, // Focus pool fees per user
+    total_lamports: u128,
+}
+
+impl World {
+    fn new() -> Self {
+        let mut svm = Svm::new();
+        let mut funded = || {
+            let key = Pubkey::new_unique();
+            svm.airdrop(&key, 1_000 * LAMPORTS_PER_SOL);
+            key
+        };
+        let authority = funded();
+        let partner = funded();
+        let users = (0..USERS).map(|_| funded()).collect();
+        let keepers = (0..KEEPERS).map(|_| funded()).collect();
+        svm.initialize(&authority);
+        let config = Config {
+            max_duration_minutes: 120,
+            ..svm.global_state().config
+        };
+        svm.send_one(ix::update_config(&authority, config), &[authority])
+            .unwrap();
+        let total_lamports = svm.total_lamports();
+        World {
+            svm,
+            authority,
+            partner,
+            users,
+            keepers,
+            fees_paid: vec![0; USERS],
+            total_lamports,
+        }
+    }
+
+    fn session_id(&self, user: usize, pick: u64) -> u64 {
+        let opened = self.svm.next_session_id(&self.users[user]);
+        pick % opened.max(1)
+    }
+
+    /// Who gets the session's rent back; the user once the session is gone.
+    fn rent_payer(&self, user: usize, session_id: u64) -> Pubkey {
+        let user = self.users[user];
+        self.svm
+            .try_get::<FocusSession>(&pda::focus_session(&user, session_id))
+            .map_or(user, |session| session.rent_payer)
+    }
+
+    /// Applies `op`; rejected instructions leave the state untouched.
+    fn apply(&mut self, op: &Op) {
+        let authority = self.authority;
+        let partner = self.partner;
+        match *op {
+            Op::Start {
+                user,
+                stake,
+                duration_minutes,
+                tasks,
+                with_partner,
+                sponsor,
+            } => {
+                let names = ["a", "b", "c", "d"];
+                let key = self.users[user];
+                let payer = sponsor.map_or(key, |keeper| self.keepers[keeper]);
+                let session_id = self.svm.next_session_id(&key);
+                let started = self.svm.send_one(
+                    ix::start_focus_session(
+                        &payer,
+                        &key,
+                        session_id,
+                        stake,
+                        duration_minutes,
+                        tasks_from(&names[..tasks]),
+                        with_partner.then_some(partner),
+                        None,
+                    ),
+                    &[key, payer],
+                );
+                if started.is_ok() {
+                    let config = self.svm.global_state().config;
+                    self.fees_paid[user] += config.focus_pool_fee(stake).unwrap();
+                }
+            }
+            Op::Complete { user, session } => {
+                let session_id = self.session_id(user, session);
+                let user = self.users[user];
+                let current_epoch = self.svm.global_state().current_epoch;
+                let _ = self.svm.send_one(
+                    ix::complete_focus_session(&user, &user, session_id, current_epoch),
+                    &[user],
+                );
+            }
+            Op::CompleteV1 { user, session } => {
+                let session_id = self.session_id(user, session);
+                let rent_payer = self.rent_payer(user, session_id);
+                let user = self.users[user];
+                let _ = self.svm.send_one(
+                    ix::complete_focus_session_v1(&user, &rent_payer, session_id),
+                    &[user],
+                );
+            }
+            Op::Fail { user, session } => {
+                let session_id = self.session_id(user, session);
+                let rent_payer = self.rent_payer(user, session_id);
+                let user = self.users[user];
+                let _ = self.svm.send_one(
+                    ix::fail_focus_session(&user, &rent_payer, session_id),
+                    &[user],
+                );
+            }
+            Op::Expire {
+                keeper,
+                user,
+                session,
+            } => {
+                let session_id = self.session_id(user, session);
+                let rent_payer = self.rent_payer(user, session_id);
+                let keeper = self.keepers[keeper];
+                let _ = self.svm.send_one(
+                    ix::expire_focus_session(&keeper, &self.users[user], &rent_payer, session_id),
+                    &[keeper],
+                );
+            }
+            Op::ExpireBatch {
+                keeper,
+                ref sessions,
+            } => {
+                let mut batch: Vec<(Pubkey, u64, Pubkey)> = sessions
+                    .iter()
+                    .map(|&(user, session)| {
+                        let session_id = self.session_id(user, session);
+                        let rent_payer = self.rent_payer(user, session_id);
+                        (self.users[user], session_id, rent_payer)
+                    })
+                    .collect();
+                // a session listed twice would be writable twice in one transaction
+                batch.sort();
+                batch.dedup();
+                let keeper = self.keepers[keeper];
+                let _ = self
+                    .svm
+                    .send_one(ix::expire_focus_sessions_batch(&keeper, &batch), &[keeper]);
+            }
+            Op::Pause { user, session } => {
+                let session_id = self.session_id(user, session);
+                let user = self.users[user];
+                let _ = self
+                    .svm
+                    .send_one(ix::pause_focus_session(&user, session_id), &[user]);
+            }
+            Op::Resume { user, session } => {
+                let session_id = self.session_id(user, session);
+                let user = self.users[user];
+                let _ = self
+                    .svm
+                    .send_one(ix::resume_focus_session(&user, session_id), &[user]);
+            }
+            Op::UpdateTask {
+                user,
+                session,
+                index,
+                completed,
+                partner_signs,
+            } => {
+                let session_id = self.session_id(user, session);
+                let user = self.users[user];
+                let (co_signer, signers) = if partner_signs {
+                    (Some(partner), vec![user, partner])
+                } else {
+                    (None, vec![user])
+                };
+                let _ = self.svm.send_one(
+                    ix::update_task(&user, &user, session_id, co_signer, index, completed),
+                    &signers,
+                );
+            }
+            Op::ReviewTask {
+                user,
+                session,
+                index,
+                approved,
+            } => {
+                let session_id = self.session_id(user, session);
+                let _ = self.svm.send_one(
+                    ix::review_task(&partner, &self.users[user], session_id, index, approved),
+                    &[partner],
+                );
+            }
+            Op::Claim { user, session } => {
+                let session_id = self.session_id(user, session);
+                let rent_payer = self.rent_payer(user, session_id);
+                let user = self.users[user];
+                let _ = self
+                    .svm
+                    .send_one(ix::claim_rewards(&user, &rent_payer, session_id), &[user]);
+            }
+            Op::EmergencyWithdraw { user, session } => {
+                // only allowed while new sessions are paused, so lift the
+                // breaker again in the same transaction
+                let session_id = self.session_id(user, session);
+                let rent_payer = self.rent_payer(user, session_id);
+                let user = self.users[user];
+                let paused = CircuitBreaker {
+                    new_sessions_paused: true,
+                    ..CircuitBreaker::default()
+                };
+                let _ = self.svm.send(
+                    &[
+                        ix::set_circuit_breaker(&authority, paused),
+                        ix::emergency_withdraw(&user, &rent_payer, session_id),
+                        ix::set_circuit_breaker(&authority, CircuitBreaker::default()),
+                    ],
+                    &[authority, user],
+                );
+            }
+            Op::CloseEpoch { payer } => {
+                // close_epoch only succeeds once the epoch has run its course
+                let global_state = self.svm.global_state();
+                let epoch: RewardEpoch = self.svm.get(&pda::epoch(global_state.current_epoch));
+                let epoch_end = epoch.start_time + global_state.config.epoch_duration_seconds;
+                self.svm.warp((epoch_end - self.svm.now()).max(0));
+                self.svm.close_epoch(&self.users[payer]);
+            }
+            Op::ClaimEpochRewards { user, epoch } => {
+                let user = self.users[user];
+                let _ = self
+                    .svm
+                    .send_one(ix::claim_epoch_rewards(&user, epoch), &[user]);
+            }
+            Op::WithdrawFocusPool { amount } => {
+                let _ = self.svm.send_one(
+                    ix::withdraw_focus_pool(&authority, &authority, amount),
+                    &[authority],
+                );
+            }
+            Op::WithdrawFailurePool { amount } => {
+                let _ = self.svm.send_one(
+                    ix::withdraw_failure_pool(&authority, &authority, amount),
+                    &[authority],
+                );
+            }
+            Op::SetRefundCurve { max_refund_bps } => {
+                let curve = match max_refund_bps {
+                    Some(max_refund_bps) => RefundCurve::Linear { max_refund_bps },
+                    None => RefundCurve::None,
+                };
+                self.svm
+                    .send_one(ix::set_refund_curve(&authority, curve), &[authority])
+                    .unwrap();
+            }
+            Op::Reconcile => {
+                // the vaults are never short, so reconcile must not halt withdrawals
+                self.svm.send_one(ix::reconcile(), &[partner]).unwrap();
+                assert!(!self.svm.global_state().circuit_breaker.withdrawals_paused);
+            }
+            Op::Warp { minutes } => self.svm.warp_minutes(minutes),
+        }
+    }
+
+    fn check_invariants(&self) {
+        assert_eq!(
+            self.svm.total_lamports(),
+            self.total_lamports,
+            "lamports created or destroyed"
+        );
+        self.svm.assert_solvent();
+        let global_state = self.svm.global_state();
+        assert!(
+            global_state.failure_pool_reserved <= global_state.failure_pool,
+            "reserved epoch rewards exceed the failure pool"
+        );
+
+        // every staked lamport went to the focus pool, back to the user,
+        // to the failure pool or keeper, or is still held for a session
+        let sessions = self.svm.program_accounts::<FocusSession>();
+        for (user, fees_paid) in self.users.iter().zip(&self.fees_paid) {
+            let Some(stats) = self.svm.try_get::<UserStats>(&pda::user_stats(user)) else {
+                continue;
+            };
+            let principal: u64 = sessions
+                .iter()
+                .filter(|(_, session)| session.user == *user)
+                .map(|(_, session)| session.principal().unwrap())
+                .sum();
+            assert_eq!(
+                stats.total_staked,
+                fees_paid + stats.total_refunded + stats.total_forfeited + principal,
+                "user stats don't account for the stake"
+            );
+            assert!(stats.best_streak >= stats.current_streak);
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn vaults_stay_solvent(ops in prop::collection::vec(op(), 1..80)) {
+        let mut world = World::new();
+        for op in &ops {
+            world.apply(op);
+            world.check_invariants();
+        }
+    }
+}
@@ -0,0 +1,2973 @@
+//! Scenario tests for the deepwork program, run natively with a warpable clock.
+//!
+//! Every instruction runs for real, CPIs included; see `common` for how.
+//! NoPendingBalance can't be hit on-chain, since completing a session always
+//! leaves a non-zero pending balance; `preview.rs` covers it through the
+//! client's claim preview.
+
+mod common;
+
+use anchor_lang::error::ErrorCode as AnchorErrorCode;
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::{AnchorSerialize, Discriminator, Space};
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    default_account_state::DefaultAccountState, mint_close_authority::MintCloseAuthority,
+    non_transferable::NonTransferable, pausable::PausableConfig,
+    permanent_delegate::PermanentDelegate, transfer_fee::TransferFeeConfig,
+    transfer_hook::TransferHook, BaseStateWithExtensionsMut, ExtensionType,
+};
+use anchor_spl::token_2022::spl_token_2022::state::AccountState as TokenAccountState;
+use common::*;
+use deepwork::{ErrorCode, GlobalStateV0, UserStateV0};
+use deepwork_client::{
+    instruction as ix, pda, Challenge, CircuitBreaker, Config, EpochShare, FocusReport,
+    GlobalState, RefundCurve, RefundStep, RewardEpoch, SessionKey, StreakTier, UserState,
+};
+use solana_sdk::signature::{Keypair, Signer};
+
+const STAKE: u64 = LAMPORTS_PER_SOL;
+// Stake left in the vault after the default 1% focus pool fee
+const LOCKED: u64 = STAKE - STAKE / 100;
+
+fn setup() -> (Svm, Pubkey) {
+    let mut svm = Svm::new();
+    let authority = Pubkey::new_unique();
+    svm.airdrop(&authority, 10 * LAMPORTS_PER_SOL);
+    svm.initialize(&authority);
+    (svm, authority)
+}
+
+fn funded_user(svm: &mut Svm) -> Pubkey {
+    let user = Pubkey::new_unique();
+    svm.airdrop(&user, 100 * LAMPORTS_PER_SOL);
+    user
+}
+
+fn expect_err(result: Result<(), ProgramError>, expected: ProgramError) {
+    assert_eq!(result, Err(expected));
+}
+
+/// Warps past the session's duration and completes it.
+fn complete(svm: &mut Svm, user: &Pubkey, session_id: u64) {
+    let session = svm.focus_session(user, session_id);
+    let end = session.start_time + session.duration_minutes as i64 * 60;
+    svm.warp((end - svm.now()).max(0));
+    let current_epoch = svm.global_state().current_epoch;
+    svm.send_one(
+        ix::complete_focus_session(user, user, session_id, current_epoch),
+        &[*user],
+    )
+    .unwrap();
+}
+
+/// Warps to the end of the current reward epoch and closes it.
+fn end_epoch(svm: &mut Svm, payer: &Pubkey) {
+    let global_state = svm.global_state();
+    let epoch: RewardEpoch = svm.get(&pda::epoch(global_state.current_epoch));
+    let epoch_end = epoch.start_time + global_state.config.epoch_duration_seconds;
+    svm.warp((epoch_end - svm.now()).max(0));
+    svm.close_epoch(payer);
+}
+
+fn set_circuit_breaker(svm: &mut Svm, authority: &Pubkey, circuit_breaker: CircuitBreaker) {
+    svm.send_one(
+        ix::set_circuit_breaker(authority, circuit_breaker),
+        &[*authority],
+    )
+    .unwrap();
+}
+
+#[test]
+fn complete_waits_for_duration_minus_grace() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 60, &["write"], None);
+
+    // default grace is 5 minutes
+    svm.warp_minutes(54);
+    expect_err(
+        svm.send_one(
+            ix::complete_focus_session(&user, &user, session_id, 0),
+            &[user],
+        ),
+        program_error(ErrorCode::SessionNotComplete),
+    );
+
+    svm.warp_minutes(1);
+    svm.send_one(
+        ix::complete_focus_session(&user, &user, session_id, 0),
+        &[user],
+    )
+    .unwrap();
+
+    let session = svm.focus_session(&user, session_id);
+    assert!(!session.is_active);
+    assert_eq!(session.stake_amount, 0);
+    assert_eq!(session.pending_balance, LOCKED);
+
+    // stake x minutes shares in the current reward epoch
+    let epoch: RewardEpoch = svm.get(&pda::epoch(0));
+    let share: EpochShare = svm.get(&pda::epoch_share(0, &user));
+    assert_eq!(epoch.total_shares, LOCKED as u128 * 60);
+    assert_eq!(share.shares, epoch.total_shares);
+
+    expect_err(
+        svm.send_one(
+            ix::complete_focus_session(&user, &user, session_id, 0),
+            &[user],
+        ),
+        program_error(ErrorCode::NoActiveSession),
+    );
+    svm.assert_solvent();
+}
+
+#[test]
+fn start_validates_stake_duration_and_tasks() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let config = Config::default();
+    let too_many: Vec<String> = (0..=config.max_tasks).map(|i| i.to_string()).collect();
+    let too_many: Vec<&str> = too_many.iter().map(String::as_str).collect();
+    let start = |stake_amount, duration_minutes, tasks: &[&str]| {
+        ix::start_focus_session(
+            &user,
+            &user,
+            0,
+            stake_amount,
+            duration_minutes,
+            tasks_from(tasks),
+            None,
+            None,
+        )
+    };
+
+    for (instruction, error) in [
+        (
+            start(config.min_stake - 1, 30, &["a"]),
+            ErrorCode::StakeTooLow,
+        ),
+        (start(STAKE, 0, &["a"]), ErrorCode::InvalidDuration),
+        (
+            start(STAKE, config.max_duration_minutes + 1, &["a"]),
+            ErrorCode::InvalidDuration,
+        ),
+        (start(STAKE, 30, &[]), ErrorCode::NoTasksProvided),
+        (start(STAKE, 30, &too_many), ErrorCode::TooManyTasks),
+    ] {
+        expect_err(svm.send_one(instruction, &[user]), program_error(error));
+    }
+    assert!(!svm.exists(&pda::focus_session(&user, 0)));
+
+    set_circuit_breaker(
+        &mut svm,
+        &authority,
+        CircuitBreaker {
+            new_sessions_paused: true,
+            ..CircuitBreaker::default()
+        },
+    );
+    expect_err(
+        svm.send_one(start(STAKE, 30, &["a"]), &[user]),
+        program_error(ErrorCode::NewSessionsPaused),
+    );
+    set_circuit_breaker(&mut svm, &authority, CircuitBreaker::default());
+
+    // the boundaries themselves are accepted
+    let max_tasks = &too_many[..config.max_tasks as usize];
+    svm.send_one(
+        start(config.min_stake, config.max_duration_minutes, max_tasks),
+        &[user],
+    )
+    .unwrap();
+    assert_eq!(svm.focus_session(&user, 0).tasks.len(), max_tasks.len());
+}
+
+#[test]
+fn claim_refunds_share_of_completed_tasks() {
+    for completed in 0..=4u8 {
+        let (mut svm, _) = setup();
+        let user = funded_user(&mut svm);
+        let session_id = svm.start_session(&user, STAKE, 30, &["a", "b", "c", "d"], None);
+        complete(&mut svm, &user, session_id);
+
+        for index in 0..completed {
+            svm.send_one(
+                ix::update_task(&user, &user, session_id, None, index, true),
+                &[user],
+            )
+            .unwrap();
+        }
+        // un-completing is always allowed and lowers the refund
+        if completed < 4 {
+            svm.send(
+                &[
+                    ix::update_task(&user, &user, session_id, None, 3, true),
+                    ix::update_task(&user, &user, session_id, None, 3, false),
+                ],
+                &[user],
+            )
+            .unwrap();
+        }
+
+        let session_key = pda::focus_session(&user, session_id);
+        let session_rent = svm.balance(&session_key);
+        let user_before = svm.balance(&user);
+        let failure_pool_before = svm.global_state().failure_pool;
+        svm.send_one(ix::claim_rewards(&user, &user, session_id), &[user])
+            .unwrap();
+
+        let refund = LOCKED * (completed as u64 * 100 / 4) / 100;
+        let penalty = LOCKED - refund;
+        assert_eq!(svm.balance(&user), user_before + refund + session_rent);
+        assert_eq!(
+            svm.global_state().failure_pool,
+            failure_pool_before + penalty
+        );
+        assert!(!svm.exists(&session_key));
+        svm.assert_solvent();
+    }
+}
+
+#[test]
+fn tasks_only_change_on_settled_sessions() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let partner = Pubkey::new_unique();
+    let session_id = svm.start_session(&user, STAKE, 30, &["a", "b"], Some(partner));
+
+    expect_err(
+        svm.send_one(
+            ix::update_task(&user, &user, session_id, None, 0, true),
+            &[user],
+        ),
+        program_error(ErrorCode::SessionStillActive),
+    );
+    expect_err(
+        svm.send_one(
+            ix::review_task(&partner, &user, session_id, 0, true),
+            &[partner],
+        ),
+        program_error(ErrorCode::SessionStillActive),
+    );
+    expect_err(
+        svm.send_one(ix::claim_rewards(&user, &user, session_id), &[user]),
+        program_error(ErrorCode::SessionStillActive),
+    );
+
+    complete(&mut svm, &user, session_id);
+    expect_err(
+        svm.send_one(
+            ix::update_task(&user, &user, session_id, None, 2, true),
+            &[user],
+        ),
+        program_error(ErrorCode::InvalidTaskIndex),
+    );
+    expect_err(
+        svm.send_one(
+            ix::review_task(&partner, &user, session_id, 2, true),
+            &[partner],
+        ),
+        program_error(ErrorCode::InvalidTaskIndex),
+    );
+}
+
+#[test]
+fn partner_signs_off_task_completion() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let partner = Pubkey::new_unique();
+    let stranger = Pubkey::new_unique();
+    let session_id = svm.start_session(&user, STAKE, 30, &["a", "b", "c"], Some(partner));
+    complete(&mut svm, &user, session_id);
+
+    // the owner alone can only request approval
+    svm.send_one(
+        ix::update_task(&user, &user, session_id, None, 0, true),
+        &[user],
+    )
+    .unwrap();
+    let session = svm.focus_session(&user, session_id);
+    assert!(!session.tasks[0].completed);
+    assert_eq!(session.pending_approvals, 0b001);
+
+    svm.send_one(
+        ix::review_task(&partner, &user, session_id, 0, true),
+        &[partner],
+    )
+    .unwrap();
+    let session = svm.focus_session(&user, session_id);
+    assert!(session.tasks[0].completed);
+    assert_eq!(session.pending_approvals, 0);
+
+    // co-signed by the partner, completion is recorded directly
+    svm.send_one(
+        ix::update_task(&user, &user, session_id, Some(partner), 1, true),
+        &[user, partner],
+    )
+    .unwrap();
+    assert!(svm.focus_session(&user, session_id).tasks[1].completed);
+
+    expect_err(
+        svm.send_one(
+            ix::update_task(&user, &user, session_id, Some(stranger), 2, true),
+            &[user, stranger],
+        ),
+        program_error(ErrorCode::InvalidPartner),
+    );
+    expect_err(
+        svm.send_one(
+            ix::review_task(&stranger, &user, session_id, 2, true),
+            &[stranger],
+        ),
+        program_error(ErrorCode::InvalidPartner),
+    );
+
+    // a rejected review clears the request without completing the task
+    svm.send_one(
+        ix::update_task(&user, &user, session_id, None, 2, true),
+        &[user],
+    )
+    .unwrap();
+    svm.send_one(
+        ix::review_task(&partner, &user, session_id, 2, false),
+        &[partner],
+    )
+    .unwrap();
+    let session = svm.focus_session(&user, session_id);
+    assert!(!session.tasks[2].completed);
+    assert_eq!(session.pending_approvals, 0);
+}
+
+#[test]
+fn session_key_checks_in_but_never_settles() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let delegate = funded_user(&mut svm);
+    let expires_at = svm.now() + 2 * 3_600;
+    let session_id = svm.start_session_with_key(
+        &user,
+        STAKE,
+        60,
+        &["a", "b"],
+        SessionKey {
+            key: delegate,
+            expires_at,
+        },
+    );
+    svm.warp_minutes(60);
+
+    let stranger = funded_user(&mut svm);
+    expect_err(
+        svm.send_one(
+            ix::complete_focus_session(&stranger, &user, session_id, 0),
+            &[stranger],
+        ),
+        program_error(ErrorCode::UnauthorizedSigner),
+    );
+    svm.send_one(
+        ix::complete_focus_session(&delegate, &user, session_id, 0),
+        &[delegate],
+    )
+    .unwrap();
+    svm.send_one(
+        ix::update_task(&delegate, &user, session_id, None, 0, true),
+        &[delegate],
+    )
+    .unwrap();
+    assert!(svm.focus_session(&user, session_id).tasks[0].completed);
+
+    // claiming still needs the owner's signature
+    expect_err(
+        svm.send_one(ix::claim_rewards(&user, &user, session_id), &[delegate]),
+        ProgramError::MissingRequiredSignature,
+    );
+
+    svm.warp(expires_at - svm.now());
+    expect_err(
+        svm.send_one(
+            ix::update_task(&delegate, &user, session_id, None, 1, true),
+            &[delegate],
+        ),
+        program_error(ErrorCode::SessionKeyExpired),
+    );
+    svm.send_one(
+        ix::update_task(&user, &user, session_id, None, 1, true),
+        &[user],
+    )
+    .unwrap();
+
+    // a key registered on one session can't act on another
+    let other = svm.start_session(&user, STAKE, 60, &["a"], None);
+    svm.warp_minutes(60);
+    expect_err(
+        svm.send_one(
+            ix::complete_focus_session(&delegate, &user, other, 0),
+            &[delegate],
+        ),
+        program_error(ErrorCode::UnauthorizedSigner),
+    );
+    let failing = svm.start_session_with_key(
+        &user,
+        STAKE,
+        60,
+        &["a"],
+        SessionKey {
+            key: delegate,
+            expires_at: svm.now() + 3_600,
+        },
+    );
+    expect_err(
+        svm.send_one(ix::fail_focus_session(&user, &user, failing), &[delegate]),
+        ProgramError::MissingRequiredSignature,
+    );
+    // and naming itself as the user points at a session that doesn't exist
+    for instruction in [
+        ix::fail_focus_session(&delegate, &delegate, failing),
+        ix::claim_rewards(&delegate, &delegate, session_id),
+    ] {
+        expect_err(
+            svm.send_one(instruction, &[delegate]),
+            anchor_error(AnchorErrorCode::AccountNotInitialized),
+        );
+    }
+    assert!(svm.focus_session(&user, failing).is_active);
+    svm.assert_solvent();
+}
+
+#[test]
+fn session_keys_are_validated_at_start() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let delegate = funded_user(&mut svm);
+    let now = svm.now();
+    let invalid = [
+        // the owner's own wallet
+        SessionKey {
+            key: user,
+            expires_at: now + 3_600,
+        },
+        // already expired
+        SessionKey {
+            key: delegate,
+            expires_at: now,
+        },
+        // valid for more than a week
+        SessionKey {
+            key: delegate,
+            expires_at: now + 7 * 86_400 + 1,
+        },
+    ];
+    for session_key in invalid {
+        expect_err(
+            svm.send_one(
+                ix::start_focus_session(
+                    &user,
+                    &user,
+                    0,
+                    STAKE,
+                    30,
+                    tasks_from(&["a"]),
+                    None,
+                    Some(session_key),
+                ),
+                &[user],
+            ),
+            program_error(ErrorCode::InvalidSessionKey),
+        );
+    }
+
+    let session_id = svm.start_session_with_key(
+        &user,
+        STAKE,
+        30,
+        &["a"],
+        SessionKey {
+            key: delegate,
+            expires_at: now + 7 * 86_400,
+        },
+    );
+    // a key the session wasn't registered with is turned away
+    let wrong_key = funded_user(&mut svm);
+    svm.warp_minutes(30);
+    expect_err(
+        svm.send_one(
+            ix::complete_focus_session(&wrong_key, &user, session_id, 0),
+            &[wrong_key],
+        ),
+        program_error(ErrorCode::UnauthorizedSigner),
+    );
+
+    // completing with the key charges it the epoch share's rent
+    let delegate_before = svm.balance(&delegate);
+    let user_before = svm.balance(&user);
+    svm.send_one(
+        ix::complete_focus_session(&delegate, &user, session_id, 0),
+        &[delegate],
+    )
+    .unwrap();
+    let rent = svm.balance(&pda::epoch_share(0, &user));
+    assert!(rent > 0);
+    assert_eq!(svm.balance(&delegate), delegate_before - rent);
+    assert_eq!(svm.balance(&user), user_before);
+    svm.assert_solvent();
+}
+
+/// Turns on one-minute heartbeats with the default coverage and gap limits.
+fn require_heartbeats(svm: &mut Svm, authority: &Pubkey) {
+    let config = Config {
+        heartbeat_interval_seconds: 60,
+        ..Config::default()
+    };
+    svm.send_one(ix::update_config(authority, config), &[*authority])
+        .unwrap();
+}
+
+/// Sends a heartbeat every `every` minutes for `minutes` minutes.
+fn heartbeat_for(svm: &mut Svm, user: &Pubkey, session_id: u64, every: i64, minutes: i64) {
+    for _ in 0..minutes / every {
+        svm.warp_minutes(every);
+        svm.send_one(ix::heartbeat(user, user, session_id), &[*user])
+            .unwrap();
+    }
+}
+
+#[test]
+fn completion_needs_heartbeat_coverage() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let unchecked = svm.start_session(&user, STAKE, 30, &["a"], None);
+    expect_err(
+        svm.send_one(ix::heartbeat(&user, &user, unchecked), &[user]),
+        program_error(ErrorCode::HeartbeatsDisabled),
+    );
+
+    // the policy is fixed at session start
+    require_heartbeats(&mut svm, &authority);
+    complete(&mut svm, &user, unchecked);
+
+    let covered = svm.start_session(&user, STAKE, 30, &["a"], None);
+    let stranger = funded_user(&mut svm);
+    expect_err(
+        svm.send_one(ix::heartbeat(&stranger, &user, covered), &[stranger]),
+        program_error(ErrorCode::UnauthorizedSigner),
+    );
+    heartbeat_for(&mut svm, &user, covered, 1, 30);
+    complete(&mut svm, &user, covered);
+    let session = svm.focus_session(&user, covered);
+    assert_eq!(session.last_heartbeat_slot, svm.now() as u64 / 60);
+
+    // each session counts its own heartbeats: one in five intervals is under the
+    // default 80%, however often a concurrent session checks in
+    let busy = svm.start_session(&user, STAKE, 30, &["a"], None);
+    let sparse = svm.start_session(&user, STAKE, 30, &["a"], None);
+    for minute in 1..=30 {
+        svm.warp_minutes(1);
+        svm.send_one(ix::heartbeat(&user, &user, busy), &[user])
+            .unwrap();
+        if minute % 5 == 0 {
+            svm.send_one(ix::heartbeat(&user, &user, sparse), &[user])
+                .unwrap();
+        }
+    }
+    complete(&mut svm, &user, busy);
+    expect_err(
+        svm.send_one(ix::complete_focus_session(&user, &user, sparse, 0), &[user]),
+        program_error(ErrorCode::InsufficientHeartbeats),
+    );
+    expect_err(
+        svm.send_one(ix::complete_focus_session_v1(&user, &user, sparse), &[user]),
+        program_error(ErrorCode::InsufficientHeartbeats),
+    );
+    svm.send_one(ix::fail_focus_session(&user, &user, sparse), &[user])
+        .unwrap();
+    svm.assert_solvent();
+}
+
+#[test]
+fn missed_heartbeats_make_a_session_expirable_early() {
+    let (mut svm, authority) = setup();
+    require_heartbeats(&mut svm, &authority);
+    let user = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+    let delegate = funded_user(&mut svm);
+    let session_id = svm.start_session_with_key(
+        &user,
+        STAKE,
+        60,
+        &["a"],
+        SessionKey {
+            key: delegate,
+            expires_at: svm.now() + 3_600,
+        },
+    );
+    heartbeat_for(&mut svm, &user, session_id, 1, 10);
+    svm.warp_minutes(1);
+    svm.send_one(ix::heartbeat(&delegate, &user, session_id), &[delegate])
+        .unwrap();
+
+    // a pause doesn't count as a gap
+    svm.send_one(ix::pause_focus_session(&user, session_id), &[user])
+        .unwrap();
+    svm.warp_minutes(20);
+    svm.send_one(ix::resume_focus_session(&user, session_id), &[user])
+        .unwrap();
+
+    // five missed heartbeats are tolerated, a sixth is not
+    svm.warp_minutes(6);
+    expect_err(
+        svm.send_one(
+            ix::expire_focus_session(&keeper, &user, &user, session_id),
+            &[keeper],
+        ),
+        program_error(ErrorCode::SessionNotComplete),
+    );
+    svm.warp(1);
+    expect_err(
+        svm.send_one(ix::heartbeat(&user, &user, session_id), &[user]),
+        program_error(ErrorCode::HeartbeatLapsed),
+    );
+    let keeper_before = svm.balance(&keeper);
+    svm.send_one(
+        ix::expire_focus_session(&keeper, &user, &user, session_id),
+        &[keeper],
+    )
+    .unwrap();
+    assert_eq!(svm.balance(&keeper), keeper_before + LOCKED / 100);
+    assert_eq!(svm.user_stats(&user).sessions_expired, 1);
+    svm.assert_solvent();
+}
+
+#[test]
+fn lapsed_sessions_cannot_pause_or_refund() {
+    let (mut svm, authority) = setup();
+    require_heartbeats(&mut svm, &authority);
+    svm.send_one(
+        ix::set_refund_curve(
+            &authority,
+            RefundCurve::Linear {
+                max_refund_bps: 10_000,
+            },
+        ),
+        &[authority],
+    )
+    .unwrap();
+    let user = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 60, &["a"], None);
+    svm.warp_minutes(1);
+    svm.send_one(ix::heartbeat(&user, &user, session_id), &[user])
+        .unwrap();
+
+    // resuming doesn't reset the gap: 4 minutes before the pause and 2 after
+    svm.warp_minutes(4);
+    svm.send_one(ix::pause_focus_session(&user, session_id), &[user])
+        .unwrap();
+    svm.warp_minutes(10);
+    svm.send_one(ix::resume_focus_session(&user, session_id), &[user])
+        .unwrap();
+    svm.warp_minutes(2);
+    expect_err(
+        svm.send_one(
+            ix::expire_focus_session(&keeper, &user, &user, session_id),
+            &[keeper],
+        ),
+        program_error(ErrorCode::SessionNotComplete),
+    );
+    svm.warp(1);
+    expect_err(
+        svm.send_one(ix::heartbeat(&user, &user, session_id), &[user]),
+        program_error(ErrorCode::HeartbeatLapsed),
+    );
+
+    // and a lapsed session can't pause its way out of it
+    expect_err(
+        svm.send_one(ix::pause_focus_session(&user, session_id), &[user]),
+        program_error(ErrorCode::HeartbeatLapsed),
+    );
+
+    // failing it refunds nothing, whatever the curve says
+    svm.send_one(ix::fail_focus_session(&user, &user, session_id), &[user])
+        .unwrap();
+    let stats = svm.user_stats(&user);
+    assert_eq!(stats.total_refunded, 0);
+    assert_eq!(stats.total_forfeited, LOCKED);
+    assert_eq!(svm.global_state().failure_pool, LOCKED);
+    svm.assert_solvent();
+}
+
+/// Heartbeats every minute with a spot-check scheduled by each one.
+fn require_challenges(svm: &mut Svm, authority: &Pubkey) {
+    let config = Config {
+        heartbeat_interval_seconds: 60,
+        challenge_rate_bps: 10_000,
+        ..Config::default()
+    };
+    svm.send_one(ix::update_config(authority, config), &[*authority])
+        .unwrap();
+}
+
+fn open_challenge(svm: &Svm, user: &Pubkey, session_id: u64) -> Option<Challenge> {
+    svm.focus_session(user, session_id).challenge
+}
+
+#[test]
+fn heartbeats_need_slot_hashes() {
+    let (mut svm, authority) = setup();
+    require_challenges(&mut svm, &authority);
+    let user = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+
+    // nothing to draw a spot-check from
+    svm.clear_slot_hashes();
+    svm.warp_minutes(1);
+    expect_err(
+        svm.send_one(ix::heartbeat(&user, &user, session_id), &[user]),
+        program_error(ErrorCode::SlotHashesUnavailable),
+    );
+    assert_eq!(svm.focus_session(&user, session_id).last_heartbeat, 0);
+
+    svm.set_slot_hash([9; 32]);
+    svm.send_one(ix::heartbeat(&user, &user, session_id), &[user])
+        .unwrap();
+    assert_eq!(
+        svm.focus_session(&user, session_id).last_heartbeat,
+        svm.now()
+    );
+}
+
+#[test]
+fn challenges_are_answered_inside_their_window() {
+    let (mut svm, authority) = setup();
+    require_challenges(&mut svm, &authority);
+    // a fixed key, so the spot-checks drawn don't depend on what else ran first
+    let user = Pubkey::new_from_array([6; 32]);
+    svm.airdrop(&user, 100 * LAMPORTS_PER_SOL);
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+    let end = svm.now() + 30 * 60;
+
+    svm.warp_minutes(1);
+    svm.send_one(ix::heartbeat(&user, &user, session_id), &[user])
+        .unwrap();
+    let challenge = open_challenge(&svm, &user, session_id).unwrap();
+    assert!(challenge.opens_at >= svm.now());
+    assert_eq!(challenge.closes_at - challenge.opens_at, 300);
+    assert!(challenge.closes_at <= end);
+    if challenge.opens_at > svm.now() {
+        expect_err(
+            svm.send_one(ix::answer_challenge(&user, &user, session_id), &[user]),
+            program_error(ErrorCode::NoOpenChallenge),
+        );
+    }
+
+    let stranger = funded_user(&mut svm);
+    expect_err(
+        svm.send_one(
+            ix::answer_challenge(&stranger, &user, session_id),
+            &[stranger],
+        ),
+        program_error(ErrorCode::UnauthorizedSigner),
+    );
+    while svm.now() < challenge.opens_at {
+        svm.warp_minutes(1);
+        svm.send_one(ix::heartbeat(&user, &user, session_id), &[user])
+            .unwrap();
+    }
+    svm.send_one(ix::answer_challenge(&user, &user, session_id), &[user])
+        .unwrap();
+
+    let mut answered = 1;
+    while svm.now() < end {
+        svm.warp_minutes(1);
+        svm.send_one(ix::heartbeat(&user, &user, session_id), &[user])
+            .unwrap();
+        if let Some(challenge) = open_challenge(&svm, &user, session_id) {
+            if svm.now() >= challenge.opens_at {
+                svm.send_one(ix::answer_challenge(&user, &user, session_id), &[user])
+                    .unwrap();
+                answered += 1;
+            }
+        }
+    }
+    assert!(answered > 1);
+    // every spot-check fits in the session, so none is left at the end
+    assert!(open_challenge(&svm, &user, session_id).is_none());
+    expect_err(
+        svm.send_one(ix::answer_challenge(&user, &user, session_id), &[user]),
+        program_error(ErrorCode::NoOpenChallenge),
+    );
+    complete(&mut svm, &user, session_id);
+    svm.assert_solvent();
+}
+
+#[test]
+fn concurrent_sessions_keep_their_own_challenges() {
+    let (mut svm, authority) = setup();
+    require_challenges(&mut svm, &authority);
+    let user = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+    let long = svm.start_session(&user, STAKE, 30, &["a"], None);
+    let short = svm.start_session(&user, STAKE, 2, &["a"], None);
+
+    // a spot-check window doesn't fit in the short session, so only the long one draws
+    svm.warp_minutes(1);
+    for session_id in [long, short] {
+        svm.send_one(ix::heartbeat(&user, &user, session_id), &[user])
+            .unwrap();
+    }
+    let challenge = open_challenge(&svm, &user, long).unwrap();
+    assert!(open_challenge(&svm, &user, short).is_none());
+
+    // the long session's pending spot-check doesn't hold up the short one
+    svm.warp_minutes(1);
+    for session_id in [long, short] {
+        svm.send_one(ix::heartbeat(&user, &user, session_id), &[user])
+            .unwrap();
+    }
+    complete(&mut svm, &user, short);
+    assert!(open_challenge(&svm, &user, long) == Some(challenge));
+
+    // and missing it forfeits only the long session
+    while svm.now() <= challenge.closes_at {
+        svm.warp((challenge.closes_at + 1 - svm.now()).min(60));
+        if svm.now() <= challenge.closes_at {
+            svm.send_one(ix::heartbeat(&user, &user, long), &[user])
+                .unwrap();
+        }
+    }
+    svm.send_one(ix::update_task(&user, &user, short, None, 0, true), &[user])
+        .unwrap();
+    let before = svm.balance(&user);
+    svm.send_one(ix::claim_rewards(&user, &user, short), &[user])
+        .unwrap();
+    assert!(svm.balance(&user) >= before + LOCKED);
+    svm.send_one(
+        ix::expire_focus_session(&keeper, &user, &user, long),
+        &[keeper],
+    )
+    .unwrap();
+    let stats = svm.user_stats(&user);
+    assert_eq!(stats.sessions_completed, 1);
+    assert_eq!(stats.sessions_expired, 1);
+    assert_eq!(stats.total_refunded, LOCKED);
+    svm.assert_solvent();
+}
+
+#[test]
+fn missed_challenge_forfeits_before_the_duration_ends() {
+    let (mut svm, authority) = setup();
+    require_challenges(&mut svm, &authority);
+    let user = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+    svm.send_one(
+        ix::set_refund_curve(
+            &authority,
+            RefundCurve::Linear {
+                max_refund_bps: 10_000,
+            },
+        ),
+        &[authority],
+    )
+    .unwrap();
+    let ids: Vec<u64> = (0..3)
+        .map(|_| svm.start_session(&user, STAKE, 60, &["a"], None))
+        .collect();
+    let end = svm.now() + 60 * 60;
+    svm.warp_minutes(1);
+    let mut sessions: Vec<(u64, Challenge)> = ids
+        .into_iter()
+        .map(|session_id| {
+            svm.send_one(ix::heartbeat(&user, &user, session_id), &[user])
+                .unwrap();
+            (session_id, open_challenge(&svm, &user, session_id).unwrap())
+        })
+        .collect();
+    // each session drew its own window; settle them in the order they close
+    sessions.sort_by_key(|(_, challenge)| challenge.closes_at);
+    let [(expired, first), (batched, second), (failed, third)] = sessions[..] else {
+        unreachable!()
+    };
+    // heartbeats alone don't answer a spot-check, and keep the unmissed sessions going
+    let heartbeat_past = |svm: &mut Svm, until: i64| {
+        while svm.now() <= until {
+            svm.warp((until + 1 - svm.now()).min(60));
+            for (session_id, challenge) in &sessions {
+                if svm.now() <= challenge.closes_at {
+                    svm.send_one(ix::heartbeat(&user, &user, *session_id), &[user])
+                        .unwrap();
+                }
+            }
+        }
+    };
+
+    expect_err(
+        svm.send_one(
+            ix::expire_focus_session(&keeper, &user, &user, expired),
+            &[keeper],
+        ),
+        program_error(ErrorCode::SessionNotComplete),
+    );
+    svm.send_one(
+        ix::expire_focus_sessions_batch(&keeper, &[(user, batched, user)]),
+        &[keeper],
+    )
+    .unwrap();
+    assert!(svm.focus_session(&user, batched).is_active);
+
+    heartbeat_past(&mut svm, first.closes_at);
+    for result in [
+        svm.send_one(ix::heartbeat(&user, &user, expired), &[user]),
+        svm.send_one(ix::answer_challenge(&user, &user, expired), &[user]),
+    ] {
+        expect_err(result, program_error(ErrorCode::ChallengeMissed));
+    }
+    let keeper_before = svm.balance(&keeper);
+    svm.send_one(
+        ix::expire_focus_session(&keeper, &user, &user, expired),
+        &[keeper],
+    )
+    .unwrap();
+
+    heartbeat_past(&mut svm, second.closes_at);
+    svm.send_one(
+        ix::expire_focus_sessions_batch(&keeper, &[(user, batched, user)]),
+        &[keeper],
+    )
+    .unwrap();
+    assert!(!svm.exists(&pda::focus_session(&user, batched)));
+    assert_eq!(svm.balance(&keeper), keeper_before + 2 * (LOCKED / 100));
+    assert_eq!(svm.user_stats(&user).sessions_expired, 2);
+
+    // failing instead forfeits everything, whatever the refund curve says
+    heartbeat_past(&mut svm, third.closes_at);
+    assert!(svm.now() < end);
+    svm.send_one(ix::fail_focus_session(&user, &user, failed), &[user])
+        .unwrap();
+    let stats = svm.user_stats(&user);
+    assert_eq!(stats.total_refunded, 0);
+    assert_eq!(stats.sessions_failed, 1);
+
+    // the miss doesn't carry over to sessions started after it
+    let next = svm.start_session(&user, STAKE, 30, &["a"], None);
+    svm.warp_minutes(1);
+    svm.send_one(ix::heartbeat(&user, &user, next), &[user])
+        .unwrap();
+    svm.assert_solvent();
+}
+
+#[test]
+fn legacy_complete_returns_stake_and_closes() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 25, &["read"], None);
+
+    expect_err(
+        svm.send_one(
+            ix::complete_focus_session_v1(&user, &user, session_id),
+            &[user],
+        ),
+        program_error(ErrorCode::SessionNotComplete),
+    );
+
+    svm.warp_minutes(25);
+    let session_key = pda::focus_session(&user, session_id);
+    let expected = svm.balance(&user) + LOCKED + svm.balance(&session_key);
+    svm.send_one(
+        ix::complete_focus_session_v1(&user, &user, session_id),
+        &[user],
+    )
+    .unwrap();
+
+    assert_eq!(svm.balance(&user), expected);
+    assert!(!svm.exists(&session_key));
+    svm.assert_solvent();
+}
+
+#[test]
+fn user_stats_track_every_settlement() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+
+    // two completions in a row, the first one claimed in full
+    let claimed = svm.start_session(&user, STAKE, 30, &["a"], None);
+    complete(&mut svm, &user, claimed);
+    svm.send_one(
+        ix::update_task(&user, &user, claimed, None, 0, true),
+        &[user],
+    )
+    .unwrap();
+    svm.send_one(ix::claim_rewards(&user, &user, claimed), &[user])
+        .unwrap();
+    let second = svm.start_session(&user, STAKE, 30, &["a"], None);
+    complete(&mut svm, &user, second);
+    let stats = svm.user_stats(&user);
+    assert_eq!(stats.current_streak, 2);
+    assert_eq!(stats.best_streak, 2);
+
+    // failing and expiring both break the streak
+    let failed = svm.start_session(&user, STAKE, 30, &["a"], None);
+    svm.warp_minutes(10);
+    svm.send_one(ix::fail_focus_session(&user, &user, failed), &[user])
+        .unwrap();
+    let expired = svm.start_session(&user, STAKE, 30, &["a"], None);
+    let last_start = svm.now();
+    svm.warp_minutes(35);
+    svm.send_one(
+        ix::expire_focus_session(&keeper, &user, &user, expired),
+        &[keeper],
+    )
+    .unwrap();
+
+    let stats = svm.user_stats(&user);
+    assert_eq!(stats.user, user);
+    assert_eq!(stats.sessions_started, 4);
+    assert_eq!(stats.sessions_completed, 2);
+    assert_eq!(stats.sessions_failed, 1);
+    assert_eq!(stats.sessions_expired, 1);
+    assert_eq!(stats.minutes_focused, 30 + 30 + 10);
+    assert_eq!(stats.total_staked, 4 * STAKE);
+    assert_eq!(stats.total_refunded, LOCKED);
+    // the keeper bounty counts as forfeited too
+    assert_eq!(stats.total_forfeited, 2 * LOCKED);
+    assert_eq!(stats.current_streak, 0);
+    assert_eq!(stats.best_streak, 2);
+    assert_eq!(stats.last_session_at, last_start);
+    svm.assert_solvent();
+}
+
+#[test]
+fn fail_forfeits_stake_without_refund_curve() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 60, &["code"], None);
+    svm.warp_minutes(59);
+
+    let session_key = pda::focus_session(&user, session_id);
+    let expected = svm.balance(&user) + svm.balance(&session_key);
+    svm.send_one(ix::fail_focus_session(&user, &user, session_id), &[user])
+        .unwrap();
+
+    assert_eq!(svm.balance(&user), expected);
+    assert_eq!(svm.global_state().failure_pool, LOCKED);
+    assert_eq!(svm.global_state().failure_pool_inflow, LOCKED);
+    assert!(!svm.exists(&session_key));
+
+    // settled sessions are closed
+    expect_err(
+        svm.send_one(ix::fail_focus_session(&user, &user, session_id), &[user]),
+        anchor_error(AnchorErrorCode::AccountNotInitialized),
+    );
+    svm.assert_solvent();
+}
+
+#[test]
+fn fail_refunds_along_refund_curve() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+
+    let curves = [
+        // half the duration at a 50% cap refunds 25%
+        (
+            RefundCurve::Linear {
+                max_refund_bps: 5_000,
+            },
+            30,
+            2_500,
+        ),
+        // 75% elapsed reaches the second step
+        (
+            RefundCurve::Stepped {
+                steps: vec![
+                    RefundStep {
+                        min_elapsed_bps: 2_500,
+                        refund_bps: 1_000,
+                    },
+                    RefundStep {
+                        min_elapsed_bps: 7_500,
+                        refund_bps: 5_000,
+                    },
+                ],
+            },
+            45,
+            5_000,
+        ),
+    ];
+    for (curve, elapsed_minutes, refund_bps) in curves {
+        svm.send_one(ix::set_refund_curve(&authority, curve), &[authority])
+            .unwrap();
+        let session_id = svm.start_session(&user, STAKE, 60, &["code"], None);
+        svm.warp_minutes(elapsed_minutes);
+
+        let session_key = pda::focus_session(&user, session_id);
+        let user_before = svm.balance(&user) + svm.balance(&session_key);
+        let failure_pool_before = svm.global_state().failure_pool;
+        svm.send_one(ix::fail_focus_session(&user, &user, session_id), &[user])
+            .unwrap();
+
+        let refund = LOCKED * refund_bps / 10_000;
+        assert_eq!(svm.balance(&user), user_before + refund);
+        assert_eq!(
+            svm.global_state().failure_pool,
+            failure_pool_before + LOCKED - refund
+        );
+        svm.assert_solvent();
+    }
+}
+
+#[test]
+fn refund_curves_are_validated() {
+    let (mut svm, authority) = setup();
+    let step = |min_elapsed_bps, refund_bps| RefundStep {
+        min_elapsed_bps,
+        refund_bps,
+    };
+    let invalid = [
+        RefundCurve::Linear {
+            max_refund_bps: 10_001,
+        },
+        RefundCurve::Stepped { steps: vec![] },
+        RefundCurve::Stepped {
+            steps: vec![step(5_000, 1_000), step(5_000, 2_000)],
+        },
+        RefundCurve::Stepped {
+            steps: vec![step(1_000, 5_000), step(5_000, 1_000)],
+        },
+        RefundCurve::Stepped {
+            steps: vec![step(10_001, 1_000)],
+        },
+    ];
+    for curve in invalid {
+        expect_err(
+            svm.send_one(ix::set_refund_curve(&authority, curve), &[authority]),
+            program_error(ErrorCode::InvalidRefundCurve),
+        );
+    }
+
+    let stranger = Pubkey::new_unique();
+    expect_err(
+        svm.send_one(
+            ix::set_refund_curve(&stranger, RefundCurve::None),
+            &[stranger],
+        ),
+        anchor_error(AnchorErrorCode::RequireKeysEqViolated),
+    );
+}
+
+#[test]
+fn streak_needs_full_length_sessions() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let current_epoch = svm.global_state().current_epoch;
+    let grace_minutes = svm.global_state().config.grace_minutes;
+
+    // completing inside the grace window settles but doesn't count
+    let early = svm.start_session(&user, STAKE, 30, &["a"], None);
+    svm.warp_minutes(30 - grace_minutes as i64);
+    svm.send_one(
+        ix::complete_focus_session(&user, &user, early, current_epoch),
+        &[user],
+    )
+    .unwrap();
+    assert_eq!(svm.user_stats(&user).current_streak, 0);
+
+    // nor does a session no longer than the grace window, even run in full
+    let short = svm.start_session(&user, STAKE, grace_minutes, &["a"], None);
+    complete(&mut svm, &user, short);
+    assert_eq!(svm.user_stats(&user).current_streak, 0);
+
+    let full = svm.start_session(&user, STAKE, grace_minutes + 1, &["a"], None);
+    complete(&mut svm, &user, full);
+    let stats = svm.user_stats(&user);
+    assert_eq!(stats.sessions_completed, 3);
+    assert_eq!(stats.current_streak, 1);
+    assert_eq!(stats.best_streak, 1);
+}
+
+#[test]
+fn concurrent_sessions_extend_the_streak_once() {
+    let (mut svm, authority) = setup();
+    let tiers = vec![StreakTier {
+        min_streak: 2,
+        bonus_bps: 100,
+    }];
+    svm.send_one(ix::set_streak_tiers(&authority, tiers), &[authority])
+        .unwrap();
+    let user = funded_user(&mut svm);
+
+    // three sessions side by side count once
+    let parallel: Vec<u64> = (0..3)
+        .map(|_| svm.start_session(&user, STAKE, 30, &["a"], None))
+        .collect();
+    for &session_id in &parallel {
+        complete(&mut svm, &user, session_id);
+    }
+    assert_eq!(svm.user_stats(&user).current_streak, 1);
+
+    // and so does one started before the last completion, even if it ends later
+    let first = svm.start_session(&user, STAKE, 30, &["a"], None);
+    svm.warp_minutes(10);
+    let overlapping = svm.start_session(&user, STAKE, 30, &["a"], None);
+    complete(&mut svm, &user, first);
+    complete(&mut svm, &user, overlapping);
+    let stats = svm.user_stats(&user);
+    assert_eq!(stats.sessions_completed, 5);
+    assert_eq!(stats.current_streak, 2);
+    assert_eq!(stats.best_streak, 2);
+    assert_eq!(svm.focus_session(&user, first).streak_bonus_bps, 100);
+    assert_eq!(svm.focus_session(&user, overlapping).streak_bonus_bps, 0);
+
+    // a session started after it extends the streak again
+    let next = svm.start_session(&user, STAKE, 30, &["a"], None);
+    complete(&mut svm, &user, next);
+    assert_eq!(svm.user_stats(&user).current_streak, 3);
+    svm.assert_solvent();
+}
+
+#[test]
+fn streak_tiers_are_validated() {
+    let (mut svm, authority) = setup();
+    let tier = |min_streak, bonus_bps| StreakTier {
+        min_streak,
+        bonus_bps,
+    };
+    let invalid = [
+        vec![tier(0, 100)],
+        vec![tier(3, 2_001)],
+        vec![tier(3, 100), tier(3, 200)],
+        vec![tier(3, 200), tier(5, 100)],
+        (1..=6).map(|streak| tier(streak, 100)).collect(),
+    ];
+    for streak_tiers in invalid {
+        expect_err(
+            svm.send_one(ix::set_streak_tiers(&authority, streak_tiers), &[authority]),
+            program_error(ErrorCode::InvalidStreakTiers),
+        );
+    }
+
+    let stranger = Pubkey::new_unique();
+    expect_err(
+        svm.send_one(ix::set_streak_tiers(&stranger, vec![]), &[stranger]),
+        anchor_error(AnchorErrorCode::RequireKeysEqViolated),
+    );
+}
+
+#[test]
+fn streak_bonus_is_paid_from_the_focus_pool() {
+    let (mut svm, authority) = setup();
+    let tiers = vec![
+        StreakTier {
+            min_streak: 2,
+            bonus_bps: 100,
+        },
+        StreakTier {
+            min_streak: 3,
+            bonus_bps: 1_000,
+        },
+    ];
+    svm.send_one(ix::set_streak_tiers(&authority, tiers), &[authority])
+        .unwrap();
+    let user = funded_user(&mut svm);
+
+    // the tier is fixed when the session completes
+    for expected_bps in [0, 100, 1_000] {
+        let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+        complete(&mut svm, &user, session_id);
+        assert_eq!(
+            svm.focus_session(&user, session_id).streak_bonus_bps,
+            expected_bps
+        );
+        svm.send_one(
+            ix::update_task(&user, &user, session_id, None, 0, true),
+            &[user],
+        )
+        .unwrap();
+    }
+    let fees = 3 * (STAKE / 100);
+    assert_eq!(svm.global_state().focus_pool, fees);
+
+    let claim = |svm: &mut Svm, session_id| {
+        let expected = svm.balance(&user) + svm.balance(&pda::focus_session(&user, session_id));
+        svm.send_one(ix::claim_rewards(&user, &user, session_id), &[user])
+            .unwrap();
+        svm.balance(&user) - expected
+    };
+    assert_eq!(claim(&mut svm, 0), LOCKED);
+    let bonus = LOCKED / 100;
+    assert_eq!(claim(&mut svm, 1), LOCKED + bonus);
+    assert_eq!(svm.global_state().focus_pool, fees - bonus);
+
+    // a 10% bonus is more than the focus pool holds, so it pays what's left
+    assert_eq!(claim(&mut svm, 2), LOCKED + fees - bonus);
+    assert_eq!(svm.global_state().focus_pool, 0);
+    assert_eq!(svm.user_stats(&user).total_streak_bonus, fees);
+
+    // failing resets the streak back below the first tier
+    let failed = svm.start_session(&user, STAKE, 30, &["a"], None);
+    svm.send_one(ix::fail_focus_session(&user, &user, failed), &[user])
+        .unwrap();
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+    complete(&mut svm, &user, session_id);
+    assert_eq!(svm.focus_session(&user, session_id).streak_bonus_bps, 0);
+    assert_eq!(svm.user_stats(&user).best_streak, 3);
+    svm.assert_solvent();
+}
+
+/// Completes `session_id` with `report` as signed by `attestor`.
+fn complete_attested(
+    svm: &mut Svm,
+    user: &Pubkey,
+    session_id: u64,
+    attestor: &Keypair,
+    report: &FocusReport,
+) -> Result<(), ProgramError> {
+    let signature = attestor.sign_message(&report.message()).into();
+    let current_epoch = svm.global_state().current_epoch;
+    svm.send(
+        &[
+            ix::verify_focus_report(&attestor.pubkey(), report, &signature),
+            ix::complete_focus_session(user, user, session_id, current_epoch),
+        ],
+        &[*user],
+    )
+}
+
+#[test]
+fn attested_focus_report_gates_and_scales_the_refund() {
+    let (mut svm, authority) = setup();
+    let attestor = Keypair::new_from_array([1; 32]);
+    let impostor = Keypair::new_from_array([2; 32]);
+    let stranger = funded_user(&mut svm);
+    expect_err(
+        svm.send_one(
+            ix::set_attestor(&stranger, Some(attestor.pubkey())),
+            &[stranger],
+        ),
+        anchor_error(AnchorErrorCode::RequireKeysEqViolated),
+    );
+    svm.send_one(
+        ix::set_attestor(&authority, Some(attestor.pubkey())),
+        &[authority],
+    )
+    .unwrap();
+
+    let user = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+    svm.warp_minutes(30);
+    expect_err(
+        svm.send_one(
+            ix::complete_focus_session(&user, &user, session_id, 0),
+            &[user],
+        ),
+        program_error(ErrorCode::FocusReportRequired),
+    );
+    expect_err(
+        svm.send_one(
+            ix::complete_focus_session_v1(&user, &user, session_id),
+            &[user],
+        ),
+        program_error(ErrorCode::FocusReportRequired),
+    );
+
+    let report = FocusReport {
+        user,
+        session_start: svm.focus_session(&user, session_id).start_time,
+        focused_minutes: 27,
+        distractions: 3,
+    };
+    let invalid = [
+        (&impostor, report.clone()),
+        (
+            &attestor,
+            FocusReport {
+                session_start: report.session_start + 60,
+                ..report.clone()
+            },
+        ),
+        (
+            &attestor,
+            FocusReport {
+                user: stranger,
+                ..report.clone()
+            },
+        ),
+        // more focus than the session has run
+        (
+            &attestor,
+            FocusReport {
+                focused_minutes: 31,
+                ..report.clone()
+            },
+        ),
+    ];
+    for (signer, report) in invalid {
+        expect_err(
+            complete_attested(&mut svm, &user, session_id, signer, &report),
+            program_error(ErrorCode::InvalidFocusReport),
+        );
+    }
+    // a signature that isn't the attestor's, or over another report, fails the
+    // precompile before the program runs
+    let current_epoch = svm.global_state().current_epoch;
+    let forged = [
+        impostor.sign_message(&report.message()).into(),
+        attestor
+            .sign_message(
+                &FocusReport {
+                    focused_minutes: 30,
+                    ..report.clone()
+                }
+                .message(),
+            )
+            .into(),
+    ];
+    for signature in forged {
+        expect_err(
+            svm.send(
+                &[
+                    ix::verify_focus_report(&attestor.pubkey(), &report, &signature),
+                    ix::complete_focus_session(&user, &user, session_id, current_epoch),
+                ],
+                &[user],
+            ),
+            precompile_error(PrecompileError::InvalidSignature),
+        );
+    }
+    // two thirds of the duration is under the default 80%
+    expect_err(
+        complete_attested(
+            &mut svm,
+            &user,
+            session_id,
+            &attestor,
+            &FocusReport {
+                focused_minutes: 20,
+                ..report.clone()
+            },
+        ),
+        program_error(ErrorCode::InsufficientAttestedFocus),
+    );
+
+    // 90% focused less 1% for each of three distractions
+    complete_attested(&mut svm, &user, session_id, &attestor, &report).unwrap();
+    assert_eq!(
+        svm.focus_session(&user, session_id).attested_focus_bps,
+        8_700
+    );
+    svm.send_one(
+        ix::update_task(&user, &user, session_id, None, 0, true),
+        &[user],
+    )
+    .unwrap();
+    let expected = svm.balance(&user) + svm.balance(&pda::focus_session(&user, session_id));
+    let failure_pool = svm.global_state().failure_pool;
+    svm.send_one(ix::claim_rewards(&user, &user, session_id), &[user])
+        .unwrap();
+    let refund = LOCKED / 10_000 * 8_700;
+    assert_eq!(svm.balance(&user) - expected, refund);
+    assert_eq!(
+        svm.global_state().failure_pool,
+        failure_pool + LOCKED - refund
+    );
+
+    // without an attestor completion goes by the clock alone
+    svm.send_one(ix::set_attestor(&authority, None), &[authority])
+        .unwrap();
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+    complete(&mut svm, &user, session_id);
+    assert_eq!(
+        svm.focus_session(&user, session_id).attested_focus_bps,
+        10_000
+    );
+    svm.assert_solvent();
+}
+
+#[test]
+fn expire_pays_keeper_bounty_and_rent_to_the_payer() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let payer = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+    let session_id = svm.start_sponsored_session(&user, &payer, STAKE, 30, &["a"], None);
+
+    // expirable once duration plus grace has passed
+    svm.warp_minutes(34);
+    expect_err(
+        svm.send_one(
+            ix::expire_focus_session(&keeper, &user, &payer, session_id),
+            &[keeper],
+        ),
+        program_error(ErrorCode::SessionNotComplete),
+    );
+
+    svm.warp_minutes(1);
+    let session_key = pda::focus_session(&user, session_id);
+    let payer_expected = svm.balance(&payer) + svm.balance(&session_key);
+    let user_before = svm.balance(&user);
+    let keeper_before = svm.balance(&keeper);
+    // neither the user nor the payer signs, and the user's wallet isn't write-locked
+    let expire = ix::expire_focus_session(&keeper, &user, &payer, session_id);
+    assert!(expire
+        .accounts
+        .iter()
+        .all(|meta| meta.pubkey != user || !meta.is_writable));
+    svm.send_one(expire, &[keeper]).unwrap();
+
+    let bounty = LOCKED / 100;
+    assert_eq!(svm.balance(&keeper), keeper_before + bounty);
+    assert_eq!(svm.balance(&payer), payer_expected);
+    assert_eq!(svm.balance(&user), user_before);
+    assert_eq!(svm.global_state().failure_pool, LOCKED - bounty);
+    assert!(!svm.exists(&session_key));
+    svm.assert_solvent();
+}
+
+#[test]
+fn sponsored_rent_goes_back_to_the_payer() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let relayer = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+
+    // the relayer has to sign for the rent it pays
+    let start = ix::start_focus_session(
+        &relayer,
+        &user,
+        0,
+        STAKE,
+        30,
+        tasks_from(&["a"]),
+        None,
+        None,
+    );
+    expect_err(
+        svm.send_one(start.clone(), &[user]),
+        ProgramError::MissingRequiredSignature,
+    );
+
+    // the user only provides the stake
+    let user_before = svm.balance(&user);
+    let relayer_before = svm.balance(&relayer);
+    svm.send_one(start, &[user, relayer]).unwrap();
+    let claimed = 0;
+    assert_eq!(svm.balance(&user), user_before - STAKE);
+    let session_rent = svm.balance(&pda::focus_session(&user, claimed));
+    let permanent_rent =
+        svm.balance(&pda::user_state(&user)) + svm.balance(&pda::user_stats(&user));
+    assert_eq!(
+        svm.balance(&relayer),
+        relayer_before - session_rent - permanent_rent
+    );
+    assert_eq!(svm.focus_session(&user, claimed).rent_payer, relayer);
+
+    complete(&mut svm, &user, claimed);
+    expect_err(
+        svm.send_one(ix::claim_rewards(&user, &user, claimed), &[user]),
+        program_error(ErrorCode::InvalidRentPayer),
+    );
+    let relayer_before = svm.balance(&relayer);
+    let user_before = svm.balance(&user);
+    svm.send_one(ix::claim_rewards(&user, &relayer, claimed), &[user])
+        .unwrap();
+    assert_eq!(svm.balance(&relayer), relayer_before + session_rent);
+    assert_eq!(svm.balance(&user), user_before);
+
+    let failed = svm.start_sponsored_session(&user, &relayer, STAKE, 30, &["a"], None);
+    let relayer_before = svm.balance(&relayer);
+    svm.send_one(ix::fail_focus_session(&user, &relayer, failed), &[user])
+        .unwrap();
+    assert_eq!(svm.balance(&relayer), relayer_before + session_rent);
+
+    let expired = svm.start_sponsored_session(&user, &relayer, STAKE, 30, &["a"], None);
+    svm.warp_minutes(35);
+    expect_err(
+        svm.send_one(
+            ix::expire_focus_sessions_batch(&keeper, &[(user, expired, user)]),
+            &[keeper],
+        ),
+        program_error(ErrorCode::InvalidBatchAccounts),
+    );
+    let relayer_before = svm.balance(&relayer);
+    svm.send_one(
+        ix::expire_focus_sessions_batch(&keeper, &[(user, expired, relayer)]),
+        &[keeper],
+    )
+    .unwrap();
+    assert_eq!(svm.balance(&relayer), relayer_before + session_rent);
+    svm.assert_solvent();
+}
+
+#[test]
+fn completed_sessions_cannot_be_expired() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+    complete(&mut svm, &user, session_id);
+
+    svm.warp_minutes(60);
+    expect_err(
+        svm.send_one(
+            ix::expire_focus_session(&keeper, &user, &user, session_id),
+            &[keeper],
+        ),
+        program_error(ErrorCode::NoActiveSession),
+    );
+    expect_err(
+        svm.send_one(ix::fail_focus_session(&user, &user, session_id), &[user]),
+        program_error(ErrorCode::NoActiveSession),
+    );
+}
+
+#[test]
+fn batch_expiry_settles_only_overdue_sessions() {
+    let (mut svm, _) = setup();
+    let keeper = funded_user(&mut svm);
+    let overdue: Vec<Pubkey> = (0..3).map(|_| funded_user(&mut svm)).collect();
+    let fresh = funded_user(&mut svm);
+    let completed = funded_user(&mut svm);
+    let claimed = funded_user(&mut svm);
+
+    for user in &overdue {
+        svm.start_session(user, STAKE, 30, &["a"], None);
+    }
+    svm.start_session(&completed, STAKE, 30, &["a"], None);
+    complete(&mut svm, &completed, 0);
+    // settled while the keeper's batch was in flight
+    svm.start_session(&claimed, STAKE, 30, &["a"], None);
+    complete(&mut svm, &claimed, 0);
+    svm.send_one(ix::claim_rewards(&claimed, &claimed, 0), &[claimed])
+        .unwrap();
+    svm.warp_minutes(5);
+    // started 35 minutes after the others
+    svm.start_session(&fresh, STAKE, 30, &["a"], None);
+
+    let mut sessions: Vec<(Pubkey, u64, Pubkey)> =
+        overdue.iter().map(|user| (*user, 0, *user)).collect();
+    sessions.push((fresh, 0, fresh));
+    sessions.push((completed, 0, completed));
+    sessions.push((claimed, 0, claimed));
+
+    let keeper_before = svm.balance(&keeper);
+    let failure_pool_before = svm.global_state().failure_pool;
+    svm.send_one(
+        ix::expire_focus_sessions_batch(&keeper, &sessions),
+        &[keeper],
+    )
+    .unwrap();
+
+    let bounty = LOCKED / 100;
+    assert_eq!(svm.balance(&keeper), keeper_before + 3 * bounty);
+    assert_eq!(
+        svm.global_state().failure_pool,
+        failure_pool_before + 3 * (LOCKED - bounty)
+    );
+    for user in &overdue {
+        assert!(!svm.exists(&pda::focus_session(user, 0)));
+    }
+    assert!(svm.focus_session(&fresh, 0).is_active);
+    assert_eq!(svm.focus_session(&completed, 0).pending_balance, LOCKED);
+    svm.assert_solvent();
+}
+
+#[test]
+fn batch_expiry_rejects_malformed_accounts() {
+    let (mut svm, _) = setup();
+    let keeper = funded_user(&mut svm);
+    let user = funded_user(&mut svm);
+    let other = funded_user(&mut svm);
+    svm.start_session(&user, STAKE, 30, &["a"], None);
+    svm.warp_minutes(35);
+
+    // a trailing user without its session
+    let mut odd = ix::expire_focus_sessions_batch(&keeper, &[(user, 0, user)]);
+    odd.accounts.push(AccountMeta::new(other, false));
+    expect_err(
+        svm.send_one(odd, &[keeper]),
+        program_error(ErrorCode::InvalidBatchAccounts),
+    );
+
+    // the user account must be the session's owner
+    let mut mismatched = ix::expire_focus_sessions_batch(&keeper, &[(user, 0, user)]);
+    let user_meta = mismatched.accounts.len() - 4;
+    mismatched.accounts[user_meta] = AccountMeta::new(other, false);
+    expect_err(
+        svm.send_one(mismatched, &[keeper]),
+        program_error(ErrorCode::InvalidBatchAccounts),
+    );
+
+    // and the stats to the same user
+    svm.start_session(&other, STAKE, 30, &["a"], None);
+    let mut wrong_stats = ix::expire_focus_sessions_batch(&keeper, &[(user, 0, user)]);
+    let stats_meta = wrong_stats.accounts.len() - 2;
+    wrong_stats.accounts[stats_meta] = AccountMeta::new(pda::user_stats(&other), false);
+    expect_err(
+        svm.send_one(wrong_stats, &[keeper]),
+        program_error(ErrorCode::InvalidBatchAccounts),
+    );
+
+    assert!(svm.focus_session(&user, 0).is_active);
+}
+
+#[test]
+fn pauses_extend_the_session() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 60, &["a"], None);
+
+    expect_err(
+        svm.send_one(ix::resume_focus_session(&user, session_id), &[user]),
+        program_error(ErrorCode::SessionNotPaused),
+    );
+    svm.warp_minutes(20);
+    svm.send_one(ix::pause_focus_session(&user, session_id), &[user])
+        .unwrap();
+    expect_err(
+        svm.send_one(ix::pause_focus_session(&user, session_id), &[user]),
+        program_error(ErrorCode::SessionPaused),
+    );
+
+    svm.warp_minutes(40);
+    expect_err(
+        svm.send_one(
+            ix::complete_focus_session(&user, &user, session_id, 0),
+            &[user],
+        ),
+        program_error(ErrorCode::SessionPaused),
+    );
+    svm.send_one(ix::resume_focus_session(&user, session_id), &[user])
+        .unwrap();
+
+    // only the 30 minute pause budget is discounted: 60 - 30 = 30 minutes focused
+    expect_err(
+        svm.send_one(
+            ix::complete_focus_session(&user, &user, session_id, 0),
+            &[user],
+        ),
+        program_error(ErrorCode::SessionNotComplete),
+    );
+    svm.warp_minutes(25);
+    svm.send_one(
+        ix::complete_focus_session(&user, &user, session_id, 0),
+        &[user],
+    )
+    .unwrap();
+
+    expect_err(
+        svm.send_one(ix::pause_focus_session(&user, session_id), &[user]),
+        program_error(ErrorCode::NoActiveSession),
+    );
+    expect_err(
+        svm.send_one(ix::resume_focus_session(&user, session_id), &[user]),
+        program_error(ErrorCode::NoActiveSession),
+    );
+}
+
+#[test]
+fn pauses_are_limited() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 60, &["a"], None);
+
+    for _ in 0..10 {
+        svm.send(
+            &[
+                ix::pause_focus_session(&user, session_id),
+                ix::resume_focus_session(&user, session_id),
+            ],
+            &[user],
+        )
+        .unwrap();
+    }
+    expect_err(
+        svm.send_one(ix::pause_focus_session(&user, session_id), &[user]),
+        program_error(ErrorCode::TooManyPauses),
+    );
+
+    let session_id = svm.start_session(&user, STAKE, 60, &["a"], None);
+    svm.send_one(ix::pause_focus_session(&user, session_id), &[user])
+        .unwrap();
+    svm.warp_minutes(30);
+    svm.send_one(ix::resume_focus_session(&user, session_id), &[user])
+        .unwrap();
+    expect_err(
+        svm.send_one(ix::pause_focus_session(&user, session_id), &[user]),
+        program_error(ErrorCode::PauseBudgetExhausted),
+    );
+}
+
+#[test]
+fn paused_sessions_still_expire_once_the_budget_runs_out() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+    svm.send_one(ix::pause_focus_session(&user, session_id), &[user])
+        .unwrap();
+
+    // 30 minute budget + 30 minute duration + 5 minute grace
+    svm.warp_minutes(64);
+    expect_err(
+        svm.send_one(
+            ix::expire_focus_session(&keeper, &user, &user, session_id),
+            &[keeper],
+        ),
+        program_error(ErrorCode::SessionNotComplete),
+    );
+    svm.warp_minutes(1);
+    svm.send_one(
+        ix::expire_focus_session(&keeper, &user, &user, session_id),
+        &[keeper],
+    )
+    .unwrap();
+    svm.assert_solvent();
+}
+
+#[test]
+fn authority_transfer_takes_two_steps() {
+    let (mut svm, authority) = setup();
+    let successor = Pubkey::new_unique();
+    let stranger = Pubkey::new_unique();
+
+    expect_err(
+        svm.send_one(
+            ix::propose_authority(&stranger, Some(stranger)),
+            &[stranger],
+        ),
+        anchor_error(AnchorErrorCode::RequireKeysEqViolated),
+    );
+    svm.send_one(
+        ix::propose_authority(&authority, Some(successor)),
+        &[authority],
+    )
+    .unwrap();
+    expect_err(
+        svm.send_one(ix::accept_authority(&stranger), &[stranger]),
+        program_error(ErrorCode::NotPendingAuthority),
+    );
+
+    svm.send_one(ix::accept_authority(&successor), &[successor])
+        .unwrap();
+    let global_state = svm.global_state();
+    assert_eq!(global_state.authority, successor);
+    assert_eq!(global_state.pending_authority, None);
+
+    expect_err(
+        svm.send_one(
+            ix::update_config(&authority, Config::default()),
+            &[authority],
+        ),
+        anchor_error(AnchorErrorCode::RequireKeysEqViolated),
+    );
+    expect_err(
+        svm.send_one(ix::accept_authority(&successor), &[successor]),
+        program_error(ErrorCode::NotPendingAuthority),
+    );
+}
+
+#[test]
+fn config_updates_are_validated() {
+    let (mut svm, authority) = setup();
+    let invalid = [
+        Config {
+            focus_pool_fee_bps: 1_001,
+            ..Config::default()
+        },
+        Config {
+            min_stake: 999_999,
+            ..Config::default()
+        },
+        Config {
+            max_duration_minutes: 0,
+            ..Config::default()
+        },
+        Config {
+            max_tasks: 21,
+            ..Config::default()
+        },
+        Config {
+            grace_minutes: 61,
+            ..Config::default()
+        },
+        Config {
+            max_pause_minutes: 241,
+            ..Config::default()
+        },
+        Config {
+            epoch_duration_seconds: 3_599,
+            ..Config::default()
+        },
+        Config {
+            keeper_bounty_bps: 1_001,
+            ..Config::default()
+        },
+        Config {
+            heartbeat_interval_seconds: 59,
+            ..Config::default()
+        },
+        Config {
+            heartbeat_interval_seconds: 3_601,
+            ..Config::default()
+        },
+        Config {
+            min_heartbeat_coverage_bps: 10_001,
+            ..Config::default()
+        },
+        Config {
+            challenge_rate_bps: 10_001,
+            heartbeat_interval_seconds: 60,
+            ..Config::default()
+        },
+        // spot-checks without heartbeats to draw them
+        Config {
+            challenge_rate_bps: 1,
+            ..Config::default()
+        },
+        Config {
+            challenge_window_seconds: 59,
+            ..Config::default()
+        },
+        Config {
+            min_attested_focus_bps: 10_001,
+            ..Config::default()
+        },
+        Config {
+            distraction_penalty_bps: 10_001,
+            ..Config::default()
+        },
+    ];
+    for config in invalid {
+        expect_err(
+            svm.send_one(ix::update_config(&authority, config), &[authority]),
+            program_error(ErrorCode::InvalidConfig),
+        );
+    }
+
+    // the program stamps its own layout version
+    let config = Config {
+        version: 0,
+        grace_minutes: 0,
+        keeper_bounty_bps: 1_000,
+        ..Config::default()
+    };
+    svm.send_one(ix::update_config(&authority, config.clone()), &[authority])
+        .unwrap();
+    assert!(
+        svm.global_state().config
+            == Config {
+                version: Config::default().version,
+                ..config
+            }
+    );
+
+    // and new settlements follow it
+    let user = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+    svm.warp_minutes(30);
+    let keeper_before = svm.balance(&keeper);
+    svm.send_one(
+        ix::expire_focus_session(&keeper, &user, &user, session_id),
+        &[keeper],
+    )
+    .unwrap();
+    assert_eq!(svm.balance(&keeper), keeper_before + LOCKED / 10);
+}
+
+#[test]
+fn migrate_config_upgrades_the_first_deployment() {
+    let mut svm = Svm::new();
+    let authority = Pubkey::new_unique();
+    let stranger = Pubkey::new_unique();
+    svm.airdrop(&authority, 10 * LAMPORTS_PER_SOL);
+    svm.airdrop(&stranger, LAMPORTS_PER_SOL);
+
+    // the first deployment, with one session still running, one completed but
+    // not claimed and one user who already claimed
+    let global_state = pda::global_state();
+    let bump =
+        |seed: &[u8]| Pubkey::find_program_address(&[seed, global_state.as_ref()], &deepwork::ID).1;
+    let legacy = GlobalStateV0 {
+        authority,
+        focus_pool: 2_000_000,
+        failure_pool: 3_000_000,
+        total_sessions: 12,
+        vault_bump: bump(b"vault"),
+        focus_pool_bump: bump(b"focus_pool_vault"),
+        failure_pool_bump: bump(b"failure_pool_vault"),
+    };
+    let (active, completed, claimed) = (
+        funded_user(&mut svm),
+        funded_user(&mut svm),
+        funded_user(&mut svm),
+    );
+    // half the running session's tasks are done and all of the completed one's
+    let done = |descriptions: &[&str], count: usize| {
+        let mut tasks = tasks_from(descriptions);
+        tasks
+            .iter_mut()
+            .take(count)
+            .for_each(|task| task.completed = true);
+        tasks
+    };
+    let legacy_users = [
+        UserStateV0 {
+            user: active,
+            is_active: true,
+            stake_amount: STAKE - STAKE / 100,
+            start_time: svm.now() - 10 * 60,
+            duration_minutes: 25,
+            pending_balance: 0,
+            tasks: done(&["a", "b"], 1),
+        },
+        UserStateV0 {
+            user: completed,
+            is_active: false,
+            stake_amount: 0,
+            start_time: svm.now() - 60 * 60,
+            duration_minutes: 30,
+            pending_balance: STAKE / 2,
+            tasks: done(&["c"], 1),
+        },
+        UserStateV0 {
+            user: claimed,
+            is_active: false,
+            stake_amount: 0,
+            start_time: svm.now() - 2 * 60 * 60,
+            duration_minutes: 30,
+            pending_balance: 0,
+            tasks: vec![],
+        },
+    ];
+    let mut write_legacy = |key: Pubkey, discriminator: &[u8], body: Vec<u8>, space: usize| {
+        let mut data = discriminator.to_vec();
+        data.extend(body);
+        data.resize(space, 0);
+        let lamports = svm.rent.minimum_balance(space);
+        svm.set_account(
+            key,
+            AccountState {
+                lamports,
+                data,
+                owner: deepwork::ID,
+                executable: false,
+            },
+        );
+        lamports
+    };
+    let legacy_lamports = write_legacy(
+        global_state,
+        GlobalState::DISCRIMINATOR,
+        legacy.try_to_vec().unwrap(),
+        8 + GlobalStateV0::INIT_SPACE,
+    );
+    let user_state_lamports = legacy_users
+        .iter()
+        .map(|user_state| {
+            write_legacy(
+                pda::user_state(&user_state.user),
+                UserState::DISCRIMINATOR,
+                user_state.try_to_vec().unwrap(),
+                8 + UserStateV0::INIT_SPACE,
+            )
+        })
+        .last()
+        .unwrap();
+    let owed = legacy_users[0].stake_amount + legacy_users[1].pending_balance;
+    for (vault, balance) in [
+        (pda::vault(), owed),
+        (pda::focus_pool_vault(), legacy.focus_pool),
+        (pda::failure_pool_vault(), legacy.failure_pool),
+    ] {
+        svm.set_account(
+            vault,
+            AccountState {
+                lamports: svm.rent.minimum_balance(0) + balance,
+                data: vec![],
+                owner: deepwork::ID,
+                executable: false,
+            },
+        );
+    }
+
+    expect_err(
+        svm.send_one(ix::migrate_config(&stranger), &[stranger]),
+        anchor_error(AnchorErrorCode::RequireKeysEqViolated),
+    );
+    let authority_before = svm.balance(&authority);
+    svm.send_one(ix::migrate_config(&authority), &[authority])
+        .unwrap();
+
+    let space = 8 + GlobalState::INIT_SPACE;
+    let rent = svm.rent.minimum_balance(space);
+    assert_eq!(svm.balance(&global_state), rent);
+    assert_eq!(
+        svm.balance(&authority),
+        authority_before - (rent - legacy_lamports)
+    );
+    let migrated = svm.global_state();
+    assert_eq!(migrated.authority, authority);
+    assert_eq!(migrated.pending_authority, None);
+    assert_eq!(migrated.focus_pool, legacy.focus_pool);
+    assert_eq!(migrated.failure_pool, legacy.failure_pool);
+    assert_eq!(migrated.total_sessions, 12);
+    assert_eq!(migrated.vault_bump, legacy.vault_bump);
+    assert!(migrated.refund_curve == RefundCurve::None);
+    assert!(migrated.config == Config::default());
+    assert_eq!(migrated.current_epoch, 0);
+    assert!(migrated.circuit_breaker == CircuitBreaker::default());
+    assert!(migrated.streak_tiers.is_empty());
+    assert_eq!(migrated.attestor, None);
+    // migrate_user_state later moves the completed session's share to pending
+    assert_eq!(migrated.total_active_stake, owed);
+    assert_eq!(migrated.total_pending_balance, 0);
+
+    // already current
+    expect_err(
+        svm.send_one(ix::migrate_config(&authority), &[authority]),
+        program_error(ErrorCode::UnknownStateLayout),
+    );
+
+    // initialize opens epoch 0 without touching the migrated state
+    svm.initialize(&authority);
+    assert_eq!(svm.global_state().focus_pool, legacy.focus_pool);
+    assert_eq!(svm.global_state().total_active_stake, owed);
+
+    // a user state in the old layout has to be migrated before starting again
+    expect_err(
+        svm.send_one(
+            ix::start_focus_session(
+                &active,
+                &active,
+                0,
+                STAKE,
+                25,
+                tasks_from(&["a"]),
+                None,
+                None,
+            ),
+            &[active],
+        ),
+        anchor_error(AnchorErrorCode::ConstraintSpace),
+    );
+
+    for user_state in &legacy_users {
+        let user = user_state.user;
+        let user_before = svm.balance(&user);
+        svm.send_one(ix::migrate_user_state(&stranger, &user), &[stranger])
+            .unwrap();
+        let user_state_space = 8 + UserState::INIT_SPACE;
+        assert_eq!(
+            svm.balance(&pda::user_state(&user)),
+            svm.rent.minimum_balance(user_state_space)
+        );
+        assert_eq!(
+            svm.balance(&user),
+            user_before + user_state_lamports - svm.rent.minimum_balance(user_state_space)
+        );
+        expect_err(
+            svm.send_one(ix::migrate_user_state(&stranger, &user), &[stranger]),
+            program_error(ErrorCode::UnknownStateLayout),
+        );
+    }
+    svm.assert_solvent();
+    let global_state = svm.global_state();
+    assert_eq!(
+        global_state.total_active_stake,
+        legacy_users[0].stake_amount
+    );
+    assert_eq!(
+        global_state.total_pending_balance,
+        legacy_users[1].pending_balance
+    );
+
+    // the running session carries on as session 0 and settles as usual
+    let session = svm.focus_session(&active, 0);
+    assert!(session.is_active);
+    assert_eq!(session.stake_amount, legacy_users[0].stake_amount);
+    assert_eq!(session.start_time, legacy_users[0].start_time);
+    assert_eq!(session.duration_minutes, 25);
+    assert_eq!(session.tasks.len(), 2);
+    assert_eq!(session.rent_payer, stranger);
+    assert_eq!(svm.next_session_id(&active), 1);
+    assert_eq!(svm.user_stats(&active).sessions_started, 1);
+    complete(&mut svm, &active, 0);
+    svm.assert_solvent();
+    let before = svm.balance(&active);
+    let failure_pool_before = svm.global_state().failure_pool;
+    svm.send_one(ix::claim_rewards(&active, &stranger, 0), &[active])
+        .unwrap();
+    let half = legacy_users[0].stake_amount / 2;
+    assert_eq!(svm.balance(&active), before + half);
+    assert_eq!(svm.global_state().failure_pool, failure_pool_before + half);
+
+    // the completed one only has its refund left to claim
+    let session = svm.focus_session(&completed, 0);
+    assert!(!session.is_active);
+    assert_eq!(session.pending_balance, legacy_users[1].pending_balance);
+    assert_eq!(svm.user_stats(&completed).sessions_completed, 1);
+    let before = svm.balance(&completed);
+    svm.send_one(ix::claim_rewards(&completed, &stranger, 0), &[completed])
+        .unwrap();
+    assert_eq!(
+        svm.balance(&completed),
+        before + legacy_users[1].pending_balance
+    );
+
+    // nothing was left of the claimed one, so its next session is 0
+    assert!(!svm.exists(&pda::focus_session(&claimed, 0)));
+    assert_eq!(svm.next_session_id(&claimed), 0);
+    let session_id = svm.start_session(&claimed, STAKE, 25, &["a"], None);
+    assert_eq!(session_id, 0);
+    complete(&mut svm, &claimed, session_id);
+    assert_eq!(svm.global_state().total_active_stake, 0);
+    svm.assert_solvent();
+}
+
+#[test]
+fn circuit_breaker_pauses_settlements_and_withdrawals() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+    complete(&mut svm, &user, session_id);
+    end_epoch(&mut svm, &authority);
+
+    let stranger = Pubkey::new_unique();
+    expect_err(
+        svm.send_one(
+            ix::set_circuit_breaker(&stranger, CircuitBreaker::default()),
+            &[stranger],
+        ),
+        anchor_error(AnchorErrorCode::RequireKeysEqViolated),
+    );
+
+    set_circuit_breaker(
+        &mut svm,
+        &authority,
+        CircuitBreaker {
+            settlements_paused: true,
+            withdrawals_paused: true,
+            ..CircuitBreaker::default()
+        },
+    );
+    expect_err(
+        svm.send_one(ix::claim_rewards(&user, &user, session_id), &[user]),
+        program_error(ErrorCode::SettlementsPaused),
+    );
+    expect_err(
+        svm.send_one(ix::claim_epoch_rewards(&user, 0), &[user]),
+        program_error(ErrorCode::SettlementsPaused),
+    );
+    expect_err(
+        svm.send_one(
+            ix::withdraw_focus_pool(&authority, &authority, 1),
+            &[authority],
+        ),
+        program_error(ErrorCode::WithdrawalsPaused),
+    );
+    expect_err(
+        svm.send_one(
+            ix::withdraw_failure_pool(&authority, &authority, 1),
+            &[authority],
+        ),
+        program_error(ErrorCode::WithdrawalsPaused),
+    );
+
+    set_circuit_breaker(&mut svm, &authority, CircuitBreaker::default());
+    svm.send_one(ix::claim_rewards(&user, &user, session_id), &[user])
+        .unwrap();
+    svm.send_one(
+        ix::withdraw_focus_pool(&authority, &authority, 1),
+        &[authority],
+    )
+    .unwrap();
+    svm.assert_solvent();
+}
+
+#[test]
+fn emergency_withdraw_returns_principal() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let active = svm.start_session(&user, STAKE, 60, &["a"], None);
+    let completed = svm.start_session(&user, 2 * STAKE, 30, &["a", "b"], None);
+    complete(&mut svm, &user, completed);
+
+    expect_err(
+        svm.send_one(ix::emergency_withdraw(&user, &user, active), &[user]),
+        program_error(ErrorCode::EmergencyWithdrawDisabled),
+    );
+
+    set_circuit_breaker(
+        &mut svm,
+        &authority,
+        CircuitBreaker {
+            new_sessions_paused: true,
+            ..CircuitBreaker::default()
+        },
+    );
+    for (session_id, principal) in [(active, LOCKED), (completed, 2 * LOCKED)] {
+        let session_key = pda::focus_session(&user, session_id);
+        let expected = svm.balance(&user) + principal + svm.balance(&session_key);
+        svm.send_one(ix::emergency_withdraw(&user, &user, session_id), &[user])
+            .unwrap();
+        assert_eq!(svm.balance(&user), expected);
+        assert!(!svm.exists(&session_key));
+        svm.assert_solvent();
+    }
+    assert_eq!(svm.balance(&pda::vault()), svm.rent.minimum_balance(0));
+    assert_eq!(svm.user_stats(&user).total_refunded, 3 * LOCKED);
+}
+
+#[test]
+fn emergency_withdraw_token_returns_principal() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let mint = Pubkey::new_unique();
+    let user_tokens = Pubkey::new_unique();
+    let token_program = anchor_spl::token::ID;
+    svm.create_mint(mint, 6);
+    svm.create_token_account(user_tokens, &mint, &user, 1_000_000_000);
+    svm.allow_mint(&authority, &mint, 1_000_000);
+
+    let session_id = svm.start_token_session(&user, &user_tokens, &mint, 100_000_000, 30, &["a"]);
+    let principal = svm.focus_session(&user, session_id).stake_amount;
+    let tokens_before = svm.token_balance(&user_tokens);
+    let stats_before = svm.user_stats(&user);
+    set_circuit_breaker(
+        &mut svm,
+        &authority,
+        CircuitBreaker {
+            new_sessions_paused: true,
+            ..CircuitBreaker::default()
+        },
+    );
+    svm.send_one(
+        ix::emergency_withdraw_token(
+            &user,
+            &user,
+            session_id,
+            &mint,
+            &user_tokens,
+            &token_program,
+        ),
+        &[user],
+    )
+    .unwrap();
+
+    assert_eq!(svm.token_balance(&user_tokens), tokens_before + principal);
+    assert_eq!(svm.token_balance(&pda::token_vault(&mint)), 0);
+    assert!(!svm.exists(&pda::focus_session(&user, session_id)));
+    // the stats stay in lamports, so token payouts leave the totals alone
+    let stats = svm.user_stats(&user);
+    assert_eq!(stats.total_refunded, stats_before.total_refunded);
+    assert_eq!(stats.total_forfeited, stats_before.total_forfeited);
+    svm.assert_solvent();
+}
+
+#[test]
+fn token_claims_record_user_stats() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let mint = Pubkey::new_unique();
+    let user_tokens = Pubkey::new_unique();
+    let token_program = anchor_spl::token::ID;
+    svm.create_mint(mint, 6);
+    svm.create_token_account(user_tokens, &mint, &user, 1_000_000_000);
+    svm.allow_mint(&authority, &mint, 1_000_000);
+
+    let session_id =
+        svm.start_token_session(&user, &user_tokens, &mint, 100_000_000, 30, &["a", "b"]);
+    let principal = svm.focus_session(&user, session_id).stake_amount;
+    complete(&mut svm, &user, session_id);
+    svm.send_one(
+        ix::update_task(&user, &user, session_id, None, 0, true),
+        &[user],
+    )
+    .unwrap();
+    // another user's stats account doesn't match the seeds
+    let other = funded_user(&mut svm);
+    svm.start_session(&other, STAKE, 30, &["a"], None);
+    let mut wrong_stats = ix::claim_rewards_token(
+        &user,
+        &user,
+        session_id,
+        &mint,
+        &user_tokens,
+        &token_program,
+    );
+    wrong_stats.accounts[2] = AccountMeta::new(pda::user_stats(&other), false);
+    expect_err(
+        svm.send_one(wrong_stats, &[user]),
+        anchor_error(AnchorErrorCode::ConstraintSeeds),
+    );
+
+    let tokens_before = svm.token_balance(&user_tokens);
+    let stats_before = svm.user_stats(&user);
+    svm.send_one(
+        ix::claim_rewards_token(
+            &user,
+            &user,
+            session_id,
+            &mint,
+            &user_tokens,
+            &token_program,
+        ),
+        &[user],
+    )
+    .unwrap();
+
+    assert_eq!(
+        svm.token_balance(&user_tokens),
+        tokens_before + principal / 2
+    );
+    assert_eq!(
+        svm.token_balance(&pda::token_failure_pool(&mint)),
+        principal - principal / 2
+    );
+    let stats = svm.user_stats(&user);
+    assert_eq!(stats.sessions_started, 1);
+    assert_eq!(stats.sessions_completed, 1);
+    assert_eq!(stats.minutes_focused, 30);
+    // token amounts aren't lamports, so the claim leaves the totals alone
+    assert_eq!(stats.total_staked, 0);
+    assert_eq!(stats.total_refunded, stats_before.total_refunded);
+    assert_eq!(stats.total_forfeited, stats_before.total_forfeited);
+    assert_eq!(stats.total_streak_bonus, 0);
+
+    svm.assert_solvent();
+}
+
+#[test]
+fn pool_withdrawals_stop_at_rent_and_reservations() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let recipient = Pubkey::new_unique();
+    let stranger = Pubkey::new_unique();
+
+    svm.start_session(&user, STAKE, 30, &["a"], None);
+    let fee = STAKE / 100;
+    expect_err(
+        svm.send_one(
+            ix::withdraw_focus_pool(&authority, &recipient, fee + 1),
+            &[authority],
+        ),
+        program_error(ErrorCode::MathError),
+    );
+    expect_err(
+        svm.send_one(
+            ix::withdraw_focus_pool(&stranger, &recipient, fee),
+            &[stranger],
+        ),
+        anchor_error(AnchorErrorCode::RequireKeysEqViolated),
+    );
+    svm.send_one(
+        ix::withdraw_focus_pool(&authority, &recipient, fee),
+        &[authority],
+    )
+    .unwrap();
+    assert_eq!(svm.balance(&recipient), fee);
+    assert_eq!(svm.global_state().focus_pool, 0);
+    svm.assert_solvent();
+
+    // a completed session earns the next failure's stake in epoch 0
+    let winner = funded_user(&mut svm);
+    let winner_session = svm.start_session(&winner, STAKE, 30, &["a"], None);
+    complete(&mut svm, &winner, winner_session);
+    svm.send_one(ix::fail_focus_session(&user, &user, 0), &[user])
+        .unwrap();
+    end_epoch(&mut svm, &authority);
+
+    let global_state = svm.global_state();
+    assert_eq!(global_state.failure_pool_reserved, LOCKED);
+    expect_err(
+        svm.send_one(
+            ix::withdraw_failure_pool(&authority, &recipient, 1),
+            &[authority],
+        ),
+        program_error(ErrorCode::PoolFundsReserved),
+    );
+    expect_err(
+        svm.send_one(
+            ix::withdraw_failure_pool(&authority, &recipient, LOCKED + 1),
+            &[authority],
+        ),
+        program_error(ErrorCode::MathError),
+    );
+}
+
+#[test]
+fn epoch_rewards_are_paid_pro_rata() {
+    let (mut svm, _) = setup();
+    let alice = funded_user(&mut svm);
+    let bob = funded_user(&mut svm);
+    let loser = funded_user(&mut svm);
+
+    // alice earns 3x bob's shares
+    let alice_session = svm.start_session(&alice, 3 * STAKE, 30, &["a"], None);
+    let bob_session = svm.start_session(&bob, STAKE, 30, &["a"], None);
+    let loser_session = svm.start_session(&loser, 4 * STAKE, 30, &["a"], None);
+    svm.send_one(
+        ix::fail_focus_session(&loser, &loser, loser_session),
+        &[loser],
+    )
+    .unwrap();
+    complete(&mut svm, &alice, alice_session);
+    complete(&mut svm, &bob, bob_session);
+
+    expect_err(
+        svm.send_one(ix::claim_epoch_rewards(&alice, 0), &[alice]),
+        program_error(ErrorCode::EpochNotClosed),
+    );
+    expect_err(
+        svm.send_one(ix::close_epoch(&alice, 0), &[alice]),
+        program_error(ErrorCode::EpochNotOver),
+    );
+
+    end_epoch(&mut svm, &alice);
+    let rewards = 4 * LOCKED;
+    assert_eq!(svm.get::<RewardEpoch>(&pda::epoch(0)).rewards, rewards);
+
+    for (user, share) in [(alice, rewards * 3 / 4), (bob, rewards / 4)] {
+        let share_key = pda::epoch_share(0, &user);
+        let expected = svm.balance(&user) + share + svm.balance(&share_key);
+        svm.send_one(ix::claim_epoch_rewards(&user, 0), &[user])
+            .unwrap();
+        assert_eq!(svm.balance(&user), expected);
+        assert!(!svm.exists(&share_key));
+        svm.assert_solvent();
+    }
+
+    let global_state = svm.global_state();
+    assert_eq!(global_state.failure_pool, 0);
+    assert_eq!(global_state.failure_pool_reserved, 0);
+    assert_eq!(
+        svm.get::<RewardEpoch>(&pda::epoch(0)).claimed_rewards,
+        rewards
+    );
+
+    // shares can only be claimed once
+    expect_err(
+        svm.send_one(ix::claim_epoch_rewards(&alice, 0), &[alice]),
+        anchor_error(AnchorErrorCode::AccountNotInitialized),
+    );
+}
+
+#[test]
+fn sol_instructions_reject_token_sessions() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+    let mint = Pubkey::new_unique();
+    let user_tokens = Pubkey::new_unique();
+    svm.create_mint(mint, 6);
+    svm.create_token_account(user_tokens, &mint, &user, 1_000_000_000);
+    svm.allow_mint(&authority, &mint, 1_000_000);
+
+    let session_id = svm.start_token_session(&user, &user_tokens, &mint, 100_000_000, 30, &["a"]);
+    svm.warp_minutes(40);
+    for (instruction, signer) in [
+        (ix::fail_focus_session(&user, &user, session_id), user),
+        (
+            ix::expire_focus_session(&keeper, &user, &user, session_id),
+            keeper,
+        ),
+        (
+            ix::complete_focus_session_v1(&user, &user, session_id),
+            user,
+        ),
+        (ix::emergency_withdraw(&user, &user, session_id), user),
+    ] {
+        expect_err(
+            svm.send_one(instruction, &[signer]),
+            program_error(ErrorCode::AssetMismatch),
+        );
+    }
+
+    complete(&mut svm, &user, session_id);
+    expect_err(
+        svm.send_one(ix::claim_rewards(&user, &user, session_id), &[user]),
+        program_error(ErrorCode::AssetMismatch),
+    );
+    // token sessions don't earn SOL epoch shares
+    assert_eq!(svm.get::<RewardEpoch>(&pda::epoch(0)).total_shares, 0);
+}
+
+#[test]
+fn token_instructions_check_session_state_and_breakers() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let keeper = funded_user(&mut svm);
+    let mint = Pubkey::new_unique();
+    let user_tokens = Pubkey::new_unique();
+    let keeper_tokens = Pubkey::new_unique();
+    svm.create_mint(mint, 6);
+    svm.create_token_account(user_tokens, &mint, &user, 1_000_000_000);
+    svm.create_token_account(keeper_tokens, &mint, &keeper, 0);
+    svm.allow_mint(&authority, &mint, 1_000_000);
+    let token_program = anchor_spl::token::ID;
+
+    let session_id = svm.start_token_session(&user, &user_tokens, &mint, 100_000_000, 30, &["a"]);
+    expect_err(
+        svm.send_one(
+            ix::claim_rewards_token(
+                &user,
+                &user,
+                session_id,
+                &mint,
+                &user_tokens,
+                &token_program,
+            ),
+            &[user],
+        ),
+        program_error(ErrorCode::SessionStillActive),
+    );
+    expect_err(
+        svm.send_one(
+            ix::expire_focus_session_token(
+                &keeper,
+                &keeper_tokens,
+                &user,
+                &user,
+                session_id,
+                &mint,
+                &token_program,
+            ),
+            &[keeper],
+        ),
+        program_error(ErrorCode::SessionNotComplete),
+    );
+    expect_err(
+        svm.send_one(
+            ix::emergency_withdraw_token(
+                &user,
+                &user,
+                session_id,
+                &mint,
+                &user_tokens,
+                &token_program,
+            ),
+            &[user],
+        ),
+        program_error(ErrorCode::EmergencyWithdrawDisabled),
+    );
+
+    complete(&mut svm, &user, session_id);
+    expect_err(
+        svm.send_one(
+            ix::fail_focus_session_token(
+                &user,
+                &user,
+                session_id,
+                &mint,
+                &user_tokens,
+                &token_program,
+            ),
+            &[user],
+        ),
+        program_error(ErrorCode::NoActiveSession),
+    );
+
+    set_circuit_breaker(
+        &mut svm,
+        &authority,
+        CircuitBreaker {
+            settlements_paused: true,
+            withdrawals_paused: true,
+            ..CircuitBreaker::default()
+        },
+    );
+    expect_err(
+        svm.send_one(
+            ix::claim_rewards_token(
+                &user,
+                &user,
+                session_id,
+                &mint,
+                &user_tokens,
+                &token_program,
+            ),
+            &[user],
+        ),
+        program_error(ErrorCode::SettlementsPaused),
+    );
+    let withdrawals = [
+        ix::withdraw_focus_pool_token(&authority, &mint, &user_tokens, &token_program, 1),
+        ix::withdraw_failure_pool_token(&authority, &mint, &user_tokens, &token_program, 1),
+    ];
+    for instruction in withdrawals.clone() {
+        expect_err(
+            svm.send_one(instruction, &[authority]),
+            program_error(ErrorCode::WithdrawalsPaused),
+        );
+    }
+
+    set_circuit_breaker(&mut svm, &authority, CircuitBreaker::default());
+    // the failure pool is empty and the focus pool only holds the fee
+    let fee = 1_000_000;
+    for instruction in [
+        ix::withdraw_focus_pool_token(&authority, &mint, &user_tokens, &token_program, fee + 1),
+        ix::withdraw_failure_pool_token(&authority, &mint, &user_tokens, &token_program, 1),
+    ] {
+        expect_err(
+            svm.send_one(instruction, &[authority]),
+            program_error(ErrorCode::MathError),
+        );
+    }
+}
+
+#[test]
+fn mint_config_updates_are_admin_only() {
+    let (mut svm, authority) = setup();
+    let mint = Pubkey::new_unique();
+    let stranger = Pubkey::new_unique();
+    svm.create_mint(mint, 6);
+    svm.allow_mint(&authority, &mint, 1_000_000);
+
+    expect_err(
+        svm.send_one(
+            ix::update_mint_config(&authority, &mint, true, 0),
+            &[authority],
+        ),
+        program_error(ErrorCode::InvalidConfig),
+    );
+    expect_err(
+        svm.send_one(
+            ix::update_mint_config(&stranger, &mint, false, 5),
+            &[stranger],
+        ),
+        anchor_error(AnchorErrorCode::RequireKeysEqViolated),
+    );
+
+    svm.send_one(
+        ix::update_mint_config(&authority, &mint, false, 5_000_000),
+        &[authority],
+    )
+    .unwrap();
+    let mint_config: deepwork_client::MintConfig = svm.get(&pda::mint_config(&mint));
+    assert!(!mint_config.enabled);
+    assert_eq!(mint_config.min_stake, 5_000_000);
+}
+
+#[test]
+fn token_sessions_need_an_enabled_supported_mint() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let token_program = anchor_spl::token_2022::ID;
+
+    for extension in [
+        ExtensionType::TransferFeeConfig,
+        ExtensionType::TransferHook,
+        ExtensionType::PermanentDelegate,
+        ExtensionType::Pausable,
+        ExtensionType::NonTransferable,
+        ExtensionType::MintCloseAuthority,
+        ExtensionType::DefaultAccountState,
+    ] {
+        let mint = Pubkey::new_unique();
+        svm.create_mint_2022(mint, 6, &[extension], |state| match extension {
+            ExtensionType::TransferFeeConfig => {
+                state.init_extension::<TransferFeeConfig>(true).unwrap();
+            }
+            ExtensionType::TransferHook => {
+                state.init_extension::<TransferHook>(true).unwrap();
+            }
+            ExtensionType::PermanentDelegate => {
+                state.init_extension::<PermanentDelegate>(true).unwrap();
+            }
+            ExtensionType::Pausable => {
+                state.init_extension::<PausableConfig>(true).unwrap();
+            }
+            ExtensionType::NonTransferable => {
+                state.init_extension::<NonTransferable>(true).unwrap();
+            }
+            ExtensionType::MintCloseAuthority => {
+                state.init_extension::<MintCloseAuthority>(true).unwrap();
+            }
+            _ => {
+                state
+                    .init_extension::<DefaultAccountState>(true)
+                    .unwrap()
+                    .state = TokenAccountState::Frozen as u8;
+            }
+        });
+        expect_err(
+            svm.send_one(
+                ix::allow_mint(&authority, &mint, &token_program, 1_000_000),
+                &[authority],
+            ),
+            program_error(ErrorCode::UnsupportedMint),
+        );
+    }
+
+    // accounts that merely default to unfrozen are fine
+    let thawed = Pubkey::new_unique();
+    svm.create_mint_2022(thawed, 6, &[ExtensionType::DefaultAccountState], |state| {
+        state
+            .init_extension::<DefaultAccountState>(true)
+            .unwrap()
+            .state = TokenAccountState::Initialized as u8;
+    });
+    svm.allow_mint(&authority, &thawed, 1_000_000);
+
+    let mint = Pubkey::new_unique();
+    let user_tokens = Pubkey::new_unique();
+    svm.create_mint(mint, 6);
+    svm.create_token_account(user_tokens, &mint, &user, 1_000_000_000);
+    svm.allow_mint(&authority, &mint, 1_000_000);
+    let start = |stake_amount| {
+        ix::start_focus_session_token(
+            &user,
+            &user,
+            0,
+            &mint,
+            &user_tokens,
+            &anchor_spl::token::ID,
+            stake_amount,
+            30,
+            tasks_from(&["a"]),
+            None,
+            None,
+        )
+    };
+
+    expect_err(
+        svm.send_one(start(999_999), &[user]),
+        program_error(ErrorCode::StakeTooLow),
+    );
+    svm.send_one(
+        ix::update_mint_config(&authority, &mint, false, 1_000_000),
+        &[authority],
+    )
+    .unwrap();
+    expect_err(
+        svm.send_one(start(1_000_000), &[user]),
+        program_error(ErrorCode::MintNotAllowed),
+    );
+    assert_eq!(svm.token_balance(&user_tokens), 1_000_000_000);
+
+    svm.send_one(
+        ix::update_mint_config(&authority, &mint, true, 1_000_000),
+        &[authority],
+    )
+    .unwrap();
+    svm.send_one(start(1_000_000), &[user]).unwrap();
+    assert_eq!(svm.token_balance(&user_tokens), 999_000_000);
+    assert_eq!(svm.token_balance(&pda::token_vault(&mint)), 990_000);
+    assert_eq!(svm.token_balance(&pda::token_focus_pool(&mint)), 10_000);
+}
+
+#[test]
+fn lamports_are_conserved_across_mixed_settlements() {
+    let (mut svm, authority) = setup();
+    let keeper = funded_user(&mut svm);
+    let users: Vec<Pubkey> = (0..6).map(|_| funded_user(&mut svm)).collect();
+    for (index, user) in users.iter().enumerate() {
+        svm.start_session(user, (index as u64 + 1) * STAKE, 30, &["a", "b", "c"], None);
+    }
+    svm.send_one(
+        ix::set_refund_curve(
+            &authority,
+            RefundCurve::Linear {
+                max_refund_bps: 8_000,
+            },
+        ),
+        &[authority],
+    )
+    .unwrap();
+    let total = svm.total_lamports();
+
+    svm.warp_minutes(10);
+    svm.send_one(ix::fail_focus_session(&users[0], &users[0], 0), &[users[0]])
+        .unwrap();
+    svm.warp_minutes(20);
+    for user in &users[1..4] {
+        svm.send_one(ix::complete_focus_session(user, user, 0, 0), &[*user])
+            .unwrap();
+    }
+    svm.send_one(
+        ix::update_task(&users[1], &users[1], 0, None, 0, true),
+        &[users[1]],
+    )
+    .unwrap();
+    svm.send_one(ix::claim_rewards(&users[1], &users[1], 0), &[users[1]])
+        .unwrap();
+    svm.send_one(
+        ix::complete_focus_session_v1(&users[4], &users[4], 0),
+        &[users[4]],
+    )
+    .unwrap();
+    svm.warp_minutes(5);
+    svm.send_one(
+        ix::expire_focus_sessions_batch(
+            &keeper,
+            &[(users[5], 0, users[5]), (users[2], 0, users[2])],
+        ),
+        &[keeper],
+    )
+    .unwrap();
+    svm.assert_solvent();
+
+    end_epoch(&mut svm, &keeper);
+    svm.send_one(ix::claim_epoch_rewards(&users[2], 0), &[users[2]])
+        .unwrap();
+    svm.send_one(ix::claim_rewards(&users[2], &users[2], 0), &[users[2]])
+        .unwrap();
+    svm.send_one(ix::claim_rewards(&users[3], &users[3], 0), &[users[3]])
+        .unwrap();
+    svm.send_one(ix::claim_epoch_rewards(&users[3], 0), &[users[3]])
+        .unwrap();
+    svm.assert_solvent();
+
+    // every session settled: the vault is back to its rent reserve
+    assert_eq!(svm.balance(&pda::vault()), svm.rent.minimum_balance(0));
+    assert_eq!(svm.total_lamports(), total);
+}
+
+#[test]
+fn reconcile_pauses_withdrawals_on_a_shortfall() {
+    let (mut svm, authority) = setup();
+    let user = funded_user(&mut svm);
+    let active = svm.start_session(&user, 2 * STAKE, 30, &["a"], None);
+    let completed = svm.start_session(&user, STAKE, 30, &["a"], None);
+    complete(&mut svm, &user, completed);
+
+    let global_state = svm.global_state();
+    assert_eq!(global_state.total_active_stake, 2 * LOCKED);
+    assert_eq!(global_state.total_pending_balance, LOCKED);
+
+    // anyone can reconcile, and a surplus is no reason to halt anything
+    svm.airdrop(&pda::failure_pool_vault(), LAMPORTS_PER_SOL);
+    let caller = funded_user(&mut svm);
+    svm.send_one(ix::reconcile(), &[caller]).unwrap();
+    assert!(!svm.global_state().circuit_breaker.withdrawals_paused);
+    let mut failure_pool_vault = svm.account(&pda::failure_pool_vault()).unwrap().clone();
+    failure_pool_vault.lamports -= LAMPORTS_PER_SOL;
+    svm.set_account(pda::failure_pool_vault(), failure_pool_vault);
+
+    // lamports leaking out of the vault behind the counters' back
+    let mut vault = svm.account(&pda::vault()).unwrap().clone();
+    vault.lamports -= 1;
+    svm.set_account(pda::vault(), vault);
+    svm.send_one(ix::reconcile(), &[caller]).unwrap();
+    assert!(svm.global_state().circuit_breaker.withdrawals_paused);
+    expect_err(
+        svm.send_one(
+            ix::withdraw_focus_pool(&authority, &authority, 1),
+            &[authority],
+        ),
+        program_error(ErrorCode::WithdrawalsPaused),
+    );
+
+    // settlements keep the counters in step with the vault
+    let mut vault = svm.account(&pda::vault()).unwrap().clone();
+    vault.lamports += 1;
+    svm.set_account(pda::vault(), vault);
+    svm.send_one(ix::claim_rewards(&user, &user, completed), &[user])
+        .unwrap();
+    svm.send_one(ix::fail_focus_session(&user, &user, active), &[user])
+        .unwrap();
+    let global_state = svm.global_state();
+    assert_eq!(global_state.total_active_stake, 0);
+    assert_eq!(global_state.total_pending_balance, 0);
+    svm.assert_solvent();
+}
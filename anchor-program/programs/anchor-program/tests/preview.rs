@@ -0,0 +1,262 @@
+//! Checks the client's payout previews against what the program actually pays.
+//!
+//! Each test reads the accounts a client would fetch, computes the preview, then
+//! runs the instruction and compares it with the balance changes.
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use deepwork_client::preview::{preview_claim, preview_early_exit, preview_epoch_reward};
+use deepwork_client::{
+    instruction as ix, pda, Config, EpochShare, RefundCurve, RefundStep, RewardEpoch, StreakTier,
+};
+
+const STAKE: u64 = LAMPORTS_PER_SOL;
+
+fn setup() -> (Svm, Pubkey) {
+    let mut svm = Svm::new();
+    let authority = Pubkey::new_unique();
+    svm.airdrop(&authority, 10 * LAMPORTS_PER_SOL);
+    svm.initialize(&authority);
+    (svm, authority)
+}
+
+fn funded_user(svm: &mut Svm) -> Pubkey {
+    let user = Pubkey::new_unique();
+    svm.airdrop(&user, 100 * LAMPORTS_PER_SOL);
+    user
+}
+
+fn set_fee(svm: &mut Svm, authority: &Pubkey, focus_pool_fee_bps: u16) {
+    let config = Config {
+        focus_pool_fee_bps,
+        ..svm.global_state().config
+    };
+    svm.send_one(ix::update_config(authority, config), &[*authority])
+        .unwrap();
+}
+
+fn complete(svm: &mut Svm, user: &Pubkey, session_id: u64) {
+    let session = svm.focus_session(user, session_id);
+    let end = session.start_time + session.duration_minutes as i64 * 60;
+    svm.warp((end - svm.now()).max(0));
+    let current_epoch = svm.global_state().current_epoch;
+    svm.send_one(
+        ix::complete_focus_session(user, user, session_id, current_epoch),
+        &[*user],
+    )
+    .unwrap();
+}
+
+/// Lamports the user and the session account hold together; claims and
+/// failures close the session to the user.
+fn user_total(svm: &Svm, user: &Pubkey, session_id: u64) -> u64 {
+    svm.balance(user) + svm.balance(&pda::focus_session(user, session_id))
+}
+
+#[test]
+fn early_exit_preview_matches_fail() {
+    let (mut svm, authority) = setup();
+    set_fee(&mut svm, &authority, 250);
+    let user = funded_user(&mut svm);
+    let curves = [
+        RefundCurve::None,
+        RefundCurve::Linear {
+            max_refund_bps: 8_000,
+        },
+        RefundCurve::Stepped {
+            steps: vec![
+                RefundStep {
+                    min_elapsed_bps: 2_000,
+                    refund_bps: 1_500,
+                },
+                RefundStep {
+                    min_elapsed_bps: 6_000,
+                    refund_bps: 4_500,
+                },
+            ],
+        },
+    ];
+    for curve in curves {
+        svm.send_one(
+            ix::set_refund_curve(&authority, curve.clone()),
+            &[authority],
+        )
+        .unwrap();
+        for elapsed_minutes in [0, 7, 25, 41, 59] {
+            let session_id = svm.start_session(&user, STAKE, 60, &["a"], None);
+            svm.warp_minutes(elapsed_minutes);
+
+            let session = svm.focus_session(&user, session_id);
+            // the fee went to the focus pool at start
+            assert_eq!(session.stake_amount, STAKE - STAKE * 250 / 10_000);
+            let refund = preview_early_exit(&session, &curve, svm.now()).unwrap();
+            let before = user_total(&svm, &user, session_id);
+            let failure_pool_before = svm.global_state().failure_pool;
+            svm.send_one(ix::fail_focus_session(&user, &user, session_id), &[user])
+                .unwrap();
+
+            assert_eq!(svm.balance(&user), before + refund);
+            assert_eq!(
+                svm.global_state().failure_pool,
+                failure_pool_before + session.stake_amount - refund
+            );
+        }
+    }
+    svm.assert_solvent();
+}
+
+#[test]
+fn claim_preview_matches_claim_rewards() {
+    let (mut svm, authority) = setup();
+    set_fee(&mut svm, &authority, 300);
+    let tiers = vec![
+        StreakTier {
+            min_streak: 2,
+            bonus_bps: 150,
+        },
+        StreakTier {
+            min_streak: 3,
+            bonus_bps: 700,
+        },
+    ];
+    svm.send_one(ix::set_streak_tiers(&authority, tiers), &[authority])
+        .unwrap();
+    let user = funded_user(&mut svm);
+
+    // a growing streak with a varying share of tasks done
+    for (completed_tasks, streak_bonus_bps) in [(1, 0), (3, 150), (2, 700), (0, 700)] {
+        let session_id = svm.start_session(&user, STAKE, 30, &["a", "b", "c"], None);
+        complete(&mut svm, &user, session_id);
+        for task_index in 0..completed_tasks {
+            svm.send_one(
+                ix::update_task(&user, &user, session_id, None, task_index, true),
+                &[user],
+            )
+            .unwrap();
+        }
+
+        let session = svm.focus_session(&user, session_id);
+        assert_eq!(session.streak_bonus_bps, streak_bonus_bps);
+        let global_state = svm.global_state();
+        let preview = preview_claim(&session, global_state.focus_pool).unwrap();
+        assert_eq!(preview.completed_tasks, completed_tasks);
+        assert_eq!(preview.total_tasks, 3);
+        assert_eq!(
+            preview.refund_amount + preview.penalty_amount,
+            STAKE - STAKE * 300 / 10_000
+        );
+
+        let before = user_total(&svm, &user, session_id);
+        svm.send_one(ix::claim_rewards(&user, &user, session_id), &[user])
+            .unwrap();
+        assert_eq!(
+            svm.balance(&user),
+            before + preview.refund_amount + preview.streak_bonus
+        );
+        let after = svm.global_state();
+        assert_eq!(
+            after.failure_pool,
+            global_state.failure_pool + preview.penalty_amount
+        );
+        assert_eq!(
+            after.focus_pool,
+            global_state.focus_pool - preview.streak_bonus
+        );
+    }
+    svm.assert_solvent();
+}
+
+#[test]
+fn claim_preview_needs_a_completed_session() {
+    let (mut svm, _) = setup();
+    let user = funded_user(&mut svm);
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+
+    // nothing is pending until the session completes
+    let session = svm.focus_session(&user, session_id);
+    let err = preview_claim(&session, svm.global_state().focus_pool).unwrap_err();
+    assert_eq!(err, deepwork::ErrorCode::NoPendingBalance.into());
+
+    complete(&mut svm, &user, session_id);
+    let session = svm.focus_session(&user, session_id);
+    let preview = preview_claim(&session, svm.global_state().focus_pool).unwrap();
+    assert_eq!(preview.penalty_amount, session.pending_balance);
+}
+
+#[test]
+fn claim_preview_caps_the_bonus_at_the_focus_pool() {
+    let (mut svm, authority) = setup();
+    set_fee(&mut svm, &authority, 0);
+    let tiers = vec![StreakTier {
+        min_streak: 1,
+        bonus_bps: 2_000,
+    }];
+    svm.send_one(ix::set_streak_tiers(&authority, tiers), &[authority])
+        .unwrap();
+    let user = funded_user(&mut svm);
+
+    // with no fees the focus pool is empty, so there is no bonus to pay
+    let session_id = svm.start_session(&user, STAKE, 30, &["a"], None);
+    complete(&mut svm, &user, session_id);
+    svm.send_one(
+        ix::update_task(&user, &user, session_id, None, 0, true),
+        &[user],
+    )
+    .unwrap();
+    let session = svm.focus_session(&user, session_id);
+    assert_eq!(session.streak_bonus_bps, 2_000);
+    let preview = preview_claim(&session, svm.global_state().focus_pool).unwrap();
+    assert_eq!(preview.streak_bonus, 0);
+
+    let before = user_total(&svm, &user, session_id);
+    svm.send_one(ix::claim_rewards(&user, &user, session_id), &[user])
+        .unwrap();
+    assert_eq!(svm.balance(&user), before + preview.refund_amount);
+    svm.assert_solvent();
+}
+
+#[test]
+fn epoch_reward_preview_matches_claim_epoch_rewards() {
+    let (mut svm, authority) = setup();
+    let (alice, bob, quitter) = (
+        funded_user(&mut svm),
+        funded_user(&mut svm),
+        funded_user(&mut svm),
+    );
+    let alice_session = svm.start_session(&alice, STAKE, 45, &["a"], None);
+    let bob_session = svm.start_session(&bob, 2 * STAKE, 30, &["a"], None);
+    let failed = svm.start_session(&quitter, 3 * STAKE, 60, &["a"], None);
+    svm.send_one(
+        ix::fail_focus_session(&quitter, &quitter, failed),
+        &[quitter],
+    )
+    .unwrap();
+    complete(&mut svm, &alice, alice_session);
+    complete(&mut svm, &bob, bob_session);
+
+    // nothing to claim until the epoch closes
+    let epoch: RewardEpoch = svm.get(&pda::epoch(0));
+    let share: EpochShare = svm.get(&pda::epoch_share(0, &alice));
+    assert_eq!(preview_epoch_reward(&epoch, &share).unwrap(), 0);
+
+    let epoch_end = epoch.start_time + svm.global_state().config.epoch_duration_seconds;
+    svm.warp((epoch_end - svm.now()).max(0));
+    svm.close_epoch(&authority);
+    let epoch: RewardEpoch = svm.get(&pda::epoch(0));
+    assert!(epoch.rewards > 0);
+    let mut paid = 0;
+    for user in [alice, bob] {
+        let share_key = pda::epoch_share(0, &user);
+        let share: EpochShare = svm.get(&share_key);
+        let reward = preview_epoch_reward(&epoch, &share).unwrap();
+        let before = svm.balance(&user) + svm.balance(&share_key);
+        svm.send_one(ix::claim_epoch_rewards(&user, 0), &[user])
+            .unwrap();
+        assert_eq!(svm.balance(&user), before + reward);
+        paid += reward;
+    }
+    assert!(paid <= epoch.rewards);
+    svm.assert_solvent();
+}
@@ -39,6 +39,10 @@ async function initializeGlobalState() {
           [Buffer.from('failure_pool_vault'), globalStatePDA.toBuffer()],
           PROGRAM_ID
         )[0],
+        epoch: PublicKey.findProgramAddressSync(
+          [Buffer.from('epoch'), Buffer.alloc(8)],
+          PROGRAM_ID
+        )[0],
         authority: provider.wallet.publicKey,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
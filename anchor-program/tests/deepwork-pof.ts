@@ -13,6 +13,27 @@ describe("deepwork - PoF flows", () => {
   let focusPoolPda: anchor.web3.PublicKey;
   let failurePoolPda: anchor.web3.PublicKey;
 
+  // first session of a fresh user has session_id 0
+  const findSessionPdas = (user: anchor.web3.PublicKey, sessionId = 0) => {
+    const [userStatePda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("user_state"), user.toBuffer()],
+      program.programId
+    );
+    const [userStatsPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("user_stats"), user.toBuffer()],
+      program.programId
+    );
+    const [focusSessionPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("focus_session"),
+        user.toBuffer(),
+        new anchor.BN(sessionId).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    return { userStatePda, userStatsPda, focusSessionPda };
+  };
+
   const getLamports = async (pk: anchor.web3.PublicKey) => {
     const info = await provider.connection.getAccountInfo(pk);
     return info?.lamports ?? 0;
@@ -49,6 +70,10 @@ describe("deepwork - PoF flows", () => {
         vault: vaultPda,
         focusPoolVault: focusPoolPda,
         failurePoolVault: failurePoolPda,
+        epoch: anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("epoch"), new anchor.BN(0).toArrayLike(Buffer, "le", 8)],
+          program.programId
+        )[0],
         authority: authority.publicKey,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -60,10 +85,8 @@ describe("deepwork - PoF flows", () => {
 
   it("start splits 1% to focus pool and 99% to vault", async () => {
     const user = anchor.web3.Keypair.generate();
-    const [userStatePda] = anchor.web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("user_state"), user.publicKey.toBuffer()],
-      program.programId
-    );
+    const { userStatePda, userStatsPda, focusSessionPda } =
+      findSessionPdas(user.publicKey);
 
     await provider.connection
       .requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL)
@@ -76,21 +99,28 @@ describe("deepwork - PoF flows", () => {
       .startFocusSession(
         new anchor.BN(100_000_000),
         new anchor.BN(25),
-        [{ description: "Task 1", completed: false } as any]
+        [{ description: "Task 1", completed: false } as any],
+        null,
+        null
       )
       .accounts({
         userState: userStatePda,
+        focusSession: focusSessionPda,
+        userStats: userStatsPda,
         globalState: globalStatePda,
         vault: vaultPda,
         focusPoolVault: focusPoolPda,
         user: user.publicKey,
+        payer: user.publicKey,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([user])
       .rpc();
 
-    const userState = await program.account.userState.fetch(userStatePda);
-    assert.equal(userState.isActive, true);
+    const focusSession = await program.account.focusSession.fetch(
+      focusSessionPda
+    );
+    assert.equal(focusSession.isActive, true);
 
     const postVaultLamports = await getLamports(vaultPda);
     const postFocusLamports = await getLamports(focusPoolPda);
@@ -106,12 +136,10 @@ describe("deepwork - PoF flows", () => {
     );
   });
 
-  it("completes a focus session and returns 99% to user (closes user_state)", async () => {
+  it("completes a focus session and returns 99% to user (closes focus_session)", async () => {
     const user = anchor.web3.Keypair.generate();
-    const [userStatePda] = anchor.web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("user_state"), user.publicKey.toBuffer()],
-      program.programId
-    );
+    const { userStatePda, userStatsPda, focusSessionPda } =
+      findSessionPdas(user.publicKey);
 
     await provider.connection
       .requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL)
@@ -121,14 +149,19 @@ describe("deepwork - PoF flows", () => {
       .startFocusSession(
         new anchor.BN(100_000_000),
         new anchor.BN(1),
-        [{ description: "Task 1", completed: false } as any]
+        [{ description: "Task 1", completed: false } as any],
+        null,
+        null
       ) // 1 minute; grace allows immediate completion
       .accounts({
         userState: userStatePda,
+        focusSession: focusSessionPda,
+        userStats: userStatsPda,
         globalState: globalStatePda,
         vault: vaultPda,
         focusPoolVault: focusPoolPda,
         user: user.publicKey,
+        payer: user.publicKey,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([user])
@@ -139,7 +172,9 @@ describe("deepwork - PoF flows", () => {
     await program.methods
       .completeFocusSessionV1()
       .accounts({
-        userState: userStatePda,
+        focusSession: focusSessionPda,
+        rentPayer: user.publicKey,
+        userStats: userStatsPda,
         globalState: globalStatePda,
         vault: vaultPda,
         user: user.publicKey,
@@ -155,19 +190,17 @@ describe("deepwork - PoF flows", () => {
       "vault should pay back 99%"
     );
 
-    // user_state closed on completion; fetching should fail
+    // focus_session closed on completion; fetching should fail
     try {
-      await program.account.userState.fetch(userStatePda);
-      assert.fail("user_state should be closed");
+      await program.account.focusSession.fetch(focusSessionPda);
+      assert.fail("focus_session should be closed");
     } catch { }
   });
 
-  it("fails a focus session and routes 99% to failure pool (closes user_state)", async () => {
+  it("fails a focus session and routes 99% to failure pool (closes focus_session)", async () => {
     const user = anchor.web3.Keypair.generate();
-    const [userStatePda] = anchor.web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("user_state"), user.publicKey.toBuffer()],
-      program.programId
-    );
+    const { userStatePda, userStatsPda, focusSessionPda } =
+      findSessionPdas(user.publicKey);
 
     await provider.connection
       .requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL)
@@ -177,14 +210,19 @@ describe("deepwork - PoF flows", () => {
       .startFocusSession(
         new anchor.BN(100_000_000),
         new anchor.BN(25),
-        [{ description: "Task 1", completed: false } as any]
+        [{ description: "Task 1", completed: false } as any],
+        null,
+        null
       )
       .accounts({
         userState: userStatePda,
+        focusSession: focusSessionPda,
+        userStats: userStatsPda,
         globalState: globalStatePda,
         vault: vaultPda,
         focusPoolVault: focusPoolPda,
         user: user.publicKey,
+        payer: user.publicKey,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .signers([user])
@@ -195,7 +233,9 @@ describe("deepwork - PoF flows", () => {
     await program.methods
       .failFocusSession()
       .accounts({
-        userState: userStatePda,
+        focusSession: focusSessionPda,
+        rentPayer: user.publicKey,
+        userStats: userStatsPda,
         globalState: globalStatePda,
         vault: vaultPda,
         failurePoolVault: failurePoolPda,
@@ -213,8 +253,8 @@ describe("deepwork - PoF flows", () => {
     );
 
     try {
-      await program.account.userState.fetch(userStatePda);
-      assert.fail("user_state should be closed");
+      await program.account.focusSession.fetch(focusSessionPda);
+      assert.fail("focus_session should be closed");
     } catch { }
   });
 